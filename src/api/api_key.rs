@@ -0,0 +1,527 @@
+use super::{APIError, APIResponse, HttpResponseExt};
+use crate::{config::API_KEY_CONFIG, global::get_timestamp};
+use actix_web::{
+    body::EitherBody,
+    dev::{Service, ServiceRequest, ServiceResponse},
+    get,
+    http::{
+        header::{self, HeaderValue},
+        StatusCode,
+    },
+    web, Error, HttpRequest, HttpResponse, Responder,
+};
+use futures_util::future::{ready, LocalBoxFuture};
+use log::info;
+use rocksdb::DB;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::HashMap,
+    sync::{Mutex, OnceLock},
+    time::{Duration, Instant},
+};
+use utoipa::ToSchema;
+
+pub const API_KEY_HEADER: &'static str = "X-API-Key";
+pub const API_KEY_QUERY: &'static str = "api_key";
+pub const KEY_API_USAGE: &'static str = "api_usage";
+const DAY_SECONDS: u64 = 86400;
+
+/// Paths which are neither limited nor counted.
+const UNLIMITED_PATHS: [&'static str; 4] = ["/admin/", "/metrics", "/healthz", "/readyz"];
+/// Failed admin token checks per client ip, one every 10 seconds after 5 in a row.
+const ADMIN_AUTH_LIMIT: RateLimit = RateLimit { rate: 0.1, burst: 5.0 };
+
+lazy_static! {
+    pub static ref API_KEYS: ApiKeyConfig = load_api_keys();
+    /// Token buckets by `key:<name>` or `ip:<address>`, replicas do not share them.
+    static ref BUCKETS: Mutex<HashMap<String, TokenBucket>> = Mutex::new(HashMap::new());
+    static ref USAGE: Usage = Usage::default();
+}
+
+/// Set by `usage_open`, the counts are kept in memory only without it.
+static USAGE_DB: OnceLock<DB> = OnceLock::new();
+
+/// A token bucket refilled with `rate` requests per second up to `burst`, no limit when `rate` is 0.
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct RateLimit {
+    pub rate: f64,
+    pub burst: f64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ApiKey {
+    pub key: String,
+    /// shown in the usage, the key itself is never returned
+    pub name: String,
+    #[serde(flatten)]
+    pub limit: RateLimit,
+    /// requests per UTC day, no quota when 0
+    #[serde(default)]
+    pub daily_quota: u64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ApiKeyConfig {
+    /// limit per client ip of the requests without a key, they are refused when unset
+    pub anonymous: Option<RateLimit>,
+    /// read the client ip from `Forwarded` / `X-Forwarded-For`, only behind a proxy which sets them
+    #[serde(default)]
+    pub trust_proxy: bool,
+    #[serde(default = "default_usage_path")]
+    pub usage_path: String,
+    #[serde(default)]
+    pub keys: Vec<ApiKey>,
+}
+
+fn default_usage_path() -> String {
+    "./.api_usage".to_string()
+}
+
+fn load_api_keys() -> ApiKeyConfig {
+    if API_KEY_CONFIG.is_empty() {
+        return ApiKeyConfig {
+            anonymous: None,
+            trust_proxy: false,
+            usage_path: default_usage_path(),
+            keys: vec![],
+        };
+    }
+
+    let data = std::fs::read_to_string(API_KEY_CONFIG.as_str())
+        .unwrap_or_else(|_| panic!("api key config not found: {}", *API_KEY_CONFIG));
+    serde_json::from_str(&data).unwrap()
+}
+
+pub fn api_keys_enabled() -> bool {
+    !API_KEY_CONFIG.is_empty()
+}
+
+struct TokenBucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl TokenBucket {
+    fn refill(&mut self, limit: &RateLimit, now: Instant) {
+        let elapsed = now.duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * limit.rate).min(limit.burst);
+        self.updated = now;
+    }
+
+    /// Err with the seconds until there are `cost` tokens, at most a full bucket.
+    fn wait(&mut self, limit: &RateLimit, now: Instant, cost: u64) -> Result<f64, u64> {
+        self.refill(limit, now);
+        let cost = (cost as f64).min(limit.burst);
+        if self.tokens >= cost {
+            Ok(cost)
+        } else {
+            Err(((cost - self.tokens) / limit.rate).ceil() as u64)
+        }
+    }
+
+    /// Take `cost` tokens, at most a full bucket, Err with the seconds until there are enough.
+    fn take(&mut self, limit: &RateLimit, now: Instant, cost: u64) -> Result<(), u64> {
+        let cost = self.wait(limit, now, cost)?;
+        self.tokens -= cost;
+        Ok(())
+    }
+}
+
+fn bucket_update<F>(id: String, limit: &RateLimit, f: F) -> Result<(), u64>
+where
+    F: FnOnce(&mut TokenBucket, Instant) -> Result<(), u64>,
+{
+    if limit.rate <= 0.0 {
+        return Ok(());
+    }
+
+    let now = Instant::now();
+    let mut buckets = BUCKETS.lock().unwrap();
+    let bucket = buckets.entry(id).or_insert(TokenBucket {
+        tokens: limit.burst,
+        updated: now,
+    });
+    f(bucket, now)
+}
+
+fn bucket_take(id: String, limit: &RateLimit, cost: u64) -> Result<(), u64> {
+    bucket_update(id, limit, |bucket, now| bucket.take(limit, now, cost))
+}
+
+fn usage_key(day: u64, name: &str) -> String {
+    format!("{}:{:012}:{}", KEY_API_USAGE, day, name)
+}
+
+/// Start of the current UTC day.
+fn usage_today() -> u64 {
+    get_timestamp() / DAY_SECONDS * DAY_SECONDS
+}
+
+/// Start of the UTC day `days_ago` days before today.
+pub fn usage_day(days_ago: u64) -> u64 {
+    usage_today().saturating_sub(days_ago * DAY_SECONDS)
+}
+
+/// Open the usage db at `usage_path`, before the first request is counted.
+pub fn usage_open() -> Result<(), rocksdb::Error> {
+    if !api_keys_enabled() {
+        return Ok(());
+    }
+
+    let db = DB::open_default(API_KEYS.usage_path.as_str())?;
+    info!("[api_key] usage db: {}", API_KEYS.usage_path);
+    USAGE.load(&db, usage_today(), &API_KEYS.keys);
+    let _ = USAGE_DB.set(db);
+    Ok(())
+}
+
+fn usage_db_get(db: Option<&DB>, day: u64, name: &str) -> u64 {
+    let data = match db {
+        Some(db) => db.get(usage_key(day, name)).unwrap(),
+        None => None,
+    };
+    match data {
+        Some(data) => u64::from_be_bytes(data.as_slice().try_into().unwrap()),
+        None => 0,
+    }
+}
+
+/// Requests by day and key name, written to the usage db by `usage_flush`.
+#[derive(Default)]
+struct Usage {
+    counts: Mutex<HashMap<(u64, String), u64>>,
+}
+
+impl Usage {
+    /// Add the counts of the day stored in the db to the ones counted before it was opened.
+    fn load(&self, db: &DB, day: u64, keys: &[ApiKey]) {
+        let mut counts = self.counts.lock().unwrap();
+        for key in keys {
+            *counts.entry((day, key.name.clone())).or_insert(0) += usage_db_get(Some(db), day, &key.name);
+        }
+    }
+
+    /// Requests of a key on a day, counting the ones not flushed yet.
+    fn get(&self, db: Option<&DB>, day: u64, name: &str) -> u64 {
+        match self.counts.lock().unwrap().get(&(day, name.to_string())) {
+            Some(count) => *count,
+            None => usage_db_get(db, day, name),
+        }
+    }
+
+    /// Count `cost` requests of the key, Err when they exceed its daily quota.
+    fn add(&self, key: &ApiKey, day: u64, cost: u64) -> Result<(), ()> {
+        let mut counts = self.counts.lock().unwrap();
+        let count = counts.entry((day, key.name.clone())).or_insert(0);
+        if key.daily_quota > 0 && *count + cost > key.daily_quota {
+            return Err(());
+        }
+        *count += cost;
+        Ok(())
+    }
+
+    /// Write the counts to the db without holding the lock, the flushed ones of past days are dropped from memory.
+    fn flush(&self, db: Option<&DB>, today: u64) {
+        let flushed = self.counts.lock().unwrap().clone();
+        if let Some(db) = db {
+            for ((day, name), count) in flushed.iter() {
+                db.put(usage_key(*day, name), count.to_be_bytes()).unwrap();
+            }
+        }
+        self.counts
+            .lock()
+            .unwrap()
+            .retain(|key, count| key.0 == today || flushed.get(key) != Some(count));
+    }
+}
+
+/// Requests of a key on a day, counting the ones not flushed yet.
+pub fn usage_get(day: u64, name: &str) -> u64 {
+    USAGE.get(USAGE_DB.get(), day, name)
+}
+
+/// Write the counts to the usage db, the ones of past days are dropped from memory.
+pub async fn usage_flush() {
+    loop {
+        tokio::time::sleep(Duration::from_secs(10)).await;
+
+        USAGE.flush(USAGE_DB.get(), usage_today());
+
+        // buckets which refilled are the same as new ones
+        let now = Instant::now();
+        BUCKETS.lock().unwrap().retain(|id, bucket| {
+            let limit = if let Some(name) = id.strip_prefix("key:") {
+                API_KEYS.keys.iter().find(|key| key.name == name).map(|key| key.limit)
+            } else if id.starts_with("admin:") {
+                Some(ADMIN_AUTH_LIMIT)
+            } else {
+                API_KEYS.anonymous
+            };
+            match limit {
+                Some(limit) => {
+                    bucket.refill(&limit, now);
+                    bucket.tokens < limit.burst
+                }
+                None => false,
+            }
+        });
+    }
+}
+
+/// The key of a request, from the `X-API-Key` header or the `api_key` query param.
+fn request_key(req: &HttpRequest) -> Option<String> {
+    if let Some(value) = req.headers().get(API_KEY_HEADER) {
+        return value.to_str().ok().map(|key| key.to_string());
+    }
+
+    let query = web::Query::<HashMap<String, String>>::from_query(req.query_string()).ok()?;
+    query.get(API_KEY_QUERY).cloned()
+}
+
+fn request_api_key(req: &HttpRequest) -> Result<Option<&'static ApiKey>, ()> {
+    let key = match request_key(req) {
+        Some(key) => key,
+        None => return Ok(None),
+    };
+
+    // compare digests so the time taken does not depend on how much of a key matches
+    let digest = Sha256::digest(key);
+    API_KEYS
+        .keys
+        .iter()
+        .find(|item| Sha256::digest(&item.key) == digest)
+        .map(Some)
+        .ok_or(())
+}
+
+fn response_status(status: StatusCode, error: &str) -> HttpResponse {
+    HttpResponse::build(status).json(APIError {
+        error_code: status.as_u16() as u64,
+        error: error.to_string(),
+    })
+}
+
+fn response_too_many(error: &str, retry_after: u64) -> HttpResponse {
+    let mut response = response_status(StatusCode::TOO_MANY_REQUESTS, error);
+    response
+        .headers_mut()
+        .insert(header::RETRY_AFTER, HeaderValue::from(retry_after.max(1)));
+    response
+}
+
+fn client_ip(req: &HttpRequest) -> String {
+    let ip = if API_KEYS.trust_proxy {
+        req.connection_info().realip_remote_addr().map(|addr| addr.to_string())
+    } else {
+        req.peer_addr().map(|addr| addr.ip().to_string())
+    };
+    ip.unwrap_or_default()
+}
+
+/// The response refusing an admin token check of a client ip with too many failed ones.
+pub fn admin_auth_check(req: &HttpRequest) -> Option<HttpResponse> {
+    let result = bucket_update(format!("admin:{}", client_ip(req)), &ADMIN_AUTH_LIMIT, |bucket, now| {
+        bucket.wait(&ADMIN_AUTH_LIMIT, now, 1).map(|_| ())
+    });
+    result
+        .err()
+        .map(|retry_after| response_too_many("too many failed admin token checks", retry_after))
+}
+
+/// Count a failed admin token check of the client ip.
+pub fn admin_auth_failed(req: &HttpRequest) {
+    let _ = bucket_take(format!("admin:{}", client_ip(req)), &ADMIN_AUTH_LIMIT, 1);
+}
+
+/// The response refusing the request, None when it may pass. It costs `cost` tokens and requests of the quota.
+pub fn check(req: &HttpRequest, cost: u64) -> Option<HttpResponse> {
+    if !api_keys_enabled() || UNLIMITED_PATHS.iter().any(|path| req.path().starts_with(path)) {
+        return None;
+    }
+
+    let api_key = match request_api_key(req) {
+        Ok(api_key) => api_key,
+        Err(_) => return Some(response_status(StatusCode::UNAUTHORIZED, "invalid api key")),
+    };
+
+    let result = match api_key {
+        Some(api_key) => bucket_take(format!("key:{}", api_key.name), &api_key.limit, cost),
+        None => {
+            let limit = match &API_KEYS.anonymous {
+                Some(limit) => limit,
+                None => return Some(response_status(StatusCode::UNAUTHORIZED, "api key required")),
+            };
+            bucket_take(format!("ip:{}", client_ip(req)), limit, cost)
+        }
+    };
+
+    if let Err(retry_after) = result {
+        return Some(response_too_many("rate limited", retry_after));
+    }
+
+    if let Some(api_key) = api_key {
+        if USAGE.add(api_key, usage_today(), cost).is_err() {
+            let retry_after = usage_today() + DAY_SECONDS - get_timestamp();
+            return Some(response_too_many("daily quota exceeded", retry_after));
+        }
+    }
+    None
+}
+
+/// Rate limits and usage counting of the public routes, wrapped around the app with `wrap_fn`.
+pub fn limit<S, B>(req: ServiceRequest, srv: &S) -> LocalBoxFuture<'static, Result<ServiceResponse<EitherBody<B>>, Error>>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    if let Some(response) = check(req.request(), 1) {
+        return Box::pin(ready(Ok(req.into_response(response).map_into_right_body())));
+    }
+
+    let future = srv.call(req);
+    Box::pin(async move { Ok(future.await?.map_into_left_body()) })
+}
+
+pub fn register(config: &mut web::ServiceConfig) {
+    config.service(key_usage);
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct UsageResponse {
+    pub name: String,
+    /// start of the UTC day
+    pub day: u64,
+    pub requests: u64,
+    /// 0 when there is no quota
+    pub daily_quota: u64,
+    pub rate: f64,
+    pub burst: f64,
+}
+
+pub fn usage_to_display(api_key: &ApiKey, day: u64) -> UsageResponse {
+    UsageResponse {
+        name: api_key.name.clone(),
+        day,
+        requests: usage_get(day, &api_key.name),
+        daily_quota: api_key.daily_quota,
+        rate: api_key.limit.rate,
+        burst: api_key.limit.burst,
+    }
+}
+
+/// Requests made today with the api key of the request and its limits.
+#[utoipa::path(
+    get,
+    path = "/usage",
+    tag = "status",
+    params(("X-API-Key" = Option<String>, Header, description = "or the `api_key` query param")),
+    responses((status = 200, body = APIResponse<UsageResponse>))
+)]
+#[get("/usage")]
+async fn key_usage(req: HttpRequest) -> impl Responder {
+    match request_api_key(&req) {
+        Ok(Some(api_key)) => HttpResponse::response_data(usage_to_display(api_key, usage_today())),
+        _ => HttpResponse::response_error(1, "api key required"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LIMIT: RateLimit = RateLimit { rate: 2.0, burst: 10.0 };
+
+    fn api_key(name: &str, daily_quota: u64) -> ApiKey {
+        ApiKey {
+            key: format!("{}-key", name),
+            name: name.to_string(),
+            limit: LIMIT,
+            daily_quota,
+        }
+    }
+
+    #[test]
+    fn bucket_takes_the_cost() {
+        let now = Instant::now();
+        let mut bucket = TokenBucket {
+            tokens: LIMIT.burst,
+            updated: now,
+        };
+        assert_eq!(bucket.take(&LIMIT, now, 4), Ok(()));
+        assert_eq!(bucket.take(&LIMIT, now, 6), Ok(()));
+        // 1 token at 2 per second
+        assert_eq!(bucket.take(&LIMIT, now, 1), Err(1));
+        // 5 tokens in 2.5 seconds
+        assert_eq!(bucket.take(&LIMIT, now, 5), Err(3));
+        assert_eq!(bucket.take(&LIMIT, now + Duration::from_secs(3), 5), Ok(()));
+    }
+
+    #[test]
+    fn bucket_cost_is_at_most_the_burst() {
+        let now = Instant::now();
+        let mut bucket = TokenBucket {
+            tokens: LIMIT.burst,
+            updated: now,
+        };
+        assert_eq!(bucket.take(&LIMIT, now, 100), Ok(()));
+        assert_eq!(bucket.tokens, 0.0);
+        assert_eq!(bucket.take(&LIMIT, now, 100), Err(5));
+    }
+
+    #[test]
+    fn admin_auth_refused_after_failed_checks() {
+        let req = actix_web::test::TestRequest::default()
+            .peer_addr("10.0.0.1:1234".parse().unwrap())
+            .to_http_request();
+        for _ in 0..5 {
+            assert!(admin_auth_check(&req).is_none());
+            admin_auth_failed(&req);
+        }
+        let response = admin_auth_check(&req).unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers().get(header::RETRY_AFTER).unwrap(), "10");
+
+        // other clients are not refused
+        let other = actix_web::test::TestRequest::default()
+            .peer_addr("10.0.0.2:1234".parse().unwrap())
+            .to_http_request();
+        assert!(admin_auth_check(&other).is_none());
+    }
+
+    #[test]
+    fn usage_add_refuses_over_the_quota() {
+        let usage = Usage::default();
+        let key = api_key("test-quota", 10);
+        assert_eq!(usage.add(&key, DAY_SECONDS, 8), Ok(()));
+        assert_eq!(usage.add(&key, DAY_SECONDS, 3), Err(()));
+        assert_eq!(usage.add(&key, DAY_SECONDS, 2), Ok(()));
+        assert_eq!(usage.add(&key, DAY_SECONDS, 1), Err(()));
+        assert_eq!(usage.get(None, DAY_SECONDS, "test-quota"), 10);
+
+        let unlimited = api_key("test-no-quota", 0);
+        assert_eq!(usage.add(&unlimited, DAY_SECONDS, 1_000_000), Ok(()));
+    }
+
+    #[test]
+    fn usage_flush_keeps_only_today_in_memory() {
+        let path = std::env::temp_dir().join(format!("insdexer-test-{}-api-usage", std::process::id()));
+        let _ = std::fs::remove_dir_all(&path);
+        let db = DB::open_default(&path).unwrap();
+        let usage = Usage::default();
+        let key = api_key("test-flush", 0);
+        usage.add(&key, DAY_SECONDS, 3).unwrap();
+        usage.add(&key, 2 * DAY_SECONDS, 5).unwrap();
+        usage.flush(Some(&db), 2 * DAY_SECONDS);
+
+        assert_eq!(usage.counts.lock().unwrap().len(), 1);
+        assert_eq!(usage.get(Some(&db), DAY_SECONDS, "test-flush"), 3);
+
+        // requests counted before the db was opened are added to the stored ones
+        let reopened = Usage::default();
+        reopened.add(&key, 2 * DAY_SECONDS, 1).unwrap();
+        reopened.load(&db, 2 * DAY_SECONDS, &[key]);
+        assert_eq!(reopened.get(None, 2 * DAY_SECONDS, "test-flush"), 6);
+    }
+}
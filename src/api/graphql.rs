@@ -0,0 +1,613 @@
+use super::{
+    router_market::{market_order_type_str, order_status_str},
+    APIState, ListPage, WebData, PAGE_LIMIT_MAX, PAGE_SIZE,
+};
+use crate::{
+    inscription::{
+        db::*,
+        marketplace::{db::*, types::MarketOrder},
+        types::*,
+    },
+    num_index,
+    txn_db::codec::StorageCodec,
+};
+use actix_web::{get, post, web, HttpResponse, Responder};
+use async_graphql::{
+    connection::{Connection, Edge},
+    http::GraphiQLSource,
+    Context, EmptyMutation, EmptySubscription, Error, Object, OutputType, Result, Schema, SimpleObject,
+};
+use rocksdb::{Direction, DB};
+use std::sync::{Arc, RwLockReadGuard};
+
+/// Deepest selection a query may nest, relations make unbounded queries possible.
+const GRAPHQL_DEPTH_MAX: usize = 12;
+/// Most fields a query may resolve, a connection counts its selection once per item of `first`.
+const GRAPHQL_COMPLEXITY_MAX: usize = 5000;
+
+pub type InsdexerSchema = Schema<QueryRoot, EmptyMutation, EmptySubscription>;
+
+pub fn register(config: &mut web::ServiceConfig) {
+    let schema = Schema::build(QueryRoot, EmptyMutation, EmptySubscription)
+        .limit_depth(GRAPHQL_DEPTH_MAX)
+        .limit_complexity(GRAPHQL_COMPLEXITY_MAX)
+        .finish();
+    config.app_data(web::Data::new(schema));
+    config.service(graphql);
+    config.service(graphiql);
+}
+
+#[post("/graphql")]
+async fn graphql(
+    schema: web::Data<InsdexerSchema>,
+    state: WebData,
+    request: web::Json<async_graphql::Request>,
+) -> impl Responder {
+    let request = request.into_inner().data(state.get_ref().clone());
+    HttpResponse::Ok().json(schema.execute(request).await)
+}
+
+#[get("/graphql")]
+async fn graphiql() -> impl Responder {
+    HttpResponse::Ok()
+        .content_type("text/html; charset=utf-8")
+        .body(GraphiQLSource::build().endpoint("/graphql").finish())
+}
+
+fn db<'a>(ctx: &Context<'a>) -> RwLockReadGuard<'a, DB> {
+    ctx.data_unchecked::<Arc<APIState>>().db.read().unwrap()
+}
+
+/// Complexity of a connection field, its selection resolved for each of the `first` items.
+fn connection_complexity(first: Option<i32>, child_complexity: usize) -> usize {
+    let limit = first.map_or(PAGE_SIZE, |first| (first.max(0) as u64).min(PAGE_LIMIT_MAX));
+    (limit as usize).max(1) * child_complexity
+}
+
+/// A page of an index, cursors are the same opaque keys as the `cursor` of the RESTful api.
+fn key_connection<N: OutputType>(
+    ctx: &Context<'_>,
+    prefix: &str,
+    start: &str,
+    dir: Direction,
+    after: Option<String>,
+    first: Option<i32>,
+    node: impl Fn(&DB, &str, &[u8]) -> Option<N>,
+) -> Result<Connection<String, N>> {
+    let limit = first.map(|first| first.max(0) as u64);
+    let page = ListPage::new(prefix, None, &after, limit).ok_or_else(|| Error::new("Invalid cursor"))?;
+
+    let db = db(ctx);
+    let items = db.get_items_after(prefix, start, page.after(), 0, page.limit, dir);
+    let next_cursor = page.next_cursor(items.len(), items.last().map(|(key, _)| key));
+
+    // the page is at least one item, with `first: 0` it only tells whether there are more
+    if limit == Some(0) {
+        return Ok(Connection::new(page.after.is_some(), !items.is_empty()));
+    }
+
+    let mut connection = Connection::new(page.after.is_some(), next_cursor.is_some());
+    for (key, value) in &items {
+        if let Some(node) = node(&db, std::str::from_utf8(key).unwrap(), value) {
+            connection.edges.push(Edge::new(hex::encode(key), node));
+        }
+    }
+    Ok(connection)
+}
+
+fn inscription_node(db: &DB, id: u64) -> Option<InscriptionNode> {
+    db.get_inscription_meta_by_id(id).map(InscriptionNode)
+}
+
+fn order_node(db: &DB, key: &str) -> Option<MarketOrderNode> {
+    let order_id = &key[key.rfind(':')? + 1..];
+    db.market_get_order_by_id(order_id).map(MarketOrderNode)
+}
+
+pub struct QueryRoot;
+
+#[Object]
+impl QueryRoot {
+    async fn inscription(&self, ctx: &Context<'_>, id: Option<u64>, tx: Option<String>) -> Option<InscriptionNode> {
+        let db = db(ctx);
+        let id = match (id, tx) {
+            (Some(id), _) => id,
+            (None, Some(tx)) => db.get_inscription_id_by_tx(&tx.to_lowercase()),
+            (None, None) => return None,
+        };
+        inscription_node(&db, id)
+    }
+
+    /// Newest inscriptions first.
+    #[graphql(complexity = "connection_complexity(first, child_complexity)")]
+    async fn inscriptions(
+        &self,
+        ctx: &Context<'_>,
+        after: Option<String>,
+        first: Option<i32>,
+    ) -> Result<Connection<String, InscriptionNode>> {
+        let start = make_index_key(KEY_INSC_INDEX_ID, num_index!(db(ctx).get_top_inscription_id()));
+        key_connection(
+            ctx,
+            KEY_INSC_INDEX_ID,
+            &start,
+            Direction::Reverse,
+            after,
+            first,
+            |_, _, value| Some(InscriptionNode(Inscription::decode(value))),
+        )
+    }
+
+    /// Inscriptions sent or received by an address, newest first.
+    #[graphql(complexity = "connection_complexity(first, child_complexity)")]
+    async fn transactions(
+        &self,
+        ctx: &Context<'_>,
+        address: String,
+        after: Option<String>,
+        first: Option<i32>,
+    ) -> Result<Connection<String, InscriptionNode>> {
+        let prefix = make_index_key(KEY_INSC_INDEX_ADDRESS, address.to_lowercase());
+        key_connection(ctx, &prefix, &prefix, Direction::Forward, after, first, |db, key, _| {
+            inscription_node(db, db_key_index2id_desc(key))
+        })
+    }
+
+    /// NFTs held by an address.
+    #[graphql(complexity = "connection_complexity(first, child_complexity)")]
+    async fn nfts(
+        &self,
+        ctx: &Context<'_>,
+        holder: String,
+        after: Option<String>,
+        first: Option<i32>,
+    ) -> Result<Connection<String, InscriptionNode>> {
+        let prefix = make_index_key(KEY_INSC_NFT_INDEX_HOLDER_ADDRESS, holder.to_lowercase());
+        key_connection(ctx, &prefix, &prefix, Direction::Forward, after, first, |db, key, _| {
+            inscription_node(db, db_key_index2id_desc(key))
+        })
+    }
+
+    async fn token(&self, ctx: &Context<'_>, tick: String) -> Option<TokenNode> {
+        db(ctx).get_token(&tick).map(TokenNode)
+    }
+
+    /// Tokens in deploy order.
+    #[graphql(complexity = "connection_complexity(first, child_complexity)")]
+    async fn tokens(
+        &self,
+        ctx: &Context<'_>,
+        after: Option<String>,
+        first: Option<i32>,
+    ) -> Result<Connection<String, TokenNode>> {
+        key_connection(
+            ctx,
+            KEY_INSC_TOKEN_INDEX_ID,
+            KEY_INSC_TOKEN_INDEX_ID,
+            Direction::Forward,
+            after,
+            first,
+            |_, _, value| Some(TokenNode(InscriptionToken::decode(value))),
+        )
+    }
+
+    async fn collection(&self, ctx: &Context<'_>, tx: String) -> Option<CollectionNode> {
+        let tx = tx.to_lowercase();
+        db(ctx)
+            .inscription_nft_collection_exists(&tx)
+            .then_some(CollectionNode { tx })
+    }
+
+    /// Collections, newest first.
+    #[graphql(complexity = "connection_complexity(first, child_complexity)")]
+    async fn collections(
+        &self,
+        ctx: &Context<'_>,
+        after: Option<String>,
+        first: Option<i32>,
+    ) -> Result<Connection<String, CollectionNode>> {
+        key_connection(
+            ctx,
+            KEY_INSC_NFT_COLL_INDEX_ID,
+            KEY_INSC_NFT_COLL_INDEX_ID,
+            Direction::Forward,
+            after,
+            first,
+            |db, key, _| {
+                let insc = db.get_inscription_meta_by_id(db_key_index2id_desc(key))?;
+                Some(CollectionNode { tx: insc.tx_hash })
+            },
+        )
+    }
+
+    async fn market_order(&self, ctx: &Context<'_>, order_id: String) -> Option<MarketOrderNode> {
+        db(ctx).market_get_order_by_id(&order_id).map(MarketOrderNode)
+    }
+
+    /// Orders listed by an address, open ones unless `closed` is set.
+    #[graphql(complexity = "connection_complexity(first, child_complexity)")]
+    async fn market_orders(
+        &self,
+        ctx: &Context<'_>,
+        seller: String,
+        closed: Option<bool>,
+        after: Option<String>,
+        first: Option<i32>,
+    ) -> Result<Connection<String, MarketOrderNode>> {
+        let index = match closed {
+            Some(true) => KEY_MARKET_ORDER_INDEX_SELLER_CLOSE_CANCEL,
+            _ => KEY_MARKET_ORDER_INDEX_SELLER,
+        };
+        let prefix = make_index_key(index, seller.to_lowercase()) + ":";
+        key_connection(ctx, &prefix, &prefix, Direction::Forward, after, first, |db, key, _| {
+            order_node(db, key)
+        })
+    }
+}
+
+pub struct InscriptionNode(Inscription);
+
+#[Object(name = "Inscription")]
+impl InscriptionNode {
+    async fn id(&self) -> u64 {
+        self.0.id
+    }
+
+    async fn tx(&self) -> &str {
+        &self.0.tx_hash
+    }
+
+    async fn from(&self) -> &str {
+        &self.0.from
+    }
+
+    async fn to(&self) -> &str {
+        &self.0.to
+    }
+
+    async fn blocknumber(&self) -> u64 {
+        self.0.blocknumber
+    }
+
+    async fn tx_index(&self) -> u64 {
+        self.0.tx_index
+    }
+
+    async fn timestamp(&self) -> u64 {
+        self.0.timestamp
+    }
+
+    async fn mime_category(&self) -> &str {
+        self.0.mime_category.as_str()
+    }
+
+    async fn mime_type(&self) -> &str {
+        &self.0.mime_type
+    }
+
+    async fn mime_data(&self, ctx: &Context<'_>) -> String {
+        match &self.0.content_hash {
+            Some(content_hash) => db(ctx).get_inscription_content(content_hash).unwrap_or_default(),
+            None => self.0.mime_data.clone(),
+        }
+    }
+
+    async fn verified(&self) -> &str {
+        self.0.verified.as_str()
+    }
+
+    async fn fail_reason(&self) -> Option<String> {
+        self.0
+            .fail_reason
+            .map(|reason| serde_json::to_value(reason).unwrap().as_str().unwrap().to_string())
+    }
+
+    /// Holder of an NFT inscription.
+    async fn owner(&self, ctx: &Context<'_>) -> Option<String> {
+        self.0.signature.as_ref()?;
+        db(ctx).get_inscription_nft_holder_by_id(self.0.id)
+    }
+
+    async fn collection(&self, ctx: &Context<'_>) -> Option<CollectionNode> {
+        self.0.signature.as_ref()?;
+        db(ctx)
+            .get_inscription_nft_collection_by_id(self.0.id)
+            .map(|tx| CollectionNode { tx })
+    }
+
+    async fn market_order(&self, ctx: &Context<'_>) -> Option<MarketOrderNode> {
+        let order_id = self.0.market_order_id.as_ref()?;
+        db(ctx).market_get_order_by_id(order_id).map(MarketOrderNode)
+    }
+
+    /// Transfers of an NFT inscription, newest first.
+    #[graphql(complexity = "connection_complexity(first, child_complexity)")]
+    async fn transfers(
+        &self,
+        ctx: &Context<'_>,
+        after: Option<String>,
+        first: Option<i32>,
+    ) -> Result<Connection<String, InscriptionNode>> {
+        let prefix = make_index_key(KEY_INSC_NFT_TRANS_INDEX_ID, num_index!(self.0.id)) + ":";
+        key_connection(ctx, &prefix, &prefix, Direction::Forward, after, first, |db, key, _| {
+            inscription_node(db, db_key_index2id_desc(key))
+        })
+    }
+}
+
+#[derive(SimpleObject)]
+pub struct TokenHolder {
+    address: String,
+    balance: String,
+}
+
+pub struct TokenNode(InscriptionToken);
+
+#[Object(name = "Token")]
+impl TokenNode {
+    async fn tick(&self) -> &str {
+        &self.0.tick
+    }
+
+    async fn tx(&self) -> &str {
+        &self.0.tx
+    }
+
+    async fn from(&self) -> &str {
+        &self.0.from
+    }
+
+    async fn blocknumber(&self) -> u64 {
+        self.0.blocknumber
+    }
+
+    async fn timestamp(&self) -> u64 {
+        self.0.timestamp
+    }
+
+    async fn holder_count(&self) -> u64 {
+        self.0.holders
+    }
+
+    async fn mint_max(&self) -> String {
+        self.0.mint_max.to_string()
+    }
+
+    async fn mint_limit(&self) -> String {
+        self.0.mint_limit.to_string()
+    }
+
+    async fn mint_progress(&self) -> String {
+        self.0.mint_progress.to_string()
+    }
+
+    async fn mint_finished(&self) -> bool {
+        self.0.mint_finished
+    }
+
+    async fn market_cap(&self) -> String {
+        self.0.market_cap.to_string()
+    }
+
+    async fn market_floor_price(&self) -> String {
+        self.0.market_floor_price.to_string()
+    }
+
+    async fn deploy_inscription(&self, ctx: &Context<'_>) -> Option<InscriptionNode> {
+        inscription_node(&db(ctx), self.0.insc_id)
+    }
+
+    async fn balance(&self, ctx: &Context<'_>, address: String) -> String {
+        db(ctx).get_token_balance(&self.0.tick, &address.to_lowercase()).to_string()
+    }
+
+    /// Holders, largest balance first.
+    #[graphql(complexity = "connection_complexity(first, child_complexity)")]
+    async fn holders(
+        &self,
+        ctx: &Context<'_>,
+        after: Option<String>,
+        first: Option<i32>,
+    ) -> Result<Connection<String, TokenHolder>> {
+        let prefix = make_index_key(KEY_INSC_BALANCE_INDEX_TICK_BALANCE_HOLDER, &self.0.tick) + ":";
+        key_connection(ctx, &prefix, &prefix, Direction::Forward, after, first, |_, key, value| {
+            Some(TokenHolder {
+                address: key[key.len() - 42..].to_string(),
+                balance: u64::from_be_bytes(value.try_into().unwrap()).to_string(),
+            })
+        })
+    }
+
+    /// Transfer inscriptions, newest first.
+    #[graphql(complexity = "connection_complexity(first, child_complexity)")]
+    async fn transfers(
+        &self,
+        ctx: &Context<'_>,
+        after: Option<String>,
+        first: Option<i32>,
+    ) -> Result<Connection<String, InscriptionNode>> {
+        let prefix = make_index_key(KEY_INSC_TOKEN_TRANSFER, &self.0.tick) + ":";
+        key_connection(ctx, &prefix, &prefix, Direction::Forward, after, first, |db, key, _| {
+            inscription_node(db, db_key_index2id_desc(key))
+        })
+    }
+
+    /// Open listings, lowest unit price first.
+    #[graphql(complexity = "connection_complexity(first, child_complexity)")]
+    async fn orders(
+        &self,
+        ctx: &Context<'_>,
+        after: Option<String>,
+        first: Option<i32>,
+    ) -> Result<Connection<String, MarketOrderNode>> {
+        let prefix = make_index_key(KEY_MARKET_ORDER_INDEX_TICK_PRICE, &self.0.tick) + ":";
+        key_connection(ctx, &prefix, &prefix, Direction::Forward, after, first, |db, key, _| {
+            order_node(db, key)
+        })
+    }
+
+    /// Open bids, highest price first.
+    #[graphql(complexity = "connection_complexity(first, child_complexity)")]
+    async fn bids(
+        &self,
+        ctx: &Context<'_>,
+        after: Option<String>,
+        first: Option<i32>,
+    ) -> Result<Connection<String, MarketOrderNode>> {
+        let prefix = make_index_key(KEY_MARKET_BID_INDEX_TICK_PRICE, &self.0.tick) + ":";
+        key_connection(ctx, &prefix, &prefix, Direction::Forward, after, first, |db, key, _| {
+            order_node(db, key)
+        })
+    }
+}
+
+pub struct CollectionNode {
+    tx: String,
+}
+
+#[Object(name = "Collection")]
+impl CollectionNode {
+    async fn tx(&self) -> &str {
+        &self.tx
+    }
+
+    async fn inscription(&self, ctx: &Context<'_>) -> Option<InscriptionNode> {
+        let db = db(ctx);
+        inscription_node(&db, db.get_inscription_id_by_tx(&self.tx))
+    }
+
+    /// Open collection-wide bids, highest price first.
+    #[graphql(complexity = "connection_complexity(first, child_complexity)")]
+    async fn bids(
+        &self,
+        ctx: &Context<'_>,
+        after: Option<String>,
+        first: Option<i32>,
+    ) -> Result<Connection<String, MarketOrderNode>> {
+        let prefix = make_index_key(KEY_MARKET_BID_INDEX_COLLECTION_PRICE, &self.tx) + ":";
+        key_connection(ctx, &prefix, &prefix, Direction::Forward, after, first, |db, key, _| {
+            order_node(db, key)
+        })
+    }
+}
+
+pub struct MarketOrderNode(MarketOrder);
+
+#[Object(name = "MarketOrder")]
+impl MarketOrderNode {
+    async fn order_id(&self) -> &str {
+        &self.0.order_id
+    }
+
+    async fn order_type(&self) -> &str {
+        market_order_type_str(&self.0.order_type)
+    }
+
+    async fn order_status(&self) -> String {
+        order_status_str(&self.0.order_status)
+    }
+
+    async fn from(&self) -> &str {
+        &self.0.from
+    }
+
+    async fn market(&self) -> &str {
+        &self.0.to
+    }
+
+    async fn buyer(&self) -> &str {
+        &self.0.buyer
+    }
+
+    async fn seller(&self) -> &str {
+        &self.0.seller
+    }
+
+    async fn tick(&self) -> &str {
+        &self.0.tick
+    }
+
+    async fn amount(&self) -> String {
+        self.0.amount.to_string()
+    }
+
+    async fn filled_amount(&self) -> String {
+        self.0.filled_amount.to_string()
+    }
+
+    async fn total_price(&self) -> String {
+        self.0.total_price.to_string()
+    }
+
+    async fn unit_price(&self) -> String {
+        self.0.unit_price.to_string()
+    }
+
+    async fn tx(&self) -> &str {
+        &self.0.tx
+    }
+
+    async fn blocknumber(&self) -> u64 {
+        self.0.blocknumber
+    }
+
+    async fn timestamp(&self) -> u64 {
+        self.0.timestamp
+    }
+
+    async fn expire_at(&self) -> Option<u64> {
+        (self.0.expire_at > 0).then_some(self.0.expire_at)
+    }
+
+    /// The inscription sent to the market to list the order.
+    async fn inscription(&self, ctx: &Context<'_>) -> Option<InscriptionNode> {
+        let db = db(ctx);
+        inscription_node(&db, db.get_inscription_id_by_tx(&self.0.tx))
+    }
+
+    async fn nft(&self, ctx: &Context<'_>) -> Option<InscriptionNode> {
+        match self.0.nft_id {
+            0 => None,
+            nft_id => inscription_node(&db(ctx), nft_id),
+        }
+    }
+
+    async fn collection(&self) -> Option<CollectionNode> {
+        (!self.0.collection.is_empty()).then(|| CollectionNode {
+            tx: self.0.collection.clone(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn schema() -> InsdexerSchema {
+        Schema::build(QueryRoot, EmptyMutation, EmptySubscription)
+            .limit_depth(GRAPHQL_DEPTH_MAX)
+            .limit_complexity(GRAPHQL_COMPLEXITY_MAX)
+            .finish()
+    }
+
+    #[test]
+    fn connections_are_charged_by_first() {
+        assert_eq!(connection_complexity(None, 3), PAGE_SIZE as usize * 3);
+        assert_eq!(connection_complexity(Some(10), 3), 30);
+        assert_eq!(connection_complexity(Some(0), 3), 3);
+        assert_eq!(connection_complexity(Some(-5), 3), 3);
+        assert_eq!(connection_complexity(Some(i32::MAX), 3), PAGE_LIMIT_MAX as usize * 3);
+    }
+
+    #[tokio::test]
+    async fn nested_connections_are_too_complex() {
+        let query = "{ inscriptions(first: 100) { edges { node { transfers(first: 100) { edges { node { id } } } } } } }";
+        let response = schema().execute(query).await;
+        assert_eq!(response.errors.len(), 1);
+        assert!(
+            response.errors[0].message.contains("too complex"),
+            "{}",
+            response.errors[0].message
+        );
+    }
+}
@@ -0,0 +1,114 @@
+use super::{
+    router_inscription::get_inscription, router_market::get_market_order, router_other::get_status, router_token::*, APIResult,
+    APIState, WebData,
+};
+use actix_web::{post, web, HttpRequest, HttpResponse, Responder};
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+
+const JSONRPC_PARSE_ERROR: i64 = -32700;
+const JSONRPC_INVALID_REQUEST: i64 = -32600;
+const JSONRPC_METHOD_NOT_FOUND: i64 = -32601;
+const JSONRPC_INVALID_PARAMS: i64 = -32602;
+/// Most requests a batch may hold, every one of them reads the db.
+pub const JSONRPC_BATCH_MAX: usize = 100;
+
+pub fn register(config: &mut web::ServiceConfig) {
+    config.service(jsonrpc);
+}
+
+type RpcError = (i64, String);
+
+/// Params of a method as the query struct of its route, positional params are named in `names` order.
+fn rpc_params<P: DeserializeOwned>(params: &Value, names: &[&str]) -> Result<P, RpcError> {
+    let params = match params {
+        Value::Null => json!({}),
+        Value::Array(list) if list.len() <= names.len() => {
+            let fields = names.iter().zip(list).map(|(name, value)| (name.to_string(), value.clone()));
+            Value::Object(fields.collect())
+        }
+        Value::Object(_) => params.clone(),
+        _ => return Err((JSONRPC_INVALID_PARAMS, "Invalid params".to_string())),
+    };
+    serde_json::from_value(params).map_err(|e| (JSONRPC_INVALID_PARAMS, format!("Invalid params: {}", e)))
+}
+
+/// Methods map to the RESTful routes, errors keep the `error_code` of the route.
+fn rpc_call(state: &APIState, method: &str, params: &Value) -> Result<Value, RpcError> {
+    let result = match method {
+        "insc_status" => get_status(state),
+        "insc_getInscription" => get_inscription(state, &rpc_params(params, &["id", "tx"])?),
+        "insc_getToken" => get_token_info(state, &rpc_params(params, &["tick"])?),
+        "insc_getBalance" => get_token_balance(state, &rpc_params(params, &["address"])?),
+        "insc_getTokenHolders" => get_token_holders(state, &rpc_params(params, &["tick", "limit", "cursor"])?),
+        "insc_getOrder" => get_market_order(state, &rpc_params(params, &["order_id"])?),
+        _ => return Err((JSONRPC_METHOD_NOT_FOUND, "Method not found".to_string())),
+    };
+
+    match result {
+        APIResult::Data(value) => Ok(value),
+        APIResult::Page(value, next_cursor) => Ok(json!({ "data": value, "next_cursor": next_cursor })),
+        APIResult::Error(error_code, error) => Err((error_code as i64, error)),
+        APIResult::NotFound => Err((404, "not found".to_string())),
+    }
+}
+
+fn rpc_error(id: Value, (code, message): RpcError) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } })
+}
+
+/// Response of a single request, None for a notification.
+fn rpc_handle(state: &APIState, request: &Value) -> Option<Value> {
+    let method = request["method"].as_str();
+    if request["jsonrpc"] != "2.0" || method.is_none() {
+        // an invalid request with a usable id gets it back
+        let id = match request.get("id") {
+            Some(id @ (Value::String(_) | Value::Number(_))) => id.clone(),
+            _ => Value::Null,
+        };
+        return Some(rpc_error(id, (JSONRPC_INVALID_REQUEST, "Invalid Request".to_string())));
+    }
+
+    let result = rpc_call(state, method.unwrap(), &request["params"]);
+    let id = request.get("id")?.clone();
+    Some(match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(error) => rpc_error(id, error),
+    })
+}
+
+#[post("/jsonrpc")]
+async fn jsonrpc(req: HttpRequest, body: web::Bytes, state: WebData) -> impl Responder {
+    let request: Value = match serde_json::from_slice(&body) {
+        Ok(request) => request,
+        Err(_) => return HttpResponse::Ok().json(rpc_error(Value::Null, (JSONRPC_PARSE_ERROR, "Parse error".to_string()))),
+    };
+
+    let response = match &request {
+        Value::Array(batch) if batch.is_empty() => Some(rpc_error(
+            Value::Null,
+            (JSONRPC_INVALID_REQUEST, "Invalid Request".to_string()),
+        )),
+        Value::Array(batch) if batch.len() > JSONRPC_BATCH_MAX => Some(rpc_error(
+            Value::Null,
+            (
+                JSONRPC_INVALID_REQUEST,
+                format!("Invalid Request: batch larger than {}", JSONRPC_BATCH_MAX),
+            ),
+        )),
+        Value::Array(batch) => {
+            // the limit middleware took one token for the http request, the batch costs one per request
+            if let Some(response) = super::api_key::check(&req, batch.len() as u64 - 1) {
+                return response;
+            }
+            let responses: Vec<Value> = batch.iter().filter_map(|request| rpc_handle(&state, request)).collect();
+            (!responses.is_empty()).then_some(Value::Array(responses))
+        }
+        _ => rpc_handle(&state, &request),
+    };
+
+    match response {
+        Some(response) => HttpResponse::Ok().json(response),
+        None => HttpResponse::NoContent().finish(),
+    }
+}
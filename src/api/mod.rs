@@ -1,41 +1,160 @@
 use rocksdb::DB;
+use serde::Serialize;
 use serde_json::json;
 use std::sync::{Arc, RwLock};
+use utoipa::ToSchema;
 
+pub mod api_key;
+pub mod graphql;
+pub mod jsonrpc;
+pub mod openapi;
+pub mod router_admin;
+pub mod router_content;
 pub mod router_inscription;
 pub mod router_market;
+pub mod router_metrics;
 pub mod router_nft;
 pub mod router_other;
+pub mod router_stream;
 pub mod router_token;
 pub mod server;
 
 pub const PAGE_SIZE: u64 = 16;
+pub const PAGE_LIMIT_MAX: u64 = 100;
 
 type WebData = actix_web::web::Data<std::sync::Arc<APIState>>;
 
 pub struct APIState {
     pub db: Arc<RwLock<DB>>,
     pub blocknumber: RwLock<u64>,
+    /// timestamp in ms of the last reopen of `db`
+    pub db_updated: RwLock<u64>,
+    /// timestamp in ms of the last successful poll of `blocknumber`, 0 before the first one
+    pub blocknumber_updated: RwLock<u64>,
 }
 
 pub struct DBRefresh;
 
+/// Outcome of a route, rendered as a RESTful response or a JSON-RPC result.
+pub enum APIResult {
+    Data(serde_json::Value),
+    Page(serde_json::Value, Option<String>),
+    Error(u64, String),
+    NotFound,
+}
+
+impl APIResult {
+    pub fn data<T: serde::Serialize>(value: T) -> Self {
+        APIResult::Data(serde_json::to_value(value).unwrap())
+    }
+
+    pub fn page<T: serde::Serialize>(value: T, next_cursor: Option<String>) -> Self {
+        APIResult::Page(serde_json::to_value(value).unwrap(), next_cursor)
+    }
+}
+
+/// Position of a list request, the opaque cursor of the previous response or a legacy page number.
+pub struct ListPage {
+    pub after: Option<String>,
+    pub skip: u64,
+    pub limit: u64,
+}
+
+impl ListPage {
+    /// None when the cursor is not a key under `prefix`.
+    pub fn new(prefix: &str, page: Option<u64>, cursor: &Option<String>, limit: Option<u64>) -> Option<Self> {
+        let limit = limit.unwrap_or(PAGE_SIZE).clamp(1, PAGE_LIMIT_MAX);
+        match cursor {
+            Some(cursor) => {
+                let key = String::from_utf8(hex::decode(cursor).ok()?).ok()?;
+                if !key.starts_with(prefix) {
+                    return None;
+                }
+                Some(ListPage {
+                    after: Some(key),
+                    skip: 0,
+                    limit,
+                })
+            }
+            None => Some(ListPage {
+                after: None,
+                skip: page.unwrap_or(1).saturating_sub(1) * limit,
+                limit,
+            }),
+        }
+    }
+
+    pub fn after(&self) -> Option<&str> {
+        self.after.as_deref()
+    }
+
+    /// Cursor of the next page, None when this page is the last one.
+    pub fn next_cursor<K: AsRef<[u8]>>(&self, count: usize, last_key: Option<K>) -> Option<String> {
+        if (count as u64) < self.limit {
+            return None;
+        }
+        last_key.map(hex::encode)
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct APIResponse<T> {
+    pub error_code: u64,
+    pub data: T,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct APIPage<T> {
+    pub error_code: u64,
+    pub data: Vec<T>,
+    /// Pass back as `cursor` for the following page, null on the last page.
+    pub next_cursor: Option<String>,
+}
+
+/// Returned with http status 200, `error_code` is 404 when the item does not exist.
+#[derive(Serialize, ToSchema)]
+pub struct APIError {
+    pub error_code: u64,
+    pub error: String,
+}
+
 pub trait HttpResponseExt {
     fn response_data<T: serde::Serialize>(value: T) -> Self;
+    fn response_page<T: serde::Serialize>(value: T, next_cursor: Option<String>) -> Self;
     fn response_error(error_code: u64, error: &str) -> Self;
     fn response_error_notfound() -> Self;
+    fn response_result(result: APIResult) -> Self;
 }
 
 impl HttpResponseExt for actix_web::HttpResponse {
     fn response_data<T: serde::Serialize>(value: T) -> Self {
-        Self::Ok().json(json!({ "error_code": 0, "data": value }))
+        Self::Ok().json(APIResponse {
+            error_code: 0,
+            data: value,
+        })
+    }
+
+    fn response_page<T: serde::Serialize>(value: T, next_cursor: Option<String>) -> Self {
+        Self::Ok().json(json!({ "error_code": 0, "data": value, "next_cursor": next_cursor }))
     }
 
     fn response_error(error_code: u64, error: &str) -> Self {
-        Self::Ok().json(json!({ "error_code": error_code, "error": error }))
+        Self::Ok().json(APIError {
+            error_code,
+            error: error.to_string(),
+        })
     }
 
     fn response_error_notfound() -> Self {
-        Self::Ok().json(json!({ "error_code": 404, "error": "not found" }))
+        Self::response_error(404, "not found")
+    }
+
+    fn response_result(result: APIResult) -> Self {
+        match result {
+            APIResult::Data(value) => Self::response_data(value),
+            APIResult::Page(value, next_cursor) => Self::response_page(value, next_cursor),
+            APIResult::Error(error_code, error) => Self::response_error(error_code, &error),
+            APIResult::NotFound => Self::response_error_notfound(),
+        }
     }
 }
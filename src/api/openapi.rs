@@ -0,0 +1,86 @@
+use super::{
+    api_key, router_admin, router_content, router_inscription, router_market, router_nft, router_other, router_token, APIError,
+};
+use actix_web::{get, web, HttpResponse, Responder};
+use utoipa::{
+    openapi::security::{HttpAuthScheme, HttpBuilder, SecurityScheme},
+    Modify, OpenApi,
+};
+use utoipa_scalar::{Scalar, Servable};
+
+/// The RESTful routes, `/events`, `/ws`, `/graphql` and `/jsonrpc` document themselves elsewhere.
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "Insdexer API",
+        description = "Errors are returned with http status 200 as `APIError`, `error_code` 404 when the item does not exist. \
+            Amounts, balances and prices are decimal strings, ids, blocknumbers, times and counts are numbers."
+    ),
+    paths(
+        router_content::content,
+        router_inscription::inscription,
+        router_inscription::inscription_path,
+        router_inscription::recent,
+        router_inscription::transactions,
+        router_inscription::created,
+        router_market::market_orders_all,
+        router_market::market_orders_list,
+        router_market::market_orders,
+        router_market::market_order,
+        router_market::market_candles,
+        router_market::market_stats_market,
+        router_market::market_stats_creator,
+        router_market::market_trades,
+        router_market::market_pnl,
+        router_nft::nft_recent,
+        router_nft::nft_collections,
+        router_nft::nfts,
+        router_nft::nft,
+        router_nft::nft_transfers,
+        router_other::status,
+        router_other::checkpoints,
+        router_other::healthz,
+        router_other::readyz,
+        api_key::key_usage,
+        router_token::tokens,
+        router_token::token_info,
+        router_token::token_holders,
+        router_token::token_balance,
+        router_token::token_txs,
+        router_admin::rollback,
+        router_admin::checkpoint,
+        router_admin::reindex,
+        router_admin::webhook_list,
+        router_admin::webhook_put,
+        router_admin::webhook_remove,
+        router_admin::webhook_dead,
+        router_admin::webhook_redeliver,
+        router_admin::usage_list,
+    ),
+    components(schemas(APIError)),
+    modifiers(&AdminSecurity)
+)]
+pub struct APIDoc;
+
+/// The `--admin-token` bearer token of the `/admin` routes.
+struct AdminSecurity;
+
+impl Modify for AdminSecurity {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "admin_token",
+            SecurityScheme::Http(HttpBuilder::new().scheme(HttpAuthScheme::Bearer).build()),
+        );
+    }
+}
+
+pub fn register(config: &mut web::ServiceConfig) {
+    config.service(openapi_json);
+    config.service(Scalar::with_url("/docs", APIDoc::openapi()));
+}
+
+#[get("/openapi.json")]
+async fn openapi_json() -> impl Responder {
+    HttpResponse::Ok().json(APIDoc::openapi())
+}
@@ -0,0 +1,382 @@
+use super::{
+    api_key::{admin_auth_check, admin_auth_failed, usage_day, usage_to_display, UsageResponse, API_KEYS},
+    APIResponse, HttpResponseExt, WebData, PAGE_LIMIT_MAX, PAGE_SIZE,
+};
+use crate::{
+    config::{ADMIN_TOKEN, API_ONLY},
+    global::{CHECKPOINT_REQUEST, REINDEX_REQUEST, ROLLBACK_BLOCK},
+    inscription::{
+        db_checkpoint::checkpoints_list,
+        events::InscribeEventFilter,
+        webhook::{db::InscribeWebhookDB, save_webhooks, Webhook, WEBHOOKS, WEBHOOK_REDELIVER},
+    },
+};
+use actix_web::{
+    dev::Payload,
+    error::{ErrorNotFound, ErrorUnauthorized, InternalError},
+    get,
+    http::header,
+    post,
+    web::{self, Json, Query},
+    FromRequest, HttpRequest, HttpResponse, Responder,
+};
+use futures_util::future::{ready, Ready};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use utoipa::{IntoParams, ToSchema};
+
+pub fn register(config: &mut web::ServiceConfig) {
+    config.service(
+        web::scope("/admin")
+            .service(rollback)
+            .service(checkpoint)
+            .service(reindex)
+            .service(webhook_list)
+            .service(webhook_put)
+            .service(webhook_remove)
+            .service(webhook_dead)
+            .service(webhook_redeliver)
+            .service(usage_list),
+    );
+}
+
+/// A request carrying the `--admin-token` bearer token. Without a token configured the admin routes do not exist.
+pub struct AdminAuth;
+
+impl FromRequest for AdminAuth {
+    type Error = actix_web::Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        if ADMIN_TOKEN.is_empty() {
+            return ready(Err(ErrorNotFound("404 Not Found")));
+        }
+
+        // the admin routes are not rate limited, failed checks are
+        if let Some(response) = admin_auth_check(req) {
+            return ready(Err(InternalError::from_response("Too Many Requests", response).into()));
+        }
+
+        let token = req
+            .headers()
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "));
+
+        // compare digests so the time taken does not depend on how much of the token matches
+        match token {
+            Some(token) if Sha256::digest(token) == Sha256::digest(ADMIN_TOKEN.as_str()) => ready(Ok(AdminAuth)),
+            _ => {
+                admin_auth_failed(req);
+                ready(Err(ErrorUnauthorized("Unauthorized")))
+            }
+        }
+    }
+}
+
+/// Requests picked up by the indexer, which does not run in api-only mode.
+fn indexer_unavailable() -> Option<HttpResponse> {
+    API_ONLY.then(|| HttpResponse::response_error(1, "not available in api-only mode"))
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+struct RollbackRequest {
+    blocknumber: u64,
+}
+
+/// Roll back to a checkpoint, the indexer exits and restores it on the next start.
+#[utoipa::path(
+    post,
+    path = "/admin/rollback",
+    tag = "admin",
+    request_body = RollbackRequest,
+    responses((status = 200, body = APIResponse<String>)),
+    security(("admin_token" = []))
+)]
+#[post("/rollback")]
+async fn rollback(_: AdminAuth, body: Json<RollbackRequest>) -> impl Responder {
+    if let Some(response) = indexer_unavailable() {
+        return response;
+    }
+
+    if !checkpoints_list().contains(&body.blocknumber) {
+        return HttpResponse::response_error(1, "checkpoint not found");
+    }
+
+    let mut rollback_block = ROLLBACK_BLOCK.lock().unwrap();
+    if *rollback_block == 0 {
+        *rollback_block = body.blocknumber;
+        HttpResponse::response_data("ok")
+    } else {
+        HttpResponse::response_error(1, "rollback in progress")
+    }
+}
+
+/// Make a checkpoint after the next synced block.
+#[utoipa::path(
+    post,
+    path = "/admin/checkpoint",
+    tag = "admin",
+    responses((status = 200, body = APIResponse<String>)),
+    security(("admin_token" = []))
+)]
+#[post("/checkpoint")]
+async fn checkpoint(_: AdminAuth) -> impl Responder {
+    if let Some(response) = indexer_unavailable() {
+        return response;
+    }
+
+    *CHECKPOINT_REQUEST.lock().unwrap() = true;
+    HttpResponse::response_data("ok")
+}
+
+/// Rebuild tokens, NFTs, balances and the market from the synced inscriptions, the indexer exits and reindexes on the next start.
+#[utoipa::path(
+    post,
+    path = "/admin/reindex",
+    tag = "admin",
+    responses((status = 200, body = APIResponse<String>)),
+    security(("admin_token" = []))
+)]
+#[post("/reindex")]
+async fn reindex(_: AdminAuth) -> impl Responder {
+    if let Some(response) = indexer_unavailable() {
+        return response;
+    }
+
+    *REINDEX_REQUEST.lock().unwrap() = true;
+    HttpResponse::response_data("ok")
+}
+
+/// A webhook as registered, unset filters are omitted.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct WebhookRequest {
+    id: String,
+    url: String,
+    /// key of the `X-Insdexer-Signature` HMAC-SHA256, deliveries are unsigned when empty
+    #[serde(default)]
+    secret: String,
+    /// comma separated event names
+    events: Option<String>,
+    address: Option<String>,
+    tick: Option<String>,
+    protocol: Option<String>,
+    category: Option<String>,
+    collection: Option<String>,
+}
+
+/// A registered webhook, its secret is not returned.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct WebhookResponse {
+    id: String,
+    url: String,
+    signed: bool,
+    events: Option<String>,
+    address: Option<String>,
+    tick: Option<String>,
+    protocol: Option<String>,
+    category: Option<String>,
+    collection: Option<String>,
+}
+
+impl From<&Webhook> for WebhookResponse {
+    fn from(webhook: &Webhook) -> Self {
+        let filter = webhook.filter.clone();
+        WebhookResponse {
+            id: webhook.id.clone(),
+            url: webhook.url.clone(),
+            signed: !webhook.secret.is_empty(),
+            events: filter.events,
+            address: filter.address,
+            tick: filter.tick,
+            protocol: filter.protocol,
+            category: filter.category,
+            collection: filter.collection,
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/webhooks",
+    tag = "admin",
+    responses((status = 200, body = APIResponse<Vec<WebhookResponse>>)),
+    security(("admin_token" = []))
+)]
+#[get("/webhooks")]
+async fn webhook_list(_: AdminAuth) -> impl Responder {
+    let webhooks = WEBHOOKS.read().unwrap();
+    let mut list: Vec<WebhookResponse> = webhooks.values().map(WebhookResponse::from).collect();
+    list.sort_by(|a, b| a.id.cmp(&b.id));
+    HttpResponse::response_data(list)
+}
+
+/// Add a webhook or replace the one with the same id, saved to `--webhook-config` when it is set.
+#[utoipa::path(
+    post,
+    path = "/admin/webhooks",
+    tag = "admin",
+    request_body = WebhookRequest,
+    responses((status = 200, body = APIResponse<WebhookResponse>)),
+    security(("admin_token" = []))
+)]
+#[post("/webhooks")]
+async fn webhook_put(_: AdminAuth, body: Json<WebhookRequest>) -> impl Responder {
+    if let Some(response) = indexer_unavailable() {
+        return response;
+    }
+
+    let body = body.into_inner();
+    if body.id.is_empty() || !(body.url.starts_with("http://") || body.url.starts_with("https://")) {
+        return HttpResponse::response_error(1, "Invalid params");
+    }
+
+    let webhook = Webhook {
+        id: body.id,
+        url: body.url,
+        secret: body.secret,
+        filter: InscribeEventFilter {
+            events: body.events,
+            address: body.address,
+            tick: body.tick,
+            protocol: body.protocol,
+            category: body.category,
+            collection: body.collection,
+        },
+    };
+    let response = WebhookResponse::from(&webhook);
+
+    let mut webhooks = WEBHOOKS.write().unwrap();
+    webhooks.insert(webhook.id.clone(), webhook);
+    save_webhooks(&webhooks);
+    HttpResponse::response_data(response)
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+struct WebhookRemoveRequest {
+    id: String,
+}
+
+/// Remove a webhook, its queued deliveries end up in the dead letters.
+#[utoipa::path(
+    post,
+    path = "/admin/webhooks/remove",
+    tag = "admin",
+    request_body = WebhookRemoveRequest,
+    responses((status = 200, body = APIResponse<String>)),
+    security(("admin_token" = []))
+)]
+#[post("/webhooks/remove")]
+async fn webhook_remove(_: AdminAuth, body: Json<WebhookRemoveRequest>) -> impl Responder {
+    if let Some(response) = indexer_unavailable() {
+        return response;
+    }
+
+    let mut webhooks = WEBHOOKS.write().unwrap();
+    if webhooks.remove(&body.id).is_none() {
+        return HttpResponse::response_error_notfound();
+    }
+    save_webhooks(&webhooks);
+    HttpResponse::response_data("ok")
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct WebhookDeadParams {
+    page: Option<u64>,
+    limit: Option<u64>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct WebhookDeliveryResponse {
+    id: u64,
+    webhook: String,
+    event: String,
+    attempts: u32,
+    /// error of the last attempt
+    error: String,
+    /// the body which was posted
+    #[schema(value_type = Object)]
+    payload: serde_json::Value,
+}
+
+/// Deliveries which failed every attempt, oldest first.
+#[utoipa::path(
+    get,
+    path = "/admin/webhooks/dead",
+    tag = "admin",
+    params(WebhookDeadParams),
+    responses((status = 200, body = APIResponse<Vec<WebhookDeliveryResponse>>)),
+    security(("admin_token" = []))
+)]
+#[get("/webhooks/dead")]
+async fn webhook_dead(_: AdminAuth, info: Query<WebhookDeadParams>, state: WebData) -> impl Responder {
+    let limit = info.limit.unwrap_or(PAGE_SIZE).clamp(1, PAGE_LIMIT_MAX);
+    let skip = info.page.unwrap_or(1).saturating_sub(1) * limit;
+    let deliveries = state.db.read().unwrap().webhook_get_dead(skip, limit);
+    let list: Vec<WebhookDeliveryResponse> = deliveries
+        .into_iter()
+        .map(|delivery| WebhookDeliveryResponse {
+            id: delivery.id,
+            webhook: delivery.webhook,
+            event: delivery.event,
+            attempts: delivery.attempts,
+            error: delivery.error,
+            payload: serde_json::from_str(&delivery.payload).unwrap(),
+        })
+        .collect();
+    HttpResponse::response_data(list)
+}
+
+/// Queue every dead letter again with its attempts reset.
+#[utoipa::path(
+    post,
+    path = "/admin/webhooks/redeliver",
+    tag = "admin",
+    responses((status = 200, body = APIResponse<String>)),
+    security(("admin_token" = []))
+)]
+#[post("/webhooks/redeliver")]
+async fn webhook_redeliver(_: AdminAuth) -> impl Responder {
+    if let Some(response) = indexer_unavailable() {
+        return response;
+    }
+
+    *WEBHOOK_REDELIVER.lock().unwrap() = true;
+    HttpResponse::response_data("ok")
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct UsageParams {
+    /// name of an api key, every key when unset
+    name: Option<String>,
+    /// days back from today, 7 by default
+    days: Option<u64>,
+}
+
+/// Requests per api key and UTC day, newest day first.
+#[utoipa::path(
+    get,
+    path = "/admin/usage",
+    tag = "admin",
+    params(UsageParams),
+    responses((status = 200, body = APIResponse<Vec<UsageResponse>>)),
+    security(("admin_token" = []))
+)]
+#[get("/usage")]
+async fn usage_list(_: AdminAuth, info: Query<UsageParams>) -> impl Responder {
+    let days = info.days.unwrap_or(7).clamp(1, PAGE_LIMIT_MAX);
+    let api_keys: Vec<_> = API_KEYS
+        .keys
+        .iter()
+        .filter(|api_key| info.name.is_none() || info.name.as_deref() == Some(api_key.name.as_str()))
+        .collect();
+
+    let mut list = Vec::new();
+    for days_ago in 0..days {
+        let day = usage_day(days_ago);
+        list.extend(api_keys.iter().map(|api_key| usage_to_display(api_key, day)));
+    }
+    HttpResponse::response_data(list)
+}
@@ -0,0 +1,205 @@
+use super::WebData;
+use crate::inscription::{db::*, types::*};
+use actix_web::{
+    get,
+    http::header::{
+        self, CacheControl, CacheDirective, ContentRange, ContentRangeSpec, ETag, EntityTag, Header, IfNoneMatch, Range,
+    },
+    web, HttpRequest, HttpResponse, HttpResponseBuilder, Responder,
+};
+use base64::{engine::general_purpose, Engine as _};
+use percent_encoding::percent_decode_str;
+use sha1::{Digest, Sha1};
+
+const CONTENT_MAX_AGE: u32 = 365 * 24 * 3600;
+
+pub fn register(config: &mut web::ServiceConfig) {
+    config.service(content);
+}
+
+pub struct InscriptionContent {
+    pub content_type: String,
+    pub data: Vec<u8>,
+}
+
+/// Decode the payload of a text, image or json inscription from its data uri parts.
+pub fn inscription_content(insc: &Inscription) -> Option<InscriptionContent> {
+    match insc.mime_category {
+        InscriptionMimeCategory::Text | InscriptionMimeCategory::Image | InscriptionMimeCategory::Json => {}
+        _ => return None,
+    }
+
+    let mut content_type = insc.mime_type.trim();
+    let is_base64 = content_type.to_lowercase().ends_with(";base64");
+    if is_base64 {
+        content_type = &content_type[..content_type.len() - ";base64".len()];
+    }
+
+    let data = if is_base64 {
+        general_purpose::STANDARD.decode(&insc.mime_data).ok()?
+    } else if insc.mime_category == InscriptionMimeCategory::Image {
+        // images are base64 without saying so, as the baseline /nft endpoint decoded them, svg ones may be plain
+        match general_purpose::STANDARD.decode(&insc.mime_data) {
+            Ok(data) => data,
+            Err(_) => percent_decode_str(&insc.mime_data).collect(),
+        }
+    } else {
+        percent_decode_str(&insc.mime_data).collect()
+    };
+
+    let content_type = if !content_type.is_empty() {
+        content_type.to_string()
+    } else if insc.mime_category == InscriptionMimeCategory::Json {
+        "application/json".to_string()
+    } else {
+        "text/plain; charset=utf-8".to_string()
+    };
+
+    Some(InscriptionContent { content_type, data })
+}
+
+fn content_etag(data: &[u8]) -> EntityTag {
+    let mut hasher = Sha1::new();
+    hasher.update(data);
+    EntityTag::new_strong(format!("{:x}", hasher.finalize()))
+}
+
+fn content_headers(builder: &mut HttpResponseBuilder, etag: EntityTag) {
+    builder
+        .insert_header(ETag(etag))
+        .insert_header(CacheControl(vec![
+            CacheDirective::Public,
+            CacheDirective::MaxAge(CONTENT_MAX_AGE),
+            CacheDirective::Extension("immutable".to_string(), None),
+        ]))
+        .insert_header((header::ACCEPT_RANGES, "bytes"));
+}
+
+/// Decoded payload of a text, image or json inscription, with etag and range support.
+#[utoipa::path(
+    get,
+    path = "/content/{path}",
+    tag = "content",
+    params(("path" = String, Path, description = "Inscription id or tx")),
+    responses(
+        (status = 200, description = "The payload with its mime type", content_type = "application/octet-stream"),
+        (status = 404, description = "Inscription not found")
+    )
+)]
+#[get("/content/{path}")]
+async fn content(path: web::Path<String>, req: HttpRequest, state: WebData) -> impl Responder {
+    let result = {
+        let db = state.db.read().unwrap();
+        let path_value = path.into_inner();
+        match path_value.parse::<u64>() {
+            Ok(id) => db.get_inscription_by_id(id),
+            Err(_) => db.get_inscription_by_tx(&path_value),
+        }
+    };
+
+    let insc = match result {
+        Some(insc) => insc,
+        None => return HttpResponse::NotFound().body("Inscription not found"),
+    };
+
+    let content = match inscription_content(&insc) {
+        Some(content) => content,
+        None => return HttpResponse::BadRequest().body("Inscription has no content"),
+    };
+
+    let etag = content_etag(&content.data);
+    let not_modified = match IfNoneMatch::parse(&req) {
+        Ok(IfNoneMatch::Any) => true,
+        Ok(IfNoneMatch::Items(items)) => items.iter().any(|item| item.weak_eq(&etag)),
+        Err(_) => false,
+    };
+
+    if not_modified {
+        let mut builder = HttpResponse::NotModified();
+        content_headers(&mut builder, etag);
+        return builder.finish();
+    }
+
+    let content_length = content.data.len() as u64;
+    if let Ok(Range::Bytes(ranges)) = Range::parse(&req) {
+        // multiple ranges are served as the full body
+        if ranges.len() == 1 {
+            return match ranges[0].to_satisfiable_range(content_length) {
+                Some((from, to)) => {
+                    let mut builder = HttpResponse::PartialContent();
+                    content_headers(&mut builder, etag);
+                    builder
+                        .content_type(content.content_type)
+                        .insert_header(ContentRange(ContentRangeSpec::Bytes {
+                            range: Some((from, to)),
+                            instance_length: Some(content_length),
+                        }))
+                        .body(content.data[from as usize..=to as usize].to_vec())
+                }
+                None => HttpResponse::RangeNotSatisfiable()
+                    .insert_header(ContentRange(ContentRangeSpec::Bytes {
+                        range: None,
+                        instance_length: Some(content_length),
+                    }))
+                    .finish(),
+            };
+        }
+    }
+
+    let mut builder = HttpResponse::Ok();
+    content_headers(&mut builder, etag);
+    builder.content_type(content.content_type).body(content.data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn insc(mime_category: InscriptionMimeCategory, mime_type: &str, mime_data: &str) -> Inscription {
+        Inscription {
+            id: 1,
+            from: "0xfrom".to_string(),
+            to: "0xto".to_string(),
+            blocknumber: 1,
+            tx_hash: "0xtx".to_string(),
+            tx_index: 0,
+            mime_category,
+            mime_type: mime_type.to_string(),
+            mime_data: mime_data.to_string(),
+            timestamp: 10,
+            event_logs: vec![],
+            market_order_id: None,
+            verified: InscriptionVerifiedStatus::Successful,
+            fail_reason: None,
+            signature: None,
+            content_hash: None,
+            json: serde_json::Value::Null,
+        }
+    }
+
+    #[test]
+    fn image_without_base64_suffix_is_decoded() {
+        let decoded = inscription_content(&insc(InscriptionMimeCategory::Image, "image/png", "iVBORw==")).unwrap();
+        assert_eq!(decoded.content_type, "image/png");
+        assert_eq!(decoded.data, vec![0x89, b'P', b'N', b'G']);
+    }
+
+    #[test]
+    fn plain_payloads_are_percent_decoded() {
+        let svg = insc(InscriptionMimeCategory::Image, "image/svg+xml", "%3Csvg%3E%3C/svg%3E");
+        assert_eq!(inscription_content(&svg).unwrap().data, b"<svg></svg>");
+
+        let text = insc(InscriptionMimeCategory::Text, "text/plain", "hello%20world%");
+        let decoded = inscription_content(&text).unwrap();
+        assert_eq!(decoded.content_type, "text/plain");
+        assert_eq!(decoded.data, b"hello world%");
+    }
+
+    #[test]
+    fn base64_payloads_are_decoded() {
+        let text = insc(InscriptionMimeCategory::Text, "text/plain;base64", "aGVsbG8=");
+        let decoded = inscription_content(&text).unwrap();
+        assert_eq!(decoded.content_type, "text/plain");
+        assert_eq!(decoded.data, b"hello");
+    }
+}
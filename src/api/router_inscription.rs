@@ -1,4 +1,7 @@
-use super::{router_market::market_order_to_display, HttpResponseExt, WebData, PAGE_SIZE};
+use super::{
+    router_market::{market_order_to_display, MarketOrderResponse},
+    APIPage, APIResponse, APIResult, APIState, HttpResponseExt, ListPage, WebData,
+};
 use crate::{
     inscription::{db::*, marketplace::db::InscribeMarketDB, types::*},
     num_index,
@@ -6,6 +9,7 @@ use crate::{
 use actix_web::{get, web, web::Query, HttpResponse, Responder};
 use rocksdb::Direction;
 use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
 
 pub fn register(config: &mut web::ServiceConfig) {
     config.service(inscription);
@@ -15,48 +19,97 @@ pub fn register(config: &mut web::ServiceConfig) {
     config.service(created);
 }
 
-pub fn inscription_to_display(db: &rocksdb::DB, insc: &Inscription, include_imagedata: bool) -> serde_json::Value {
-    let mut insc_json = serde_json::to_value(insc).unwrap();
-    if let Some(_) = &insc.signature {
-        let holder = db.get_inscription_nft_holder_by_id(insc.id);
-        insc_json["owner"] = serde_json::to_value(holder).unwrap();
+#[derive(Debug, Serialize, ToSchema)]
+pub struct InscriptionResponse {
+    pub id: u64,
+    pub from: String,
+    pub to: String,
+    pub blocknumber: u64,
+    pub tx_hash: String,
+    pub tx_index: u64,
+    /// text, image, json, ...
+    pub mime_category: String,
+    pub mime_type: String,
+    /// Empty for images in lists, read them from `/content/{path}`.
+    pub mime_data: String,
+    pub timestamp: u64,
+    #[schema(value_type = Vec<Object>)]
+    pub event_logs: Vec<web3::types::Log>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub market_order_id: Option<String>,
+    /// unknown, success or fail
+    pub verified: String,
+    #[schema(value_type = Option<String>)]
+    pub fail_reason: Option<InscriptionFailReason>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content_hash: Option<String>,
+    /// Holder of an NFT.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub owner: Option<String>,
+    /// Collection of an NFT.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub collection: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub market_order_info: Option<MarketOrderResponse>,
+}
 
-        if let Some(collection) = db.get_inscription_nft_collection_by_id(insc.id) {
-            insc_json["collection"] = serde_json::to_value(collection).unwrap();
+pub fn inscription_to_display(db: &rocksdb::DB, insc: &Inscription, include_imagedata: bool) -> InscriptionResponse {
+    let mut response = inscription_fields_to_display(insc, include_imagedata);
+    // lists read the meta rows, the payloads kept in the content store are loaded here
+    let content_hidden = !include_imagedata && insc.mime_category == InscriptionMimeCategory::Image;
+    if response.mime_data.is_empty() && !content_hidden {
+        if let Some(content_hash) = &insc.content_hash {
+            response.mime_data = db.get_inscription_content(content_hash).unwrap_or_default();
         }
     }
+    if insc.signature.is_some() {
+        response.owner = db.get_inscription_nft_holder_by_id(insc.id);
+        response.collection = db.get_inscription_nft_collection_by_id(insc.id);
+    }
 
     if let Some(market_order_id) = &insc.market_order_id {
-        if let Some(market_order) = db.market_get_order_by_id(market_order_id) {
-            insc_json["market_order_info"] = market_order_to_display(&market_order);
-        }
+        response.market_order_info = db
+            .market_get_order_by_id(market_order_id)
+            .map(|order| market_order_to_display(&order));
     }
 
-    if !include_imagedata && insc.mime_category == InscriptionMimeCategory::Image {
-        insc_json["mime_data"] = serde_json::to_value("").unwrap();
-    }
+    response
+}
 
-    insc_json["mime_category"] = serde_json::to_value(match insc.mime_category {
-        InscriptionMimeCategory::Null => "null",
-        InscriptionMimeCategory::Text => "text",
-        InscriptionMimeCategory::Image => "image",
-        InscriptionMimeCategory::Transfer => "transfer",
-        InscriptionMimeCategory::Json => "json",
-        InscriptionMimeCategory::Invoke => "invoke",
-    })
-    .unwrap();
-
-    insc_json["verified"] = serde_json::to_value(match insc.verified {
-        InscriptionVerifiedStatus::Unresolved => "unknown",
-        InscriptionVerifiedStatus::Successful => "success",
-        InscriptionVerifiedStatus::Failed => "fail",
-    })
-    .unwrap();
-
-    insc_json
+/// The inscription as shown by the api, without the fields read from the db.
+pub fn inscription_fields_to_display(insc: &Inscription, include_imagedata: bool) -> InscriptionResponse {
+    let mime_data = if !include_imagedata && insc.mime_category == InscriptionMimeCategory::Image {
+        "".to_string()
+    } else {
+        insc.mime_data.clone()
+    };
+
+    InscriptionResponse {
+        id: insc.id,
+        from: insc.from.clone(),
+        to: insc.to.clone(),
+        blocknumber: insc.blocknumber,
+        tx_hash: insc.tx_hash.clone(),
+        tx_index: insc.tx_index,
+        mime_category: insc.mime_category.as_str().to_string(),
+        mime_type: insc.mime_type.clone(),
+        mime_data,
+        timestamp: insc.timestamp,
+        event_logs: insc.event_logs.clone(),
+        market_order_id: insc.market_order_id.clone(),
+        verified: insc.verified.as_str().to_string(),
+        fail_reason: insc.fail_reason,
+        signature: insc.signature.clone(),
+        content_hash: insc.content_hash.clone(),
+        owner: None,
+        collection: None,
+        market_order_info: None,
+    }
 }
 
-pub fn insc_list_to_display(db: &rocksdb::DB, insc_list: &Vec<Inscription>) -> Vec<serde_json::Value> {
+pub fn insc_list_to_display(db: &rocksdb::DB, insc_list: &Vec<Inscription>) -> Vec<InscriptionResponse> {
     let mut result = vec![];
     for insc in insc_list {
         result.push(inscription_to_display(db, insc, false));
@@ -64,14 +117,14 @@ pub fn insc_list_to_display(db: &rocksdb::DB, insc_list: &Vec<Inscription>) -> V
     result
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-struct InscriptionsParams {
+#[derive(Debug, Serialize, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct InscriptionsParams {
     id: Option<u64>,
     tx: Option<String>,
 }
 
-#[get("/inscription")]
-async fn inscription(query: Query<InscriptionsParams>, state: WebData) -> impl Responder {
+pub fn get_inscription(state: &APIState, query: &InscriptionsParams) -> APIResult {
     let db = state.db.read().unwrap();
     let result = if let Some(insc_id) = query.id {
         db.get_inscription_by_id(insc_id)
@@ -81,13 +134,31 @@ async fn inscription(query: Query<InscriptionsParams>, state: WebData) -> impl R
         None
     };
 
-    if let Some(insc) = result {
-        HttpResponse::response_data(inscription_to_display(&db, &insc, true))
-    } else {
-        HttpResponse::response_error_notfound()
+    match result {
+        Some(insc) => APIResult::data(inscription_to_display(&db, &insc, true)),
+        None => APIResult::NotFound,
     }
 }
 
+#[utoipa::path(
+    get,
+    path = "/inscription",
+    tag = "inscription",
+    params(InscriptionsParams),
+    responses((status = 200, body = APIResponse<InscriptionResponse>))
+)]
+#[get("/inscription")]
+async fn inscription(query: Query<InscriptionsParams>, state: WebData) -> impl Responder {
+    HttpResponse::response_result(get_inscription(&state, &query))
+}
+
+#[utoipa::path(
+    get,
+    path = "/inscription/{path}",
+    tag = "inscription",
+    params(("path" = String, Path, description = "Inscription id or tx")),
+    responses((status = 200, body = APIResponse<InscriptionResponse>))
+)]
 #[get("/inscription/{path}")]
 async fn inscription_path(path: web::Path<String>, state: WebData) -> impl Responder {
     let db = state.db.read().unwrap();
@@ -104,55 +175,107 @@ async fn inscription_path(path: web::Path<String>, state: WebData) -> impl Respo
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 struct RecentParams {
     page: Option<u64>,
+    cursor: Option<String>,
+    limit: Option<u64>,
 }
 
+/// Newest inscriptions first.
+#[utoipa::path(
+    get,
+    path = "/recent",
+    tag = "inscription",
+    params(RecentParams),
+    responses((status = 200, body = APIPage<InscriptionResponse>))
+)]
 #[get("/recent")]
 async fn recent(info: Query<RecentParams>, state: WebData) -> impl Responder {
     let db = state.db.read().unwrap();
-    let page = info.page.unwrap_or(1) - 1;
+    let page = match ListPage::new(KEY_INSC_INDEX_ID, info.page, &info.cursor, info.limit) {
+        Some(page) => page,
+        None => return HttpResponse::response_error(1, "Invalid cursor"),
+    };
     let top_insc_id = db.get_top_inscription_id();
     let start_key = make_index_key(KEY_INSC_INDEX_ID, num_index!(top_insc_id));
-    let insc_list = db.get_items(KEY_INSC_INDEX_ID, &start_key, page * PAGE_SIZE, PAGE_SIZE, Direction::Reverse);
-    let insc_list = db_item_val2json::<Inscription>(insc_list);
+    let insc_list = db.get_items_after(
+        KEY_INSC_INDEX_ID,
+        &start_key,
+        page.after(),
+        page.skip,
+        page.limit,
+        Direction::Reverse,
+    );
+    let next_cursor = page.next_cursor(insc_list.len(), insc_list.last().map(|(key, _)| key));
+    let insc_list = db_item_val2row::<Inscription>(insc_list);
 
-    HttpResponse::response_data(insc_list_to_display(&db, &insc_list))
+    HttpResponse::response_page(insc_list_to_display(&db, &insc_list), next_cursor)
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 struct TransactionsParams {
     page: Option<u64>,
+    cursor: Option<String>,
+    limit: Option<u64>,
     address: String,
 }
 
+/// Inscriptions sent or received by an address.
+#[utoipa::path(
+    get,
+    path = "/transactions",
+    tag = "inscription",
+    params(TransactionsParams),
+    responses((status = 200, body = APIPage<InscriptionResponse>))
+)]
 #[get("/transactions")]
 async fn transactions(info: Query<TransactionsParams>, state: WebData) -> impl Responder {
     let db = state.db.read().unwrap();
-    let page = info.page.unwrap_or(1) - 1;
     let prefix = make_index_key(KEY_INSC_INDEX_ADDRESS, &info.address.to_lowercase());
-    let key_list = db.get_item_keys(&prefix, &prefix, page * PAGE_SIZE, PAGE_SIZE, Direction::Forward);
+    let page = match ListPage::new(&prefix, info.page, &info.cursor, info.limit) {
+        Some(page) => page,
+        None => return HttpResponse::response_error(1, "Invalid cursor"),
+    };
+    let key_list = db.get_item_keys_after(&prefix, &prefix, page.after(), page.skip, page.limit, Direction::Forward);
+    let next_cursor = page.next_cursor(key_list.len(), key_list.last());
     let id_list = db_index2id_desc(key_list);
-    let insc_list = db.get_inscriptions_by_id(&id_list);
+    let insc_list = db.get_inscriptions_meta_by_id(&id_list);
 
-    HttpResponse::response_data(insc_list_to_display(&db, &insc_list))
+    HttpResponse::response_page(insc_list_to_display(&db, &insc_list), next_cursor)
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 struct CreatedParams {
     page: Option<u64>,
+    cursor: Option<String>,
+    limit: Option<u64>,
     address: String,
 }
 
+/// Inscriptions created by an address.
+#[utoipa::path(
+    get,
+    path = "/created",
+    tag = "inscription",
+    params(CreatedParams),
+    responses((status = 200, body = APIPage<InscriptionResponse>))
+)]
 #[get("/created")]
 async fn created(info: Query<CreatedParams>, state: WebData) -> impl Responder {
     let db = state.db.read().unwrap();
-    let page = info.page.unwrap_or(1) - 1;
     let prefix = make_index_key(KEY_INSC_INDEX_CREATER, &info.address.to_lowercase());
-    let key_list = db.get_item_keys(&prefix, &prefix, page * PAGE_SIZE, PAGE_SIZE, Direction::Forward);
+    let page = match ListPage::new(&prefix, info.page, &info.cursor, info.limit) {
+        Some(page) => page,
+        None => return HttpResponse::response_error(1, "Invalid cursor"),
+    };
+    let key_list = db.get_item_keys_after(&prefix, &prefix, page.after(), page.skip, page.limit, Direction::Forward);
+    let next_cursor = page.next_cursor(key_list.len(), key_list.last());
     let id_list = db_index2id_desc(key_list);
-    let insc_list = db.get_inscriptions_by_id(&id_list);
+    let insc_list = db.get_inscriptions_meta_by_id(&id_list);
 
-    HttpResponse::response_data(insc_list_to_display(&db, &insc_list))
+    HttpResponse::response_page(insc_list_to_display(&db, &insc_list), next_cursor)
 }
@@ -1,30 +1,46 @@
-use super::{HttpResponseExt, WebData, PAGE_SIZE};
-use crate::inscription::{
-    db::*,
-    marketplace::{db::*, types::*},
+use super::{APIPage, APIResponse, APIResult, APIState, HttpResponseExt, ListPage, WebData};
+use crate::{
+    config::MARKET_ADDRESS_LIST,
+    global::get_timestamp,
+    inscription::{
+        db::*,
+        marketplace::{db::*, types::*},
+    },
+    num_index,
 };
 use actix_web::{get, web, web::Query, HttpResponse, Responder};
 use rocksdb::Direction;
 use serde::{Deserialize, Serialize};
-use serde_json::json;
+use utoipa::{IntoParams, ToSchema};
+
+/// Trades read per side for a pnl, the pnl of a busier address is refused rather than computed from part of them.
+const MARKET_PNL_TRADES_MAX: u64 = 10000;
 
 pub fn register(config: &mut web::ServiceConfig) {
     config.service(market_orders_all);
     config.service(market_orders_list);
     config.service(market_orders);
     config.service(market_order);
+    config.service(market_candles);
+    config.service(market_stats_market);
+    config.service(market_stats_creator);
+    config.service(market_trades);
+    config.service(market_pnl);
 }
 
-async fn market_get_order_list(state: WebData, page: u64, prefix: &str) -> Vec<serde_json::value::Value> {
+async fn market_get_order_list(state: WebData, page: &ListPage, prefix: &str) -> (Vec<MarketOrderResponse>, Option<String>) {
     let db = state.db.read().unwrap();
-    let key_list = db.get_item_keys(&prefix, &prefix, page * PAGE_SIZE, PAGE_SIZE, Direction::Forward);
+    let key_list = db.get_item_keys_after(prefix, prefix, page.after(), page.skip, page.limit, Direction::Forward);
+    let next_cursor = page.next_cursor(key_list.len(), key_list.last());
     let id_list = db_index2str(key_list);
     let mut order_list = Vec::new();
     for id in &id_list {
-        let order = db.market_get_order_by_id(&id.to_string()).unwrap();
-        order_list.push(market_order_to_display(&order));
+        // an index key without its order is skipped
+        if let Some(order) = db.market_get_order_by_id(&id.to_string()) {
+            order_list.push(market_order_to_display(&order));
+        }
     }
-    order_list
+    (order_list, next_cursor)
 }
 
 fn str_option(s: &str) -> Option<String> {
@@ -35,85 +51,142 @@ fn str_option(s: &str) -> Option<String> {
     }
 }
 
-fn order_status_str(status: &MarketOrderStatus) -> String {
+pub fn order_status_str(status: &MarketOrderStatus) -> String {
     match status {
         MarketOrderStatus::Init => "init".to_string(),
         MarketOrderStatus::Open => "open".to_string(),
         MarketOrderStatus::Close => "close".to_string(),
         MarketOrderStatus::Canceled => "canceled".to_string(),
+        MarketOrderStatus::Expired => "expired".to_string(),
     }
 }
 
-pub fn market_order_to_display(order: &MarketOrder) -> serde_json::Value {
-    match order.order_type {
-        MarketOrderType::Token => json!({
-            "order_type": "token",
-            "order_id": order.order_id,
-            "from": order.from,
-            "to": order.to,
-            "tick": order.tick,
-            "nft_id": serde_json::Value::Null,
-            "nft_tx": serde_json::Value::Null,
-            "amount": order.amount.to_string(),
-            "total_price": order.total_price.to_string(),
-            "unit_price": order.unit_price.to_string(),
-            "tx": order.tx,
-            "tx_setprice": str_option(&order.tx_setprice),
-            "tx_cancel": str_option(&order.tx_cancel),
-            "tx_close": str_option(&order.tx_close),
-            "blocknumber": order.blocknumber.to_string(),
-            "timestamp": order.timestamp.to_string(),
-            "order_status": order_status_str(&order.order_status),
-            "buyer": str_option(&order.buyer),
-        }),
-        MarketOrderType::NFT => json!( {
-            "order_type": "nft",
-            "order_id": order.order_id,
-            "from": order.from,
-            "to": order.to,
-            "tick": serde_json::Value::Null,
-            "nft_id": order.nft_id.to_string(),
-            "nft_tx": order.nft_tx,
-            "amount": "1",
-            "total_price": order.total_price.to_string(),
-            "unit_price": order.total_price.to_string(),
-            "tx": order.tx,
-            "tx_setprice": str_option(&order.tx_setprice),
-            "tx_cancel": str_option(&order.tx_cancel),
-            "tx_close": str_option(&order.tx_close),
-            "blocknumber": order.blocknumber.to_string(),
-            "timestamp": order.timestamp.to_string(),
-            "order_status": order_status_str(&order.order_status),
-            "buyer": str_option(&order.buyer),
-        }),
+/// Amounts and prices are decimal strings, ids, blocknumbers and times are numbers.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct MarketOrderResponse {
+    /// token, nft, token_bid or nft_bid
+    pub order_type: String,
+    pub order_id: String,
+    pub from: String,
+    pub to: String,
+    pub tick: Option<String>,
+    pub nft_id: Option<u64>,
+    pub nft_tx: Option<String>,
+    pub collection: Option<String>,
+    pub amount: String,
+    pub filled_amount: Option<String>,
+    pub total_price: String,
+    pub unit_price: String,
+    pub fee: String,
+    pub royalty: String,
+    pub seller_proceeds: String,
+    pub tx: String,
+    pub tx_setprice: Option<String>,
+    pub tx_cancel: Option<String>,
+    pub tx_close: Option<String>,
+    pub blocknumber: u64,
+    pub timestamp: u64,
+    pub expire_at: Option<u64>,
+    /// init, open, close, canceled or expired
+    pub order_status: String,
+    pub buyer: Option<String>,
+    pub seller: Option<String>,
+}
+
+pub fn market_order_to_display(order: &MarketOrder) -> MarketOrderResponse {
+    let is_token = matches!(order.order_type, MarketOrderType::Token | MarketOrderType::TokenBid);
+
+    MarketOrderResponse {
+        order_type: market_order_type_str(&order.order_type).to_string(),
+        order_id: order.order_id.clone(),
+        from: order.from.clone(),
+        to: order.to.clone(),
+        tick: is_token.then(|| order.tick.clone()),
+        nft_id: (!is_token && order.nft_id > 0).then_some(order.nft_id),
+        nft_tx: if is_token { None } else { str_option(&order.nft_tx) },
+        collection: str_option(&order.collection),
+        amount: if is_token { order.amount.to_string() } else { "1".to_string() },
+        filled_amount: is_token.then(|| order.filled_amount.to_string()),
+        total_price: order.total_price.to_string(),
+        unit_price: if is_token { order.unit_price } else { order.total_price }.to_string(),
+        fee: order.fee.to_string(),
+        royalty: order.royalty.to_string(),
+        seller_proceeds: order.seller_proceeds.to_string(),
+        tx: order.tx.clone(),
+        tx_setprice: str_option(&order.tx_setprice),
+        tx_cancel: str_option(&order.tx_cancel),
+        tx_close: str_option(&order.tx_close),
+        blocknumber: order.blocknumber,
+        timestamp: order.timestamp,
+        expire_at: (order.expire_at > 0).then_some(order.expire_at),
+        order_status: order_status_str(&order.order_status),
+        buyer: str_option(&order.buyer),
+        seller: str_option(&order.seller),
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+pub fn market_order_type_str(order_type: &MarketOrderType) -> &'static str {
+    match order_type {
+        MarketOrderType::NFT => "nft",
+        MarketOrderType::Token => "token",
+        MarketOrderType::TokenBid => "token_bid",
+        MarketOrderType::NFTBid => "nft_bid",
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 struct MarketOrderAllParams {
     page: Option<u64>,
+    cursor: Option<String>,
+    limit: Option<u64>,
     tick: Option<String>,
 }
 
+/// Orders of every market, newest first.
+#[utoipa::path(
+    get,
+    path = "/market_orders_all",
+    tag = "market",
+    params(MarketOrderAllParams),
+    responses((status = 200, body = APIPage<MarketOrderResponse>))
+)]
 #[get("/market_orders_all")]
 async fn market_orders_all(info: Query<MarketOrderAllParams>, state: WebData) -> impl Responder {
     let prefix = match &info.tick {
         Some(tick) => make_index_key(KEY_MARKET_ORDER_INDEX_TICK_TIME, tick) + ":",
         None => KEY_MARKET_ORDER_INDEX_TIME.to_string(),
     };
-    let order_list = market_get_order_list(state, info.page.unwrap_or(1) - 1, &prefix).await;
+    let page = match ListPage::new(&prefix, info.page, &info.cursor, info.limit) {
+        Some(page) => page,
+        None => return HttpResponse::response_error(1, "Invalid cursor"),
+    };
+    let (order_list, next_cursor) = market_get_order_list(state, &page, &prefix).await;
 
-    HttpResponse::response_data(order_list)
+    HttpResponse::response_page(order_list, next_cursor)
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 struct MarketOrderListParams {
     page: Option<u64>,
+    cursor: Option<String>,
+    limit: Option<u64>,
     order_type: String,
     order_status: String,
     tick: Option<String>,
+    collection: Option<String>,
+    nft_id: Option<u64>,
 }
 
+/// Open listings and bids by price, or closed orders of a tick.
+#[utoipa::path(
+    get,
+    path = "/market_orders_list",
+    tag = "market",
+    params(MarketOrderListParams),
+    responses((status = 200, body = APIPage<MarketOrderResponse>))
+)]
 #[get("/market_orders_list")]
 async fn market_orders_list(info: Query<MarketOrderListParams>, state: WebData) -> impl Responder {
     let prefix = if info.order_type == "token" {
@@ -130,22 +203,51 @@ async fn market_orders_list(info: Query<MarketOrderListParams>, state: WebData)
         }
     } else if info.order_type == "nft" {
         KEY_MARKET_ORDER_INDEX_NFT.to_string()
+    } else if info.order_type == "token_bid" && info.order_status == "open" {
+        if let Some(tick) = &info.tick {
+            make_index_key(KEY_MARKET_BID_INDEX_TICK_PRICE, tick) + ":"
+        } else {
+            return HttpResponse::response_error(1, "Invalid params");
+        }
+    } else if info.order_type == "nft_bid" && info.order_status == "open" {
+        if let Some(collection) = &info.collection {
+            make_index_key(KEY_MARKET_BID_INDEX_COLLECTION_PRICE, collection.to_lowercase()) + ":"
+        } else if let Some(nft_id) = info.nft_id {
+            make_index_key(KEY_MARKET_BID_INDEX_NFT_PRICE, num_index!(nft_id)) + ":"
+        } else {
+            return HttpResponse::response_error(1, "Invalid params");
+        }
     } else {
         return HttpResponse::response_error(1, "Invalid params");
     };
 
-    let order_list = market_get_order_list(state, info.page.unwrap_or(1) - 1, &prefix).await;
+    let page = match ListPage::new(&prefix, info.page, &info.cursor, info.limit) {
+        Some(page) => page,
+        None => return HttpResponse::response_error(1, "Invalid cursor"),
+    };
+    let (order_list, next_cursor) = market_get_order_list(state, &page, &prefix).await;
 
-    HttpResponse::response_data(order_list)
+    HttpResponse::response_page(order_list, next_cursor)
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 struct MarketMyOpenParams {
     page: Option<u64>,
+    cursor: Option<String>,
+    limit: Option<u64>,
     address: String,
     order_status: Option<String>,
 }
 
+/// Orders of an address, `order_status` is open (default) or close.
+#[utoipa::path(
+    get,
+    path = "/market_orders",
+    tag = "market",
+    params(MarketMyOpenParams),
+    responses((status = 200, body = APIPage<MarketOrderResponse>))
+)]
 #[get("/market_orders")]
 async fn market_orders(info: Query<MarketMyOpenParams>, state: WebData) -> impl Responder {
     let prefix_index = match &info.order_status {
@@ -156,22 +258,329 @@ async fn market_orders(info: Query<MarketMyOpenParams>, state: WebData) -> impl
     };
 
     let prefix = make_index_key(&prefix_index, info.address.to_lowercase());
-    let order_list = market_get_order_list(state, info.page.unwrap_or(1) - 1, &prefix).await;
+    let page = match ListPage::new(&prefix, info.page, &info.cursor, info.limit) {
+        Some(page) => page,
+        None => return HttpResponse::response_error(1, "Invalid cursor"),
+    };
+    let (order_list, next_cursor) = market_get_order_list(state, &page, &prefix).await;
 
-    HttpResponse::response_data(order_list)
+    HttpResponse::response_page(order_list, next_cursor)
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-struct MarketOrderParams {
+#[derive(Debug, Serialize, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct MarketOrderParams {
     order_id: String,
 }
 
+pub fn get_market_order(state: &APIState, info: &MarketOrderParams) -> APIResult {
+    let db = state.db.read().unwrap();
+    match db.market_get_order_by_id(&info.order_id) {
+        Some(order) => APIResult::data(market_order_to_display(&order)),
+        None => APIResult::NotFound,
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/market_order",
+    tag = "market",
+    params(MarketOrderParams),
+    responses((status = 200, body = APIResponse<MarketOrderResponse>))
+)]
 #[get("/market_order")]
 async fn market_order(info: Query<MarketOrderParams>, state: WebData) -> impl Responder {
+    HttpResponse::response_result(get_market_order(&state, &info))
+}
+
+#[derive(Debug, Serialize, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct MarketCandlesParams {
+    tick: String,
+    interval: String,
+    from: Option<u64>,
+    to: Option<u64>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct MarketCandleResponse {
+    pub time: u64,
+    pub open: String,
+    pub high: String,
+    pub low: String,
+    pub close: String,
+    pub volume: String,
+    pub amount: String,
+    pub txs: u64,
+}
+
+/// Unit price candles of a tick, `interval` is one of the candle intervals, e.g. 1h.
+#[utoipa::path(
+    get,
+    path = "/market_candles",
+    tag = "market",
+    params(MarketCandlesParams),
+    responses((status = 200, body = APIResponse<Vec<MarketCandleResponse>>))
+)]
+#[get("/market_candles")]
+async fn market_candles(info: Query<MarketCandlesParams>, state: WebData) -> impl Responder {
+    let seconds = match market_candle_interval(&info.interval) {
+        Some(seconds) => seconds,
+        None => return HttpResponse::response_error(1, "Invalid params"),
+    };
+
+    let to = info.to.unwrap_or_else(get_timestamp);
+    let from = info.from.unwrap_or(to.saturating_sub(seconds * MARKET_CANDLE_MAX_COUNT));
+    if from > to {
+        return HttpResponse::response_error(1, "Invalid params");
+    }
+
+    let db = state.db.read().unwrap();
+    let candles = db.market_get_candles(&info.tick, &info.interval, from, to, MARKET_CANDLE_MAX_COUNT);
+    let candle_list: Vec<MarketCandleResponse> = candles
+        .iter()
+        .map(|candle| MarketCandleResponse {
+            time: candle.time,
+            open: candle.open.to_string(),
+            high: candle.high.to_string(),
+            low: candle.low.to_string(),
+            close: candle.close.to_string(),
+            volume: candle.volume.to_string(),
+            amount: candle.amount.to_string(),
+            txs: candle.txs,
+        })
+        .collect();
+
+    HttpResponse::response_data(candle_list)
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct MarketRevenueResponse {
+    pub address: String,
+    pub volume: String,
+    pub fee: String,
+    pub royalty: String,
+    pub seller_proceeds: String,
+    pub txs: u64,
+}
+
+fn market_revenue_to_display(address: &str, revenue: &MarketRevenue) -> MarketRevenueResponse {
+    MarketRevenueResponse {
+        address: address.to_string(),
+        volume: revenue.volume.to_string(),
+        fee: revenue.fee.to_string(),
+        royalty: revenue.royalty.to_string(),
+        seller_proceeds: revenue.seller_proceeds.to_string(),
+        txs: revenue.txs,
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct MarketStatsParams {
+    address: Option<String>,
+}
+
+/// Revenue of every market, or a single revenue object of `address` when it is given.
+#[utoipa::path(
+    get,
+    path = "/market_stats_market",
+    tag = "market",
+    params(MarketStatsParams),
+    responses((status = 200, body = APIResponse<Vec<MarketRevenueResponse>>))
+)]
+#[get("/market_stats_market")]
+async fn market_stats_market(info: Query<MarketStatsParams>, state: WebData) -> impl Responder {
     let db = state.db.read().unwrap();
-    let order = db.market_get_order_by_id(&info.order_id);
-    match order {
-        Some(order) => HttpResponse::response_data(market_order_to_display(&order)),
-        None => return HttpResponse::response_error_notfound(),
+    match &info.address {
+        Some(address) => {
+            let address = address.to_lowercase();
+            let revenue = db.market_get_revenue(KEY_MARKET_REVENUE_MARKET, &address);
+            HttpResponse::response_data(market_revenue_to_display(&address, &revenue))
+        }
+        None => {
+            let stats_list: Vec<MarketRevenueResponse> = MARKET_ADDRESS_LIST
+                .iter()
+                .map(|address| market_revenue_to_display(address, &db.market_get_revenue(KEY_MARKET_REVENUE_MARKET, address)))
+                .collect();
+            HttpResponse::response_data(stats_list)
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct MarketCreatorStatsParams {
+    address: String,
+}
+
+/// Royalties earned by a creator.
+#[utoipa::path(
+    get,
+    path = "/market_stats_creator",
+    tag = "market",
+    params(MarketCreatorStatsParams),
+    responses((status = 200, body = APIResponse<MarketRevenueResponse>))
+)]
+#[get("/market_stats_creator")]
+async fn market_stats_creator(info: Query<MarketCreatorStatsParams>, state: WebData) -> impl Responder {
+    let db = state.db.read().unwrap();
+    let address = info.address.to_lowercase();
+    let revenue = db.market_get_revenue(KEY_MARKET_REVENUE_CREATOR, &address);
+    HttpResponse::response_data(market_revenue_to_display(&address, &revenue))
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct MarketTradeResponse {
+    pub tx: String,
+    pub order_id: String,
+    pub order_type: String,
+    pub tick: Option<String>,
+    pub nft_id: Option<u64>,
+    pub seller: String,
+    pub buyer: String,
+    pub amount: String,
+    pub total_price: String,
+    pub unit_price: String,
+    pub fee: String,
+    pub royalty: String,
+    pub seller_proceeds: String,
+    pub market: Option<String>,
+    pub timestamp: u64,
+}
+
+fn market_trade_to_display(trade: &MarketTrade) -> MarketTradeResponse {
+    MarketTradeResponse {
+        tx: trade.tx.clone(),
+        order_id: trade.order_id.clone(),
+        order_type: market_order_type_str(&trade.order_type).to_string(),
+        tick: str_option(&trade.tick),
+        nft_id: (trade.nft_id > 0).then_some(trade.nft_id),
+        seller: trade.seller.clone(),
+        buyer: trade.buyer.clone(),
+        amount: trade.amount.to_string(),
+        total_price: trade.total_price.to_string(),
+        unit_price: trade.unit_price.to_string(),
+        fee: trade.fee.to_string(),
+        royalty: trade.royalty.to_string(),
+        seller_proceeds: trade.seller_proceeds.to_string(),
+        market: str_option(&trade.market),
+        timestamp: trade.timestamp,
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct MarketTradesParams {
+    page: Option<u64>,
+    cursor: Option<String>,
+    limit: Option<u64>,
+    address: String,
+    side: String,
+}
+
+/// Trades of an address, `side` is buy or sell.
+#[utoipa::path(
+    get,
+    path = "/market_trades",
+    tag = "market",
+    params(MarketTradesParams),
+    responses((status = 200, body = APIPage<MarketTradeResponse>))
+)]
+#[get("/market_trades")]
+async fn market_trades(info: Query<MarketTradesParams>, state: WebData) -> impl Responder {
+    let index = match info.side.as_str() {
+        "buy" => KEY_MARKET_TRADE_INDEX_BUYER,
+        "sell" => KEY_MARKET_TRADE_INDEX_SELLER,
+        _ => return HttpResponse::response_error(1, "Invalid params"),
+    };
+
+    let prefix = make_index_key(index, info.address.to_lowercase()) + ":";
+    let page = match ListPage::new(&prefix, info.page, &info.cursor, info.limit) {
+        Some(page) => page,
+        None => return HttpResponse::response_error(1, "Invalid cursor"),
+    };
+
+    let db = state.db.read().unwrap();
+    let key_list = db.get_item_keys_after(&prefix, &prefix, page.after(), page.skip, page.limit, Direction::Forward);
+    let next_cursor = page.next_cursor(key_list.len(), key_list.last());
+    let trades = db.market_get_trades_by_keys(&prefix, &key_list);
+    let trade_list: Vec<MarketTradeResponse> = trades.iter().map(market_trade_to_display).collect();
+
+    HttpResponse::response_page(trade_list, next_cursor)
+}
+
+#[derive(Debug, Serialize, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct MarketPnlParams {
+    address: String,
+    tick: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct MarketPnlResponse {
+    pub tick: String,
+    pub bought_amount: String,
+    pub bought_cost: String,
+    pub sold_amount: String,
+    pub sold_proceeds: String,
+    pub position_amount: String,
+    pub position_cost: String,
+    pub realized_pnl: String,
+}
+
+/// Profit and loss of an address' token trades, per tick. Refused for an address with more than 10000 buys or sells.
+#[utoipa::path(
+    get,
+    path = "/market_pnl",
+    tag = "market",
+    params(MarketPnlParams),
+    responses((status = 200, body = APIResponse<Vec<MarketPnlResponse>>))
+)]
+#[get("/market_pnl")]
+async fn market_pnl(info: Query<MarketPnlParams>, state: WebData) -> impl Responder {
+    let db = state.db.read().unwrap();
+    let address = info.address.to_lowercase();
+    let is_token_trade =
+        |trade: &MarketTrade| !trade.tick.is_empty() && info.tick.as_ref().is_none_or(|tick| *tick == trade.tick);
+
+    let mut trades: Vec<(bool, MarketTrade)> = Vec::new();
+    for (index, is_buy) in [(KEY_MARKET_TRADE_INDEX_BUYER, true), (KEY_MARKET_TRADE_INDEX_SELLER, false)] {
+        let side_trades = db.market_get_address_trades(index, &address, 0, MARKET_PNL_TRADES_MAX + 1);
+        if side_trades.len() as u64 > MARKET_PNL_TRADES_MAX {
+            return HttpResponse::response_error(1, "Too many trades");
+        }
+        trades.extend(side_trades.into_iter().filter(is_token_trade).map(|trade| (is_buy, trade)));
     }
+    // oldest first, buys before sells of the same second
+    trades.sort_by(|(a_buy, a), (b_buy, b)| a.timestamp.cmp(&b.timestamp).then(b_buy.cmp(a_buy)));
+
+    let mut pnl_map: std::collections::BTreeMap<String, MarketPnl> = std::collections::BTreeMap::new();
+    for (is_buy, trade) in &trades {
+        let pnl = pnl_map.entry(trade.tick.clone()).or_insert_with(|| MarketPnl {
+            tick: trade.tick.clone(),
+            ..Default::default()
+        });
+        if *is_buy {
+            pnl.buy(trade.amount, trade.total_price);
+        } else {
+            pnl.sell(trade.amount, trade.seller_proceeds);
+        }
+    }
+
+    let pnl_list: Vec<MarketPnlResponse> = pnl_map
+        .values()
+        .map(|pnl| MarketPnlResponse {
+            tick: pnl.tick.clone(),
+            bought_amount: pnl.bought_amount.to_string(),
+            bought_cost: pnl.bought_cost.to_string(),
+            sold_amount: pnl.sold_amount.to_string(),
+            sold_proceeds: pnl.sold_proceeds.to_string(),
+            position_amount: pnl.position_amount.to_string(),
+            position_cost: pnl.position_cost.to_string(),
+            realized_pnl: pnl.realized.to_string(),
+        })
+        .collect();
+
+    HttpResponse::response_data(pnl_list)
 }
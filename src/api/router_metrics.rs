@@ -0,0 +1,42 @@
+use crate::metrics::API_REQUEST_DURATION;
+use actix_web::{
+    dev::{Service, ServiceRequest, ServiceResponse},
+    get, web, Error, HttpResponse, Responder,
+};
+use futures_util::future::LocalBoxFuture;
+use prometheus::{Encoder, TextEncoder};
+use std::time::Instant;
+
+pub fn register(config: &mut web::ServiceConfig) {
+    config.service(metrics);
+}
+
+/// Prometheus text format of the default registry.
+#[get("/metrics")]
+async fn metrics() -> impl Responder {
+    let encoder = TextEncoder::new();
+    let body = encoder.encode_to_string(&prometheus::gather()).unwrap();
+    HttpResponse::Ok().content_type(encoder.format_type()).body(body)
+}
+
+/// Request latency by route pattern, wrapped around the app with `wrap_fn`.
+pub fn observe<S, B>(req: ServiceRequest, srv: &S) -> LocalBoxFuture<'static, Result<ServiceResponse<B>, Error>>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    let timer = Instant::now();
+    let method = req.method().to_string();
+    let future = srv.call(req);
+
+    Box::pin(async move {
+        let response = future.await?;
+        // the pattern rather than the path, so ids and ticks do not become labels
+        let route = response.request().match_pattern().unwrap_or("unmatched".to_string());
+        API_REQUEST_DURATION
+            .with_label_values(&[&method, &route, response.status().as_str()])
+            .observe(timer.elapsed().as_secs_f64());
+        Ok(response)
+    })
+}
@@ -1,12 +1,16 @@
-use super::{router_inscription::insc_list_to_display, HttpResponseExt, WebData, PAGE_SIZE};
+use super::{
+    router_content::inscription_content,
+    router_inscription::{insc_list_to_display, InscriptionResponse},
+    APIPage, HttpResponseExt, ListPage, WebData,
+};
 use crate::{
     inscription::{db::*, types::*},
     num_index,
 };
 use actix_web::{get, web, web::Query, HttpResponse, Responder};
-use base64::{engine::general_purpose, Engine as _};
 use rocksdb::Direction;
 use serde::{Deserialize, Serialize};
+use utoipa::IntoParams;
 
 pub fn register(config: &mut web::ServiceConfig) {
     config.service(nft_recent);
@@ -16,36 +20,63 @@ pub fn register(config: &mut web::ServiceConfig) {
     config.service(nft_transfers);
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 struct RecentNFTsParams {
     page: Option<u64>,
+    cursor: Option<String>,
+    limit: Option<u64>,
     include_image_data: Option<bool>,
 }
 
+/// NFTs, newest first.
+#[utoipa::path(
+    get,
+    path = "/nft_recent",
+    tag = "nft",
+    params(RecentNFTsParams),
+    responses((status = 200, body = APIPage<InscriptionResponse>))
+)]
 #[get("/nft_recent")]
 async fn nft_recent(info: Query<RecentNFTsParams>, state: WebData) -> impl Responder {
     let db = state.db.read().unwrap();
-    let page = info.page.unwrap_or(1) - 1;
-    let key_list = db.get_item_keys(
+    let page = match ListPage::new(KEY_INSC_INDEX_NFT_ID, info.page, &info.cursor, info.limit) {
+        Some(page) => page,
+        None => return HttpResponse::response_error(1, "Invalid cursor"),
+    };
+    let key_list = db.get_item_keys_after(
         KEY_INSC_INDEX_NFT_ID,
         KEY_INSC_INDEX_NFT_ID,
-        page * PAGE_SIZE,
-        PAGE_SIZE,
+        page.after(),
+        page.skip,
+        page.limit,
         Direction::Forward,
     );
+    let next_cursor = page.next_cursor(key_list.len(), key_list.last());
     let id_list = db_index2id_desc(key_list);
-    let insc_list = db.get_inscriptions_by_id(&id_list);
+    let insc_list = db.get_inscriptions_meta_by_id(&id_list);
 
-    HttpResponse::response_data(insc_list_to_display(&db, &insc_list))
+    HttpResponse::response_page(insc_list_to_display(&db, &insc_list), next_cursor)
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 struct NFTTransfersParams {
     page: Option<u64>,
+    cursor: Option<String>,
+    limit: Option<u64>,
     tx: Option<String>,
     id: Option<u64>,
 }
 
+/// Transfers of an NFT given by `id` or `tx`, newest first.
+#[utoipa::path(
+    get,
+    path = "/nft_transfers",
+    tag = "nft",
+    params(NFTTransfersParams),
+    responses((status = 200, body = APIPage<InscriptionResponse>))
+)]
 #[get("/nft_transfers")]
 async fn nft_transfers(info: Query<NFTTransfersParams>, state: WebData) -> impl Responder {
     let db = state.db.read().unwrap();
@@ -62,56 +93,103 @@ async fn nft_transfers(info: Query<NFTTransfersParams>, state: WebData) -> impl
         None => return HttpResponse::response_error_notfound(),
     };
 
-    let page = info.page.unwrap_or(1) - 1;
     let prefix = make_index_key(KEY_INSC_NFT_TRANS_INDEX_ID, num_index!(nft_insc.id)) + ":";
-    let key_list = db.get_item_keys(&prefix, &prefix, page * PAGE_SIZE, PAGE_SIZE, Direction::Forward);
+    let page = match ListPage::new(&prefix, info.page, &info.cursor, info.limit) {
+        Some(page) => page,
+        None => return HttpResponse::response_error(1, "Invalid cursor"),
+    };
+    let key_list = db.get_item_keys_after(&prefix, &prefix, page.after(), page.skip, page.limit, Direction::Forward);
+    let next_cursor = page.next_cursor(key_list.len(), key_list.last());
     let transfer_id_list = db_index2id_desc(key_list);
-    let transfer_list = db.get_inscriptions_by_id(&transfer_id_list);
+    let transfer_list = db.get_inscriptions_meta_by_id(&transfer_id_list);
 
-    HttpResponse::response_data(insc_list_to_display(&db, &transfer_list))
+    HttpResponse::response_page(insc_list_to_display(&db, &transfer_list), next_cursor)
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 struct CollectionsParams {
     page: Option<u64>,
+    cursor: Option<String>,
+    limit: Option<u64>,
 }
 
+/// Collection inscriptions, newest first.
+#[utoipa::path(
+    get,
+    path = "/nft_collections",
+    tag = "nft",
+    params(CollectionsParams),
+    responses((status = 200, body = APIPage<InscriptionResponse>))
+)]
 #[get("/nft_collections")]
 async fn nft_collections(info: Query<CollectionsParams>, state: WebData) -> impl Responder {
     let db = state.db.read().unwrap();
-    let page = info.page.unwrap_or(1) - 1;
-    let key_list = db.get_item_keys(
+    let page = match ListPage::new(KEY_INSC_NFT_COLL_INDEX_ID, info.page, &info.cursor, info.limit) {
+        Some(page) => page,
+        None => return HttpResponse::response_error(1, "Invalid cursor"),
+    };
+    let key_list = db.get_item_keys_after(
         KEY_INSC_NFT_COLL_INDEX_ID,
         KEY_INSC_NFT_COLL_INDEX_ID,
-        page * PAGE_SIZE,
-        PAGE_SIZE,
+        page.after(),
+        page.skip,
+        page.limit,
         Direction::Forward,
     );
+    let next_cursor = page.next_cursor(key_list.len(), key_list.last());
     let id_list = db_index2id_desc(key_list);
     let insc_list = db.get_inscriptions_by_id(&id_list);
 
-    HttpResponse::response_data(insc_list)
+    HttpResponse::response_page(insc_list_to_display(&db, &insc_list), next_cursor)
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 struct NFTsParams {
     page: Option<u64>,
+    cursor: Option<String>,
+    limit: Option<u64>,
     address: String,
     include_image_data: Option<bool>,
 }
 
+/// NFTs held by an address.
+#[utoipa::path(
+    get,
+    path = "/nfts",
+    tag = "nft",
+    params(NFTsParams),
+    responses((status = 200, body = APIPage<InscriptionResponse>))
+)]
 #[get("/nfts")]
 async fn nfts(info: Query<NFTsParams>, state: WebData) -> impl Responder {
     let db = state.db.read().unwrap();
-    let page = info.page.unwrap_or(1) - 1;
     let prefix = make_index_key(KEY_INSC_NFT_INDEX_HOLDER_ADDRESS, &info.address.to_lowercase());
-    let key_list = db.get_item_keys(&prefix, &prefix, page * PAGE_SIZE, PAGE_SIZE, Direction::Forward);
+    let page = match ListPage::new(&prefix, info.page, &info.cursor, info.limit) {
+        Some(page) => page,
+        None => return HttpResponse::response_error(1, "Invalid cursor"),
+    };
+    let key_list = db.get_item_keys_after(&prefix, &prefix, page.after(), page.skip, page.limit, Direction::Forward);
+    let next_cursor = page.next_cursor(key_list.len(), key_list.last());
     let id_list = db_index2id_desc(key_list);
-    let insc_list = db.get_inscriptions_by_id(&id_list);
+    let insc_list = db.get_inscriptions_meta_by_id(&id_list);
 
-    HttpResponse::response_data(insc_list_to_display(&db, &insc_list))
+    HttpResponse::response_page(insc_list_to_display(&db, &insc_list), next_cursor)
 }
 
+/// Content of an image or text NFT.
+#[utoipa::path(
+    get,
+    path = "/nft/{path}",
+    tag = "nft",
+    params(("path" = String, Path, description = "Inscription id or tx")),
+    responses(
+        (status = 200, description = "The NFT content with its mime type", content_type = "application/octet-stream"),
+        (status = 400, description = "Not an NFT"),
+        (status = 404, description = "Inscription not found")
+    )
+)]
 #[get("/nft/{path}")]
 async fn nft(path: web::Path<String>, state: WebData) -> impl Responder {
     let db = state.db.read().unwrap();
@@ -123,13 +201,10 @@ async fn nft(path: web::Path<String>, state: WebData) -> impl Responder {
 
     match result {
         Some(insc) => match insc.mime_category {
-            InscriptionMimeCategory::Image => {
-                let bytes = general_purpose::STANDARD.decode(&insc.mime_data).unwrap();
-                HttpResponse::Ok().content_type(insc.mime_type).body(bytes)
-            }
-            InscriptionMimeCategory::Text => HttpResponse::Ok()
-                .content_type("text/plain; charset=utf-8")
-                .body(insc.mime_data),
+            InscriptionMimeCategory::Image | InscriptionMimeCategory::Text => match inscription_content(&insc) {
+                Some(content) => HttpResponse::Ok().content_type(content.content_type).body(content.data),
+                None => HttpResponse::BadRequest().body("Invalid NFT content"),
+            },
             _ => HttpResponse::BadRequest().body("Not an NFT"),
         },
         None => HttpResponse::NotFound().body("Inscription not found"),
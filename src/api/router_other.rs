@@ -1,24 +1,35 @@
-use super::{HttpResponseExt, WebData};
+use super::{APIResponse, APIResult, APIState, HttpResponseExt, WebData};
 use crate::{
-    global::ROLLBACK_BLOCK,
+    config::HEALTH_MAX_LAG,
+    global::{get_timestamp_ms, ROLLBACK_BLOCK},
     inscription::{db::*, db_checkpoint::checkpoints_list},
 };
-use actix_web::{
-    get,
-    web::{self, Query},
-    HttpResponse, Responder,
-};
-use serde::{Deserialize, Serialize};
-use serde_json::json;
+use actix_web::{get, web, HttpResponse, Responder};
+use serde::Serialize;
+use utoipa::ToSchema;
 
 pub fn register(config: &mut web::ServiceConfig) {
     config.service(status);
     config.service(checkpoints);
-    config.service(rollback);
+    config.service(healthz);
+    config.service(readyz);
 }
 
-#[get("/status")]
-async fn status(state: WebData) -> impl Responder {
+/// The db handle is reopened and the chain head polled every 3 seconds, older ones are stale.
+const HEALTH_STALE_MS: u64 = 30_000;
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct StatusResponse {
+    pub latest_id: u64,
+    pub latest_sync_id: u64,
+    pub block_number: u64,
+    pub block_number_inscribe: u64,
+    pub block_number_sync: u64,
+    /// Negative while the indexer is behind the chain.
+    pub block_number_behind: i64,
+}
+
+pub fn get_status(state: &APIState) -> APIResult {
     let db = state.db.read().unwrap();
     let top_insc_id = db.get_top_inscription_id();
     let top_sync_id = db.get_top_inscription_sync_id();
@@ -30,34 +41,154 @@ async fn status(state: WebData) -> impl Responder {
         None => 0,
     };
 
-    let result = json!({
-        "latest_id": top_insc_id,
-        "latest_sync_id": top_sync_id,
-        "block_number": current_blocknumber,
-        "block_number_inscribe": insc_blocknumber,
-        "block_number_sync": sync_blocknumber,
-        "block_number_behind": insc_blocknumber as i64 - current_blocknumber as i64,
-    });
+    APIResult::data(StatusResponse {
+        latest_id: top_insc_id,
+        latest_sync_id: top_sync_id,
+        block_number: current_blocknumber,
+        block_number_inscribe: insc_blocknumber,
+        block_number_sync: sync_blocknumber,
+        block_number_behind: insc_blocknumber as i64 - current_blocknumber as i64,
+    })
+}
 
-    HttpResponse::response_data(result)
+#[utoipa::path(
+    get,
+    path = "/status",
+    tag = "status",
+    responses((status = 200, body = APIResponse<StatusResponse>))
+)]
+#[get("/status")]
+async fn status(state: WebData) -> impl Responder {
+    HttpResponse::response_result(get_status(&state))
 }
 
+/// Blocknumbers of the checkpoints which can be rolled back to.
+#[utoipa::path(
+    get,
+    path = "/checkpoints",
+    tag = "status",
+    responses((status = 200, body = APIResponse<Vec<u64>>))
+)]
 #[get("/checkpoints")]
 async fn checkpoints() -> impl Responder {
     HttpResponse::response_data(checkpoints_list())
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-struct RollbackParams {
-    blocknumber: u64,
+#[derive(Debug, Serialize, ToSchema)]
+pub struct HealthCheck {
+    /// db, rpc, lag or rollback
+    pub name: String,
+    pub ok: bool,
+    pub message: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct HealthResponse {
+    pub ok: bool,
+    pub checks: Vec<HealthCheck>,
+}
+
+fn health_check(name: &str, ok: bool, message: String) -> HealthCheck {
+    HealthCheck {
+        name: name.to_string(),
+        ok,
+        message,
+    }
+}
+
+/// Checks of the api process itself: the db handle and the chain head poll are fresh.
+fn health_checks_live(state: &APIState) -> Vec<HealthCheck> {
+    let now = get_timestamp_ms() as u64;
+    let db_age = now.saturating_sub(*state.db_updated.read().unwrap());
+    let rpc_age = now.saturating_sub(*state.blocknumber_updated.read().unwrap());
+
+    vec![
+        health_check("db", db_age <= HEALTH_STALE_MS, format!("reopened {} ms ago", db_age)),
+        health_check("rpc", rpc_age <= HEALTH_STALE_MS, format!("polled {} ms ago", rpc_age)),
+    ]
 }
 
-#[get("/rollback")]
-async fn rollback(info: Query<RollbackParams>) -> impl Responder {
-    if *ROLLBACK_BLOCK.lock().unwrap() == 0 {
-        *ROLLBACK_BLOCK.lock().unwrap() = info.blocknumber;
-        HttpResponse::response_data("ok")
+/// Block up to which every synced inscription is inscribed.
+fn get_inscribed_blocknumber(db: &rocksdb::DB) -> u64 {
+    let top_insc_id = db.get_top_inscription_id();
+    if top_insc_id >= db.get_top_inscription_sync_id() {
+        return db.get_sync_blocknumber();
+    }
+
+    match db.get_inscription_meta_by_id(top_insc_id + 1) {
+        Some(insc) => insc.blocknumber.saturating_sub(1),
+        None => db.get_sync_blocknumber(),
+    }
+}
+
+/// The live checks plus the inscribe lag and pending rollbacks, which a restart does not fix.
+fn health_checks_ready(state: &APIState) -> Vec<HealthCheck> {
+    let mut checks = health_checks_live(state);
+    let db = state.db.read().unwrap();
+
+    let blocknumber = *state.blocknumber.read().unwrap();
+    let inscribed_blocknumber = get_inscribed_blocknumber(&db);
+    let lag = blocknumber.saturating_sub(inscribed_blocknumber);
+    checks.push(health_check(
+        "lag",
+        lag <= *HEALTH_MAX_LAG,
+        format!(
+            "inscribed {}, chain head {}, max lag {}",
+            inscribed_blocknumber, blocknumber, *HEALTH_MAX_LAG
+        ),
+    ));
+
+    // the global is set in the indexer process, the db key is seen from api-only replicas too
+    let rollback_blocknumber = match *ROLLBACK_BLOCK.lock().unwrap() {
+        0 => db.get_rollback_blocknumber(),
+        blocknumber => blocknumber,
+    };
+    checks.push(health_check(
+        "rollback",
+        rollback_blocknumber == 0,
+        format!("pending rollback to {}", rollback_blocknumber),
+    ));
+
+    checks
+}
+
+fn response_health(checks: Vec<HealthCheck>) -> HttpResponse {
+    let ok = checks.iter().all(|check| check.ok);
+    let response = HealthResponse { ok, checks };
+    if ok {
+        HttpResponse::Ok().json(response)
     } else {
-        HttpResponse::response_error(1, "rollback in progress")
+        HttpResponse::ServiceUnavailable().json(response)
     }
 }
+
+/// Liveness, fails with http status 503 when the db handle or the chain head poll is stale.
+#[utoipa::path(
+    get,
+    path = "/healthz",
+    tag = "status",
+    responses(
+        (status = 200, body = HealthResponse),
+        (status = 503, body = HealthResponse)
+    )
+)]
+#[get("/healthz")]
+async fn healthz(state: WebData) -> impl Responder {
+    response_health(health_checks_live(&state))
+}
+
+/// Readiness, also fails with http status 503 when the inscribe worker lags the chain head by more than `--health-max-lag`
+/// blocks or a rollback is pending.
+#[utoipa::path(
+    get,
+    path = "/readyz",
+    tag = "status",
+    responses(
+        (status = 200, body = HealthResponse),
+        (status = 503, body = HealthResponse)
+    )
+)]
+#[get("/readyz")]
+async fn readyz(state: WebData) -> impl Responder {
+    response_health(health_checks_ready(&state))
+}
@@ -0,0 +1,133 @@
+use super::{router_inscription::inscription_fields_to_display, router_market::market_order_to_display};
+use crate::inscription::events::{InscribeEvent, InscribeEventFilter, INSCRIBE_EVENTS};
+use actix_web::{get, web, web::Query, HttpRequest, HttpResponse, Responder};
+use futures_util::StreamExt;
+use log::warn;
+use serde_json::json;
+use std::{sync::Arc, time::Duration};
+use tokio::sync::broadcast::{error::RecvError, Receiver};
+
+/// Seconds between keepalive comments on an idle event stream.
+const STREAM_KEEPALIVE_SECS: u64 = 15;
+
+pub fn register(config: &mut web::ServiceConfig) {
+    config.service(events);
+    config.service(ws);
+}
+
+/// The event as sent to stream subscribers and webhooks.
+pub fn event_to_display(event: &InscribeEvent) -> serde_json::Value {
+    match event {
+        InscribeEvent::Inscription(insc) => json!(inscription_fields_to_display(insc, false)),
+        InscribeEvent::TokenTransfer {
+            tick,
+            insc_id,
+            tx,
+            from,
+            to,
+            amount,
+            blocknumber,
+        } => json!({
+            "tick": tick,
+            "insc_id": insc_id,
+            "tx": tx,
+            "from": from,
+            "to": to,
+            "amount": amount.to_string(),
+            "blocknumber": blocknumber,
+        }),
+        InscribeEvent::NFTTransfer {
+            nft_id,
+            transfer_id,
+            tx,
+            from,
+            to,
+            holder,
+            collection,
+            blocknumber,
+        } => json!({
+            "nft_id": nft_id,
+            "transfer_id": transfer_id,
+            "tx": tx,
+            "from": from,
+            "to": to,
+            "holder": holder,
+            "collection": collection,
+            "blocknumber": blocknumber,
+        }),
+        InscribeEvent::MarketOrder { order, .. } => json!(market_order_to_display(order)),
+        InscribeEvent::Rollback { blocknumber } => json!({ "blocknumber": blocknumber }),
+    }
+}
+
+/// Next message of a stream as (event name, data), None when the indexer is gone.
+/// A subscriber too slow for the buffer gets a `lagged` message with the count of skipped events.
+async fn stream_next(
+    rx: &mut Receiver<Arc<InscribeEvent>>,
+    filter: &InscribeEventFilter,
+) -> Option<(&'static str, serde_json::Value)> {
+    loop {
+        match rx.recv().await {
+            Ok(event) if filter.matches(&event) => return Some((event.name(), event_to_display(&event))),
+            Ok(_) => continue,
+            Err(RecvError::Lagged(count)) => {
+                warn!("[api] event stream lagged: {}", count);
+                return Some(("lagged", json!({ "skipped": count })));
+            }
+            Err(RecvError::Closed) => return None,
+        }
+    }
+}
+
+#[get("/events")]
+async fn events(info: Query<InscribeEventFilter>) -> impl Responder {
+    let state = (INSCRIBE_EVENTS.subscribe(), info.into_inner());
+    let stream = futures_util::stream::unfold(state, |(mut rx, filter)| async move {
+        let keepalive = Duration::from_secs(STREAM_KEEPALIVE_SECS);
+        let data = match tokio::time::timeout(keepalive, stream_next(&mut rx, &filter)).await {
+            Ok(Some((name, data))) => format!("event: {}\ndata: {}\n\n", name, data),
+            Ok(None) => return None,
+            Err(_) => ": keepalive\n\n".to_string(),
+        };
+        Some((Ok::<_, actix_web::Error>(web::Bytes::from(data)), (rx, filter)))
+    });
+
+    HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header(("Cache-Control", "no-cache"))
+        .streaming(stream)
+}
+
+#[get("/ws")]
+async fn ws(req: HttpRequest, body: web::Payload, info: Query<InscribeEventFilter>) -> actix_web::Result<HttpResponse> {
+    let (response, mut session, mut msg_stream) = actix_ws::handle(&req, body)?;
+    let mut rx = INSCRIBE_EVENTS.subscribe();
+    let filter = info.into_inner();
+
+    actix_web::rt::spawn(async move {
+        loop {
+            tokio::select! {
+                msg = msg_stream.next() => match msg {
+                    Some(Ok(actix_ws::Message::Ping(bytes))) => {
+                        if session.pong(&bytes).await.is_err() {
+                            return;
+                        }
+                    }
+                    Some(Ok(actix_ws::Message::Close(_))) | Some(Err(_)) | None => break,
+                    Some(Ok(_)) => {}
+                },
+                next = stream_next(&mut rx, &filter) => match next {
+                    Some((name, data)) => {
+                        if session.text(json!({ "event": name, "data": data }).to_string()).await.is_err() {
+                            return;
+                        }
+                    }
+                    None => break,
+                },
+            }
+        }
+        let _ = session.close(None).await;
+    });
+
+    Ok(response)
+}
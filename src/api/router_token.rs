@@ -1,12 +1,16 @@
-use super::{HttpResponseExt, WebData, PAGE_SIZE, router_inscription::insc_list_to_display};
+use super::{
+    router_inscription::{insc_list_to_display, InscriptionResponse},
+    APIPage, APIResponse, APIResult, APIState, HttpResponseExt, ListPage, WebData, PAGE_SIZE,
+};
 use crate::{
-    inscription::{db::*, types::*},
+    global::get_timestamp,
+    inscription::{db::*, marketplace::db::InscribeMarketDB, types::*},
     num_index,
-    txn_db::TxnDB,
+    txn_db::{codec::StorageCodec, DBBase, TxnDB},
 };
 use actix_web::{get, web, web::Query, HttpResponse, Responder};
 use serde::{Deserialize, Serialize};
-use serde_json::json;
+use utoipa::{IntoParams, ToSchema};
 
 pub fn register(config: &mut web::ServiceConfig) {
     config.service(tokens);
@@ -16,33 +20,75 @@ pub fn register(config: &mut web::ServiceConfig) {
     config.service(token_txs);
 }
 
-fn token_to_display(token: &InscriptionToken) -> serde_json::Value {
-    json!({
-        "insc_id": token.insc_id,
-        "tick": token.tick,
-        "tick_i": token.tick_i,
-        "tx": token.tx,
-        "from": token.from,
-        "blocknumber": token.blocknumber.to_string(),
-        "timestamp": token.timestamp.to_string(),
-        "holders": token.holders.to_string(),
-        "mint_max": token.mint_max.to_string(),
-        "mint_limit": token.mint_limit.to_string(),
-        "mint_progress": token.mint_progress.to_string(),
-        "mint_finished": token.mint_finished,
-        "market_volume24h": token.market_volume24h.to_string(),
-        "market_txs24h": token.market_txs24h.to_string(),
-        "market_cap": token.market_cap.to_string(),
-        "market_floor_price": token.market_floor_price.to_string(),
-    })
-}
-
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TokenResponse {
+    pub insc_id: u64,
+    pub tick: String,
+    pub tick_i: String,
+    pub tx: String,
+    pub from: String,
+    pub blocknumber: u64,
+    pub timestamp: u64,
+    pub holders: u64,
+    pub mint_max: String,
+    pub mint_limit: String,
+    pub mint_progress: String,
+    pub mint_finished: bool,
+    pub market_volume24h: String,
+    pub market_txs24h: u64,
+    pub market_volume7d: String,
+    pub market_txs7d: u64,
+    pub market_volume30d: String,
+    pub market_txs30d: u64,
+    pub market_cap: String,
+    pub market_floor_price: String,
+}
+
+fn token_to_display(db: &rocksdb::DB, token: &InscriptionToken) -> TokenResponse {
+    let now = get_timestamp();
+    let volume24h = db.market_get_volume(&token.tick, now.saturating_sub(24 * 3600));
+    let volume7d = db.market_get_volume(&token.tick, now.saturating_sub(7 * 24 * 3600));
+    let volume30d = db.market_get_volume(&token.tick, now.saturating_sub(30 * 24 * 3600));
+
+    TokenResponse {
+        insc_id: token.insc_id,
+        tick: token.tick.clone(),
+        tick_i: token.tick_i.clone(),
+        tx: token.tx.clone(),
+        from: token.from.clone(),
+        blocknumber: token.blocknumber,
+        timestamp: token.timestamp,
+        holders: token.holders,
+        mint_max: token.mint_max.to_string(),
+        mint_limit: token.mint_limit.to_string(),
+        mint_progress: token.mint_progress.to_string(),
+        mint_finished: token.mint_finished,
+        market_volume24h: volume24h.volume.to_string(),
+        market_txs24h: volume24h.txs,
+        market_volume7d: volume7d.volume.to_string(),
+        market_txs7d: volume7d.txs,
+        market_volume30d: volume30d.volume.to_string(),
+        market_txs30d: volume30d.txs,
+        market_cap: token.market_cap.to_string(),
+        market_floor_price: token.market_floor_price.to_string(),
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 struct TokensParams {
     page: Option<u64>,
     order_by: Option<String>,
 }
 
+/// Tokens in deploy order, or sorted by `order_by` holders, mcap or mint.
+#[utoipa::path(
+    get,
+    path = "/tokens",
+    tag = "token",
+    params(TokensParams),
+    responses((status = 200, body = APIResponse<Vec<TokenResponse>>))
+)]
 #[get("/tokens")]
 async fn tokens(info: Query<TokensParams>, state: WebData) -> impl Responder {
     let db = state.db.read().unwrap();
@@ -77,57 +123,76 @@ async fn tokens(info: Query<TokensParams>, state: WebData) -> impl Responder {
 
     let mut pages = list.chunks(PAGE_SIZE.try_into().unwrap());
     if let Some(page_slice) = pages.nth(page.try_into().unwrap()) {
-        let result_list: Vec<serde_json::Value> = page_slice.iter().map(token_to_display).collect();
+        let result_list: Vec<TokenResponse> = page_slice.iter().map(|token| token_to_display(&db, token)).collect();
         HttpResponse::response_data(result_list)
     } else {
-        HttpResponse::response_data(Vec::<InscriptionToken>::new())
+        HttpResponse::response_data(Vec::<TokenResponse>::new())
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-struct TokenInfoParams {
+#[derive(Debug, Serialize, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct TokenInfoParams {
     tick: String,
 }
 
-#[get("/token_info")]
-async fn token_info(info: Query<TokenInfoParams>, state: WebData) -> impl Responder {
+pub fn get_token_info(state: &APIState, info: &TokenInfoParams) -> APIResult {
     let db = state.db.read().unwrap();
     let key_tick = make_index_key(KEY_INSC_TOKEN_INDEX_TICK_I, &info.tick);
     let id = db.get_u64(key_tick.as_str());
     if id == 0 {
-        HttpResponse::response_error_notfound();
+        return APIResult::NotFound;
     }
 
     let key_id = make_index_key(KEY_INSC_TOKEN_INDEX_ID, num_index!(id));
-    let result = db.get(key_id.as_bytes()).unwrap();
-    let res: Option<InscriptionToken> = serde_json::from_slice(&result.unwrap()).unwrap();
-    HttpResponse::response_data(token_to_display(&res.unwrap()))
+    let result = DBBase::get(&*db, key_id.as_bytes()).unwrap();
+    let token = InscriptionToken::decode(&result.unwrap());
+    APIResult::data(token_to_display(&db, &token))
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-struct TokenHoldersParams {
+#[utoipa::path(
+    get,
+    path = "/token_info",
+    tag = "token",
+    params(TokenInfoParams),
+    responses((status = 200, body = APIResponse<TokenResponse>))
+)]
+#[get("/token_info")]
+async fn token_info(info: Query<TokenInfoParams>, state: WebData) -> impl Responder {
+    HttpResponse::response_result(get_token_info(&state, &info))
+}
+
+#[derive(Debug, Serialize, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct TokenHoldersParams {
     page: Option<u64>,
+    cursor: Option<String>,
+    limit: Option<u64>,
     tick: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-struct TokenHoldersResponse {
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TokenHoldersResponse {
     address: String,
     balance: String,
 }
 
-#[get("/token_holders")]
-async fn token_holders(info: Query<TokenHoldersParams>, state: WebData) -> impl Responder {
+pub fn get_token_holders(state: &APIState, info: &TokenHoldersParams) -> APIResult {
     let db = state.db.read().unwrap();
-    let page = info.page.unwrap_or(1) - 1;
     let start_key = make_index_key(KEY_INSC_BALANCE_INDEX_TICK_BALANCE_HOLDER, &info.tick) + ":";
-    let key_list = db.get_items(
+    let page = match ListPage::new(&start_key, info.page, &info.cursor, info.limit) {
+        Some(page) => page,
+        None => return APIResult::Error(1, "Invalid cursor".to_string()),
+    };
+    let key_list = db.get_items_after(
         &start_key,
         &start_key,
-        page * PAGE_SIZE,
-        PAGE_SIZE,
+        page.after(),
+        page.skip,
+        page.limit,
         rocksdb::Direction::Forward,
     );
+    let next_cursor = page.next_cursor(key_list.len(), key_list.last().map(|(key, _)| key));
 
     let mut holders: Vec<TokenHoldersResponse> = Vec::new();
     for (key, value) in &key_list {
@@ -139,23 +204,36 @@ async fn token_holders(info: Query<TokenHoldersParams>, state: WebData) -> impl
         });
     }
 
-    HttpResponse::response_data(holders)
+    APIResult::page(holders, next_cursor)
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-struct TokenBalanceParams {
+/// Holders of a token, largest balance first.
+#[utoipa::path(
+    get,
+    path = "/token_holders",
+    tag = "token",
+    params(TokenHoldersParams),
+    responses((status = 200, body = APIPage<TokenHoldersResponse>))
+)]
+#[get("/token_holders")]
+async fn token_holders(info: Query<TokenHoldersParams>, state: WebData) -> impl Responder {
+    HttpResponse::response_result(get_token_holders(&state, &info))
+}
+
+#[derive(Debug, Serialize, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct TokenBalanceParams {
     address: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-struct TokenBalanceResponse {
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TokenBalanceResponse {
     tick: String,
     balance: String,
-    token: serde_json::Value,
+    token: TokenResponse,
 }
 
-#[get("/token_balance")]
-async fn token_balance(info: Query<TokenBalanceParams>, state: WebData) -> impl Responder {
+pub fn get_token_balance(state: &APIState, info: &TokenBalanceParams) -> APIResult {
     let db = state.db.read().unwrap();
     let prefix = make_index_key(KEY_INSC_BALANCE_INDEX_HOLDER_TICK, info.address.to_lowercase().as_str());
     let key_list = db.get_items(&prefix, &prefix, 0, 9999, rocksdb::Direction::Forward);
@@ -168,33 +246,62 @@ async fn token_balance(info: Query<TokenBalanceParams>, state: WebData) -> impl
         token_list.push(TokenBalanceResponse {
             tick,
             balance: balance.to_string(),
-            token: token_to_display(&token),
+            token: token_to_display(&db, &token),
         });
     }
-    HttpResponse::response_data(token_list)
+    APIResult::data(token_list)
+}
+
+/// Token balances of an address.
+#[utoipa::path(
+    get,
+    path = "/token_balance",
+    tag = "token",
+    params(TokenBalanceParams),
+    responses((status = 200, body = APIResponse<Vec<TokenBalanceResponse>>))
+)]
+#[get("/token_balance")]
+async fn token_balance(info: Query<TokenBalanceParams>, state: WebData) -> impl Responder {
+    HttpResponse::response_result(get_token_balance(&state, &info))
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 struct TokenTxsParams {
     tick: String,
     page: Option<u64>,
+    cursor: Option<String>,
+    limit: Option<u64>,
 }
 
+/// Transfer inscriptions of a token, newest first.
+#[utoipa::path(
+    get,
+    path = "/token_txs",
+    tag = "token",
+    params(TokenTxsParams),
+    responses((status = 200, body = APIPage<InscriptionResponse>))
+)]
 #[get("/token_txs")]
 async fn token_txs(info: Query<TokenTxsParams>, state: WebData) -> impl Responder {
     let db = state.db.read().unwrap();
-    let page = info.page.unwrap_or(1) - 1;
     let start_key = make_index_key(KEY_INSC_TOKEN_TRANSFER, info.tick.as_str()) + ":";
-    let key_list = db.get_item_keys(
+    let page = match ListPage::new(&start_key, info.page, &info.cursor, info.limit) {
+        Some(page) => page,
+        None => return HttpResponse::response_error(1, "Invalid cursor"),
+    };
+    let key_list = db.get_item_keys_after(
         &start_key,
         &start_key,
-        page * PAGE_SIZE,
-        PAGE_SIZE,
+        page.after(),
+        page.skip,
+        page.limit,
         rocksdb::Direction::Forward,
     );
+    let next_cursor = page.next_cursor(key_list.len(), key_list.last());
 
     let id_list = db_index2id_desc(key_list);
-    let insc_list = db.get_inscriptions_by_id(&id_list);
+    let insc_list = db.get_inscriptions_meta_by_id(&id_list);
 
-    HttpResponse::response_data(insc_list_to_display(&db, &insc_list))
+    HttpResponse::response_page(insc_list_to_display(&db, &insc_list), next_cursor)
 }
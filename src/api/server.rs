@@ -2,11 +2,13 @@ use super::{APIState, WebData};
 use crate::{
     config::{DB_PATH, HTTP_BIND, HTTP_PORT, WEB3_PROVIDER},
     ethereum::{init_web3_http, Web3Ex},
-    global::sleep_ms,
+    global::{get_timestamp_ms, sleep_ms},
+    metrics::CHAIN_HEAD_BLOCK,
+    txn_db::db::open_read_only,
 };
 use actix_cors::Cors;
 use actix_web::{get, middleware, web, App, HttpResponse, HttpServer, Responder};
-use log::{info, warn};
+use log::{error, info, warn};
 use std::sync::{Arc, RwLock};
 
 impl APIState {
@@ -14,13 +16,14 @@ impl APIState {
         Self {
             db: Arc::new(RwLock::new(Self::db())),
             blocknumber: 0.into(),
+            db_updated: (get_timestamp_ms() as u64).into(),
+            blocknumber_updated: 0.into(),
         }
     }
 
     pub fn db() -> rocksdb::DB {
-        let options = rocksdb::Options::default();
         loop {
-            return match rocksdb::DB::open_for_read_only(&options, DB_PATH.as_str(), false) {
+            return match open_read_only(DB_PATH.as_str()) {
                 Ok(db) => db,
                 Err(e) => {
                     warn!("[api] open db failed: {}, retry", e);
@@ -46,6 +49,8 @@ async fn blocknumber_refresh(state: WebData) {
     loop {
         let blocknumber = web3.get_blocknumber_wait().await;
         *state.blocknumber.write().unwrap() = blocknumber;
+        *state.blocknumber_updated.write().unwrap() = get_timestamp_ms() as u64;
+        CHAIN_HEAD_BLOCK.set(blocknumber as i64);
         sleep_ms(3000).await;
     }
 }
@@ -54,27 +59,50 @@ async fn db_refresh(state: WebData) {
     loop {
         let db = APIState::db();
         *state.db.write().unwrap() = db;
+        *state.db_updated.write().unwrap() = get_timestamp_ms() as u64;
         sleep_ms(3000).await;
     }
 }
 
+/// Any origin may read the public routes, the admin routes are for same-origin and non-browser clients only.
+fn cors() -> Cors {
+    Cors::default()
+        .allowed_origin_fn(|_, req| !req.uri.path().starts_with("/admin/"))
+        .allow_any_method()
+        .allow_any_header()
+}
+
 pub async fn run(wait_forever: bool) {
     let state = web::Data::new(Arc::new(APIState::new()));
     tokio::spawn(blocknumber_refresh(state.clone()));
     tokio::spawn(db_refresh(state.clone()));
+    if let Err(e) = super::api_key::usage_open() {
+        error!("[api] open api usage db failed: {}, usage is counted in memory only", e);
+    }
+    tokio::spawn(super::api_key::usage_flush());
 
     let server = HttpServer::new(move || {
         App::new()
             .app_data(state.clone())
+            .wrap_fn(super::api_key::limit)
+            .wrap_fn(super::router_metrics::observe)
             .wrap(middleware::Logger::default())
-            .wrap(Cors::default().allow_any_origin().allow_any_method().allow_any_header())
+            .wrap(cors())
             .default_service(web::route().to(not_found))
             .service(index)
+            .configure(super::router_content::register)
             .configure(super::router_inscription::register)
             .configure(super::router_market::register)
             .configure(super::router_nft::register)
             .configure(super::router_token::register)
             .configure(super::router_other::register)
+            .configure(super::api_key::register)
+            .configure(super::router_metrics::register)
+            .configure(super::router_stream::register)
+            .configure(super::graphql::register)
+            .configure(super::jsonrpc::register)
+            .configure(super::openapi::register)
+            .configure(super::router_admin::register)
     })
     .bind((HTTP_BIND.as_str(), *HTTP_PORT))
     .unwrap()
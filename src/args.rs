@@ -43,6 +43,10 @@ pub struct Args {
     #[arg(long, env, default_value = "./data")]
     pub db_path: String,
 
+    /// The block cache size of database in MB
+    #[arg(long, env, default_value = "256")]
+    pub db_block_cache_size: usize,
+
     /// The token protocol
     #[arg(long, env, default_value = "erc-20")]
     pub token_protocol: String,
@@ -79,9 +83,33 @@ pub struct Args {
     #[arg(long, env, default_value = "./.checkpoint")]
     pub checkpoint_path: String,
 
+    /// The max length of inscription content kept inline, larger content is moved to the content store
+    #[arg(long, env, default_value = "1024")]
+    pub content_inline_max: usize,
+
     /// The market address list
     #[arg(long, default_value = "")]
     pub market_address_list: String,
+
+    /// The market config file, maps market contract addresses to abi files and event field names
+    #[arg(long, env, default_value = "")]
+    pub market_config: String,
+
+    /// The webhook config file, lists webhook urls with their event filters
+    #[arg(long, env, default_value = "")]
+    pub webhook_config: String,
+
+    /// The bearer token of the admin api under /admin, the admin api is disabled when empty
+    #[arg(long, env, default_value = "")]
+    pub admin_token: String,
+
+    /// The api key config file, lists api keys with their rate limits and daily quotas, the api is not limited when empty
+    #[arg(long, env, default_value = "")]
+    pub api_key_config: String,
+
+    /// The number of blocks the inscribe worker may lag behind the chain head before /readyz fails
+    #[arg(long, env, default_value = "30")]
+    pub health_max_lag: u64,
 }
 
 pub fn parse() -> Args {
@@ -1,8 +1,8 @@
-use crate::args::Args;
+use crate::{args::Args, inscription::marketplace::market_config::MARKET_CONTRACTS};
 use clap::Parser;
 
 lazy_static! {
-    pub static ref ARGS: Args = Args::parse();
+    pub static ref ARGS: Args = parse_args();
     pub static ref TICK_MAX_LEN: usize = ARGS.tick_max_len;
     pub static ref WORKER_COUNT: u64 = ARGS.worker_count;
     pub static ref CONFIRM_BLOCK: u64 = ARGS.confirm_block;
@@ -13,6 +13,7 @@ lazy_static! {
     pub static ref START_BLOCK_MINT: u64 = ARGS.start_block_mint;
     pub static ref WORKER_BUFFER_LENGTH: usize = ARGS.worker_buffer_length;
     pub static ref DB_PATH: String = ARGS.db_path.clone();
+    pub static ref DB_BLOCK_CACHE_SIZE: usize = ARGS.db_block_cache_size;
     pub static ref TOKEN_PROTOCOL: String = ARGS.token_protocol.clone();
     pub static ref HTTP_BIND: String = ARGS.http_bind.clone();
     pub static ref HTTP_PORT: u16 = ARGS.http_port;
@@ -22,5 +23,30 @@ lazy_static! {
     pub static ref CHECKPOINT_LENGTH: usize = ARGS.checkpoint_len;
     pub static ref CHECKPOINT_PATH: String = ARGS.checkpoint_path.clone();
     pub static ref FINALIZED_BLOCK: u64 = ARGS.finalized_block;
-    pub static ref MARKET_ADDRESS_LIST: Vec<String> = ARGS.market_address_list.split(',').map(|s| s.to_string()).collect();
+    pub static ref CONTENT_INLINE_MAX: usize = ARGS.content_inline_max;
+    pub static ref MARKET_CONFIG: String = ARGS.market_config.clone();
+    pub static ref WEBHOOK_CONFIG: String = ARGS.webhook_config.clone();
+    pub static ref ADMIN_TOKEN: String = ARGS.admin_token.clone();
+    pub static ref API_KEY_CONFIG: String = ARGS.api_key_config.clone();
+    pub static ref HEALTH_MAX_LAG: u64 = ARGS.health_max_lag;
+    pub static ref MARKET_ADDRESS_LIST: Vec<String> = MARKET_CONTRACTS.keys().cloned().collect();
+}
+
+#[cfg(not(test))]
+fn parse_args() -> Args {
+    Args::parse()
+}
+
+/// The argv of the test harness is not ours, tests run with the required args only.
+#[cfg(test)]
+fn parse_args() -> Args {
+    Args::parse_from([
+        "insdexer",
+        "--web3-provider",
+        "http://127.0.0.1:8545",
+        "--start-block",
+        "0",
+        "--start-block-mint",
+        "0",
+    ])
 }
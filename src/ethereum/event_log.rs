@@ -3,17 +3,18 @@ use web3::ethabi::RawLog;
 
 impl Web3LogEvent for web3::types::Log {
     fn match_event(&self, contract: &web3::ethabi::Contract, event_name: &str) -> Option<web3::ethabi::Log> {
-        let event = contract.event(event_name).unwrap();
-        let raw_log = RawLog {
-            topics: self.topics.clone(),
-            data: self.data.0.clone(),
-        };
+        // overloaded events share the name, try each signature
+        for event in contract.events_by_name(event_name).unwrap() {
+            let raw_log = RawLog {
+                topics: self.topics.clone(),
+                data: self.data.0.clone(),
+            };
 
-        if let Ok(decode_log) = event.parse_log(raw_log) {
-            Some(decode_log)
-        } else {
-            None
+            if let Ok(decode_log) = event.parse_log(raw_log) {
+                return Some(decode_log);
+            }
         }
+        None
     }
 }
 
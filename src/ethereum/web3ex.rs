@@ -1,9 +1,10 @@
 use std::collections::HashMap;
 
 use super::{HexParseTrait, Web3Ex};
-use crate::global::sleep_ms;
+use crate::{global::sleep_ms, metrics::metrics_rpc};
 use async_trait::async_trait;
 use log::warn;
+use std::time::Instant;
 use web3::{
     types::{Address, Block, BlockId, FilterBuilder, Log, Transaction, H256},
     Transport, Web3,
@@ -15,11 +16,17 @@ where
     T::Out: Send,
 {
     async fn get_chain_id(&self) -> u64 {
-        self.eth().chain_id().await.unwrap().as_u64()
+        let timer = Instant::now();
+        let result = self.eth().chain_id().await;
+        metrics_rpc("eth_chainId", timer, &result);
+        result.unwrap().as_u64()
     }
 
     async fn get_blocknumber(&self) -> Option<u64> {
-        match self.eth().block_number().await {
+        let timer = Instant::now();
+        let result = self.eth().block_number().await;
+        metrics_rpc("eth_blockNumber", timer, &result);
+        match result {
             Ok(blocknumber) => Some(blocknumber.as_u64()),
             Err(e) => {
                 warn!("[web3] get_blocknumber error: {}", e.to_string());
@@ -39,7 +46,10 @@ where
     }
 
     async fn get_block_with_txs(&self, blocknumber: u64) -> Option<Block<Transaction>> {
-        match self.eth().block_with_txs(BlockId::Number(blocknumber.into())).await {
+        let timer = Instant::now();
+        let result = self.eth().block_with_txs(BlockId::Number(blocknumber.into())).await;
+        metrics_rpc("eth_getBlockByNumber", timer, &result);
+        match result {
             Ok(block) => block,
             Err(e) => {
                 warn!("[web3] get_block error: {} {}", blocknumber, e.to_string());
@@ -59,7 +69,10 @@ where
     }
 
     async fn get_block_info(&self, blocknumber: u64) -> Option<Block<H256>> {
-        match self.eth().block(BlockId::Number(blocknumber.into())).await {
+        let timer = Instant::now();
+        let result = self.eth().block(BlockId::Number(blocknumber.into())).await;
+        metrics_rpc("eth_getBlockByNumber", timer, &result);
+        match result {
             Ok(block) => block,
             Err(e) => {
                 warn!("[web3] get_block error: {} {}", blocknumber, e.to_string());
@@ -85,7 +98,10 @@ where
             .to_block(blocknumber.into())
             .build();
 
-        match self.eth().logs(filter).await {
+        let timer = Instant::now();
+        let result = self.eth().logs(filter).await;
+        metrics_rpc("eth_getLogs", timer, &result);
+        match result {
             Ok(logs) => Some(logs),
             Err(e) => {
                 warn!(
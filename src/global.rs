@@ -2,6 +2,10 @@ use std::sync::Mutex;
 
 lazy_static! {
     pub static ref ROLLBACK_BLOCK: Mutex<u64> = Mutex::new(0);
+    /// Make a checkpoint after the next synced block.
+    pub static ref CHECKPOINT_REQUEST: Mutex<bool> = Mutex::new(false);
+    /// Reindex on the next start, the indexer exits to restart.
+    pub static ref REINDEX_REQUEST: Mutex<bool> = Mutex::new(false);
 }
 
 pub async fn sleep_ms(ms: u64) {
@@ -1,6 +1,8 @@
-use super::types::{Inscription, InscriptionToken, NFTTransfer};
-use crate::txn_db::TxnDB;
+use super::marketplace::db::*;
+use super::types::{Inscription, InscriptionMimeCategory, InscriptionToken, NFTTransfer};
+use crate::txn_db::{codec::StorageCodec, TxnDB};
 use rocksdb::TransactionDB;
+use sha1::{Digest, Sha1};
 
 pub const NUM_INDEX_LEN: usize = 18;
 pub const DESC_INDEX_C: u64 = 10u64.pow(18);
@@ -19,9 +21,33 @@ macro_rules! num_index_desc {
     };
 }
 
+// column families
+pub const CF_SYNC: &'static str = "sync";
+pub const CF_INSCRIPTION: &'static str = "inscription";
+pub const CF_INSCRIPTION_ADDRESS: &'static str = "inscription_address";
+pub const CF_CONTENT: &'static str = "content";
+pub const CF_NFT: &'static str = "nft";
+pub const CF_TOKEN: &'static str = "token";
+pub const CF_BALANCE: &'static str = "balance";
+pub const CF_MARKET: &'static str = "market";
+
+pub const COLUMN_FAMILIES: [&'static str; 8] = [
+    CF_SYNC,
+    CF_INSCRIPTION,
+    CF_INSCRIPTION_ADDRESS,
+    CF_CONTENT,
+    CF_NFT,
+    CF_TOKEN,
+    CF_BALANCE,
+    CF_MARKET,
+];
+
 pub const KEY_ROLLBACK_BLOCKNUMBER: &'static str = "rollback_blocknumber";
+pub const KEY_ROLLBACK_COUNT: &'static str = "rollback_count";
+pub const KEY_REINDEX_PENDING: &'static str = "reindex_pending";
 pub const KEY_SYNC_BLOCKNUMBER: &'static str = "sync_blocknumber";
 pub const KEY_SYNC_BLOCK_HASH: &'static str = "sync_blockhash";
+pub const KEY_DB_VERSION: &'static str = "db_version";
 
 // inscription top
 pub const KEY_INSC_SYNC_TOP: &'static str = "insc_sync_top";
@@ -35,6 +61,9 @@ pub const KEY_INSC_INDEX_NFT_ID: &'static str = "insc_nft_id";
 pub const KEY_INSC_INDEX_CREATER: &'static str = "insc_creater-id";
 pub const KEY_INSC_INDEX_ADDRESS: &'static str = "insc_address-id";
 
+// inscription content
+pub const KEY_INSC_CONTENT: &'static str = "insc_content";
+
 // inscription nft
 pub const KEY_INSC_NFT_INDEX_CREATER: &'static str = "insc_nft_creater-id";
 pub const KEY_INSC_NFT_INDEX_HOLDER: &'static str = "insc_nft_holder_id";
@@ -76,6 +105,69 @@ pub fn make_index_key3<T1: std::fmt::Display, T2: std::fmt::Display, T3: std::fm
     format!("{}:{}:{}:{}", index, key1, key2, key3)
 }
 
+/// The index name of a key, the part before the first ':'.
+pub fn key_index_name(key: &[u8]) -> &[u8] {
+    match key.iter().position(|c| *c == b':') {
+        Some(pos) => &key[..pos],
+        None => key,
+    }
+}
+
+/// The column family a key is stored in, chosen by its index name.
+/// Keys without a known index name are sync metadata.
+pub fn key_column_family(key: &[u8]) -> &'static str {
+    match std::str::from_utf8(key_index_name(key)).unwrap_or_default() {
+        KEY_INSC_INDEX_ID | KEY_INSC_INDEX_TX | KEY_INSC_INDEX_CREATER => CF_INSCRIPTION,
+        KEY_INSC_INDEX_ADDRESS => CF_INSCRIPTION_ADDRESS,
+        KEY_INSC_CONTENT => CF_CONTENT,
+        KEY_INSC_INDEX_SIGN
+        | KEY_INSC_INDEX_NFT_ID
+        | KEY_INSC_NFT_INDEX_CREATER
+        | KEY_INSC_NFT_INDEX_HOLDER
+        | KEY_INSC_NFT_INDEX_HOLDER_ADDRESS
+        | KEY_INSC_NFT_TRANS_INDEX_ID
+        | KEY_INSC_NFT_COLL_INDEX_ID
+        | KEY_INSC_NFT_COLL_ITEM_INDEX_ID => CF_NFT,
+        KEY_INSC_TOKEN_INDEX_ID | KEY_INSC_TOKEN_INDEX_TICK | KEY_INSC_TOKEN_INDEX_TICK_I | KEY_INSC_TOKEN_TRANSFER => CF_TOKEN,
+        KEY_INSC_BALANCE_INDEX_TICK_BALANCE_HOLDER | KEY_INSC_BALANCE_INDEX_HOLDER_TICK => CF_BALANCE,
+        KEY_MARKET_ORDER_INDEX_ID
+        | KEY_MARKET_ORDER_INDEX_SELLER
+        | KEY_MARKET_ORDER_INDEX_TICK_PRICE
+        | KEY_MARKET_ORDER_INDEX_NFT
+        | KEY_MARKET_ORDER_INDEX_TIME
+        | KEY_MARKET_ORDER_INDEX_TICK_TIME
+        | KEY_MARKET_ORDER_INDEX_SELLER_CLOSE_CANCEL
+        | KEY_MARKET_ORDER_INDEX_CLOSE_TICK_TIME
+        | KEY_MARKET_ORDER_INDEX_EXPIRE
+        | KEY_MARKET_TRADE_INDEX_ID
+        | KEY_MARKET_TRADE_INDEX_TICK_TIME
+        | KEY_MARKET_TRADE_INDEX_BUYER
+        | KEY_MARKET_TRADE_INDEX_SELLER
+        | KEY_MARKET_BID_INDEX_TICK_PRICE
+        | KEY_MARKET_BID_INDEX_COLLECTION_PRICE
+        | KEY_MARKET_BID_INDEX_NFT_PRICE
+        | KEY_MARKET_VOLUME_TICK_HOUR
+        | KEY_MARKET_REVENUE_MARKET
+        | KEY_MARKET_REVENUE_CREATOR
+        | KEY_MARKET_CANDLE_TICK_INTERVAL_TIME => CF_MARKET,
+        _ => CF_SYNC,
+    }
+}
+
+pub fn make_content_hash(data: &[u8]) -> String {
+    let mut hasher = Sha1::new();
+    hasher.update(data);
+    format!("{:x}", hasher.finalize())
+}
+
+/// The categories shown by the api as content, only their payloads go to the content store.
+pub fn is_content_category(mime_category: &InscriptionMimeCategory) -> bool {
+    matches!(
+        mime_category,
+        InscriptionMimeCategory::Text | InscriptionMimeCategory::Image | InscriptionMimeCategory::Json
+    )
+}
+
 pub trait InscribeDB: TxnDB {
     fn get_first_value(&self, prefix: &str) -> Option<(Box<[u8]>, Box<[u8]>)>;
 
@@ -83,14 +175,21 @@ pub trait InscribeDB: TxnDB {
     fn get_top_inscription_sync_id(&self) -> u64;
     fn get_sync_blocknumber(&self) -> u64;
     fn get_rollback_blocknumber(&self) -> u64;
+    fn get_rollback_count(&self) -> u64;
+    fn get_reindex_pending(&self) -> bool;
+    fn get_db_version(&self) -> u64;
 
     fn get_block_hash(&self, blocknumber: u64) -> Option<String>;
 
     fn get_inscription_by_id(&self, id: u64) -> Option<Inscription>;
     fn get_inscriptions_by_id(&self, id_list: &Vec<u64>) -> Vec<Inscription>;
+    fn get_inscription_meta_by_id(&self, id: u64) -> Option<Inscription>;
+    fn get_inscriptions_meta_by_id(&self, id_list: &[u64]) -> Vec<Inscription>;
+    fn get_inscription_content(&self, content_hash: &str) -> Option<String>;
     fn get_inscription_id_by_tx(&self, tx: &str) -> u64;
     fn get_inscription_by_tx(&self, tx: &str) -> Option<Inscription>;
     fn get_inscription_nft_collection_by_id(&self, id: u64) -> Option<String>;
+    fn inscription_nft_collection_exists(&self, tx: &str) -> bool;
     fn get_inscription_nft_holder_by_id(&self, id: u64) -> Option<String>;
     fn get_token(&self, tick: &str) -> Option<InscriptionToken>;
     fn get_tokens(&self) -> std::collections::HashMap<String, InscriptionToken>;
@@ -100,6 +199,24 @@ pub trait InscribeDB: TxnDB {
     fn get_token_balance(&self, tick: &str, holder: &str) -> u64;
     fn get_items(&self, prefix: &str, start: &str, skip: u64, limit: u64, dir: rocksdb::Direction) -> Vec<(Vec<u8>, Vec<u8>)>;
     fn get_item_keys(&self, prefix: &str, start: &str, skip: u64, limit: u64, dir: rocksdb::Direction) -> Vec<String>;
+    fn get_items_after(
+        &self,
+        prefix: &str,
+        start: &str,
+        after: Option<&str>,
+        skip: u64,
+        limit: u64,
+        dir: rocksdb::Direction,
+    ) -> Vec<(Vec<u8>, Vec<u8>)>;
+    fn get_item_keys_after(
+        &self,
+        prefix: &str,
+        start: &str,
+        after: Option<&str>,
+        skip: u64,
+        limit: u64,
+        dir: rocksdb::Direction,
+    ) -> Vec<String>;
 }
 
 pub trait InscribeTxn<'a> {
@@ -107,11 +224,15 @@ pub trait InscribeTxn<'a> {
     fn set_top_inscription_sync_id(&self, id: u64);
     fn set_sync_blocknumber(&self, blocknumber: u64);
     fn set_rollback_blocknumber(&self, blocknumber: u64);
+    fn set_rollback_count(&self, count: u64);
+    fn set_reindex_pending(&self);
+    fn set_db_version(&self, version: u64);
     fn set_block_hash(&self, blocknumber: u64, block_hash: &str);
 
     fn inscription_insert(&self, insc: &Inscription);
     fn inscription_inscribe(&self, insc: &Inscription);
     fn inscription_update(&self, insc: &Inscription);
+    fn inscription_content_insert(&self, data: &str) -> String;
     fn inscription_nft_holder_update(&self, db: &TransactionDB, id: u64, new_holder: &str);
     fn inscription_nft_transfer_insert(&self, trans: &NFTTransfer);
     fn inscription_nft_set_collection(&self, id: u64, collection: &str);
@@ -120,7 +241,6 @@ pub trait InscribeTxn<'a> {
     fn inscription_token_transfer_insert(&self, tick: &str, id: u64);
     fn inscription_token_update(&self, token: &InscriptionToken);
     fn inscription_token_banalce_update(&self, db: &TransactionDB, tick: &str, holder: &str, balance_change: i64) -> i64;
-    fn delete_keys(&self, prefix: &str, max: u64) -> u64;
 }
 
 pub fn db_index2str(keys: Vec<String>) -> Vec<String> {
@@ -172,18 +292,10 @@ pub fn db_val2string(v: Vec<Vec<u8>>) -> Vec<String> {
     result
 }
 
-pub fn db_val2json<T: serde::de::DeserializeOwned>(v: Vec<Vec<u8>>) -> Vec<T> {
-    let mut result = Vec::new();
-    for item in v {
-        result.push(serde_json::from_slice(&item).unwrap());
-    }
-    result
-}
-
-pub fn db_item_val2json<T: serde::de::DeserializeOwned>(v: Vec<(Vec<u8>, Vec<u8>)>) -> Vec<T> {
+pub fn db_item_val2row<T: StorageCodec>(v: Vec<(Vec<u8>, Vec<u8>)>) -> Vec<T> {
     let mut result = Vec::new();
     for item in v {
-        result.push(serde_json::from_slice(&item.1).unwrap());
+        result.push(T::decode(&item.1));
     }
     result
 }
@@ -1,8 +1,16 @@
-use crate::config::{CHECKPOINT_LENGTH, CHECKPOINT_PATH, DB_PATH};
+use crate::{
+    config::{CHECKPOINT_LENGTH, CHECKPOINT_PATH, DB_PATH},
+    metrics::CHECKPOINT_DURATION,
+    txn_db::db::open_secondary,
+};
 use log::{error, info};
 use rocksdb::checkpoint::Checkpoint;
+use serde::{Deserialize, Serialize};
 use std::{collections::BTreeSet, fs, path::Path, sync::Mutex};
 
+/// Kept beside the checkpoints, the database is replaced by the restore.
+const ROLLBACK_MARKER_FILE: &'static str = "rollback.json";
+
 lazy_static! {
     static ref CHECKPOINT_WORKING: Mutex<bool> = Mutex::new(false);
 }
@@ -16,14 +24,12 @@ pub fn make_checkpoint_sync(blocknumber: u64) -> Result<(), rocksdb::Error> {
         std::fs::create_dir(checkpoint_base_path).unwrap();
     }
 
-    let db = rocksdb::DB::open_as_secondary(
-        &rocksdb::Options::default(),
-        DB_PATH.as_str(),
-        secondary_path.to_str().unwrap(),
-    )?;
+    let timer = CHECKPOINT_DURATION.start_timer();
+    let db = open_secondary(DB_PATH.as_str(), secondary_path.to_str().unwrap())?;
 
     let checkpoint = Checkpoint::new(&db).unwrap();
     let result = checkpoint.create_checkpoint(checkpoint_path);
+    timer.observe_duration();
 
     info!("[checkpoint] new checkpoint: {}", blocknumber);
 
@@ -105,7 +111,7 @@ pub fn rollback(blocknumber: u64) -> bool {
 
     {
         let secondary_path = Path::new(DB_PATH.as_str()).join("./.rollback_tmpdb");
-        rocksdb::DB::open_as_secondary(&rocksdb::Options::default(), &checkpoint_path, &secondary_path).unwrap();
+        open_secondary(&checkpoint_path, &secondary_path).unwrap();
     }
 
     fs::remove_dir_all(DB_PATH.as_str()).unwrap();
@@ -124,3 +130,26 @@ pub fn rollback(blocknumber: u64) -> bool {
 
     true
 }
+
+/// What the restored database does not know about a rollback, picked up on the next start.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RollbackMarker {
+    pub blocknumber: u64,
+    /// the last webhook delivery id handed out before the restore
+    pub webhook_seq: u64,
+    /// checkpoints restored so far, this one included
+    #[serde(default)]
+    pub rollback_count: u64,
+}
+
+pub fn rollback_marker_save(marker: &RollbackMarker) {
+    let marker_path = Path::new(CHECKPOINT_PATH.as_str()).join(ROLLBACK_MARKER_FILE);
+    fs::write(marker_path, serde_json::to_string(marker).unwrap()).unwrap();
+}
+
+pub fn rollback_marker_take() -> Option<RollbackMarker> {
+    let marker_path = Path::new(CHECKPOINT_PATH.as_str()).join(ROLLBACK_MARKER_FILE);
+    let data = fs::read_to_string(&marker_path).ok()?;
+    fs::remove_file(&marker_path).unwrap();
+    Some(serde_json::from_str(&data).unwrap())
+}
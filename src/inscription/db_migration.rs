@@ -0,0 +1,467 @@
+use super::{
+    db::{is_content_category, key_column_family, InscribeDB, InscribeTxn, KEY_INSC_INDEX_ID, KEY_INSC_TOKEN_INDEX_ID},
+    marketplace::{
+        db::{
+            InscribeMarketDB, InscribeMarketTxn, KEY_MARKET_CANDLE_TICK_INTERVAL_TIME, KEY_MARKET_ORDER_INDEX_ID,
+            KEY_MARKET_TRADE_INDEX_TICK_TIME, KEY_MARKET_VOLUME_TICK_HOUR,
+        },
+        types::{MarketOrder, MarketOrderStatus, MarketOrderType, MarketTrade},
+    },
+    types::{Indexer, Inscription, InscriptionToken},
+};
+use crate::txn_db::{
+    codec::{storage_codec_version, StorageCodec, STORAGE_CODEC_VERSION},
+    transaction::DBTransaction,
+    DBBase,
+};
+use log::{error, info};
+use rocksdb::{Direction, IteratorMode, TransactionDB};
+
+const MIGRATE_BATCH: u64 = 100000;
+
+type MigrationStep = fn(&Indexer);
+
+/// Append only, the step at index `i` upgrades the database from version `i` to `i + 1`.
+const MIGRATION_STEPS: [(&'static str, MigrationStep); 4] = [
+    ("column families", Indexer::migrate_column_families),
+    ("storage codec", Indexer::migrate_storage_codec),
+    ("market trades", Indexer::migrate_market_trades),
+    ("content store", Indexer::migrate_content_store),
+];
+
+pub const DB_VERSION: u64 = MIGRATION_STEPS.len() as u64;
+
+impl Indexer {
+    /// Run the pending migration steps, refusing to start on a database written by a newer version.
+    pub fn migrate(&self) {
+        let db_version = self.db.read().unwrap().get_db_version();
+        if db_version > DB_VERSION {
+            error!(
+                "[migration] database version {} is newer than supported version {}, please upgrade",
+                db_version, DB_VERSION
+            );
+            std::process::exit(1);
+        }
+
+        for (index, (name, step)) in MIGRATION_STEPS.iter().enumerate().skip(db_version as usize) {
+            let version = index as u64 + 1;
+            info!("[migration] {} -> {}: {}", version - 1, version, name);
+
+            step(self);
+
+            let db = self.db.write().unwrap();
+            let txn = DBTransaction::new(&db);
+            txn.set_db_version(version);
+            txn.commit().unwrap();
+        }
+    }
+
+    /// Move the keys written before column families existed out of the default column family.
+    fn migrate_column_families(&self) {
+        let db = self.db.write().unwrap();
+        let mut txn = db.transaction();
+        let mut total_count = 0;
+        let mut iter = db.iterator(IteratorMode::Start);
+
+        while let Some(Ok((key, value))) = iter.next() {
+            let cf = db.cf_handle(key_column_family(&key)).unwrap();
+            txn.put_cf(cf, &key, value).unwrap();
+            txn.delete(&key).unwrap();
+            total_count += 1;
+            if total_count % MIGRATE_BATCH == 0 {
+                txn.commit().unwrap();
+                txn = db.transaction();
+                info!("[migration] column families: {}", total_count);
+            }
+        }
+
+        txn.commit().unwrap();
+        info!("[migration] column families total: {}", total_count);
+    }
+
+    /// Rewrite the json rows written before the storage codec existed.
+    fn migrate_storage_codec(&self) {
+        self.migrate_rows::<Inscription>(KEY_INSC_INDEX_ID);
+        self.migrate_rows::<InscriptionToken>(KEY_INSC_TOKEN_INDEX_ID);
+        self.migrate_rows::<MarketOrder>(KEY_MARKET_ORDER_INDEX_ID);
+    }
+
+    /// Record a trade for every listing closed before trades were stored per fill, and rebuild the volume and candles
+    /// from them.
+    fn migrate_market_trades(&self) {
+        let db = self.db.write().unwrap();
+        let mut txn = DBTransaction::new(&db);
+        let mut total_count = 0;
+        let prefix = KEY_MARKET_ORDER_INDEX_ID;
+        let mut iter = DBBase::iterator(&*db, IteratorMode::From(prefix.as_bytes(), Direction::Forward));
+
+        while let Some(Ok((key, value))) = iter.next() {
+            if !key.starts_with(prefix.as_bytes()) {
+                break;
+            }
+
+            let order = MarketOrder::decode(&value);
+            if order.order_status != MarketOrderStatus::Close {
+                continue;
+            }
+            if order.order_type != MarketOrderType::Token && order.order_type != MarketOrderType::NFT {
+                continue;
+            }
+
+            // the trade happened with the close tx, not at listing time
+            let timestamp = match db.get_inscription_by_tx(&order.tx_close) {
+                Some(insc) => insc.timestamp,
+                None => order.timestamp,
+            };
+            let creator = match db.get_inscription_nft_collection_by_id(order.nft_id) {
+                Some(collection) if order.nft_id > 0 => db.get_inscription_by_tx(&collection).map(|insc| insc.from),
+                _ => None,
+            };
+
+            let trade = MarketTrade {
+                tx: order.tx_close.clone(),
+                order_id: order.order_id.clone(),
+                order_type: order.order_type,
+                tick: order.tick.clone(),
+                nft_id: order.nft_id,
+                seller: order.from.clone(),
+                buyer: order.buyer.clone(),
+                amount: order.amount,
+                total_price: order.total_price,
+                unit_price: order.unit_price,
+                timestamp,
+                market: order.to.clone(),
+                creator: creator.unwrap_or_default(),
+                fee: order.fee,
+                royalty: order.royalty,
+                seller_proceeds: order.total_price.saturating_sub(order.fee + order.royalty),
+            };
+            txn.market_trade_save(&trade);
+            total_count += 1;
+            if total_count % MIGRATE_BATCH == 0 {
+                txn.commit().unwrap();
+                txn = DBTransaction::new(&db);
+                info!("[migration] market trades: {}", total_count);
+            }
+        }
+
+        txn.commit().unwrap();
+        info!("[migration] market trades total: {}", total_count);
+
+        Self::migrate_delete_prefix(&db, KEY_MARKET_VOLUME_TICK_HOUR);
+        Self::migrate_delete_prefix(&db, KEY_MARKET_CANDLE_TICK_INTERVAL_TIME);
+
+        // candles take their open and close from the order of the updates, the tick index read backwards has the
+        // trades of each tick oldest first
+        let mut txn = DBTransaction::new(&db);
+        let mut total_count = 0;
+        let prefix = KEY_MARKET_TRADE_INDEX_TICK_TIME.to_string() + ":";
+        let end_key = KEY_MARKET_TRADE_INDEX_TICK_TIME.to_string() + ";";
+        let mut iter = DBBase::iterator(&*db, IteratorMode::From(end_key.as_bytes(), Direction::Reverse));
+
+        while let Some(Ok((key, _))) = iter.next() {
+            if !key.starts_with(prefix.as_bytes()) {
+                break;
+            }
+
+            let key = String::from_utf8(key.to_vec()).unwrap();
+            // {tick}:{time}:{tx}:{order_id}
+            let (_, trade_key) = key[prefix.len()..].split_once(':').unwrap();
+            let trade_prefix = key[..key.len() - trade_key.len()].to_string();
+            let trade = db.market_get_trades_by_keys(&trade_prefix, &[key]).pop().unwrap();
+            if trade.order_type != MarketOrderType::Token && trade.order_type != MarketOrderType::TokenBid {
+                continue;
+            }
+
+            txn.market_volume_add(&trade.tick, trade.timestamp, trade.total_price);
+            txn.market_candle_update(
+                &trade.tick,
+                trade.timestamp,
+                trade.unit_price,
+                trade.total_price,
+                trade.amount,
+            );
+            total_count += 1;
+            if total_count % MIGRATE_BATCH == 0 {
+                txn.commit().unwrap();
+                txn = DBTransaction::new(&db);
+                info!("[migration] market volume and candles: {}", total_count);
+            }
+        }
+
+        txn.commit().unwrap();
+        info!("[migration] market volume and candles total: {}", total_count);
+    }
+
+    /// Move the transfer and invoke payloads back inline from the content store, and drop the rows left unused.
+    fn migrate_content_store(&self) {
+        {
+            let db = self.db.write().unwrap();
+            let mut txn = DBTransaction::new(&db);
+            let mut total_count = 0;
+            let prefix = KEY_INSC_INDEX_ID.to_string() + ":";
+            let mut iter = DBBase::iterator(&*db, IteratorMode::From(prefix.as_bytes(), Direction::Forward));
+
+            while let Some(Ok((key, value))) = iter.next() {
+                if !key.starts_with(prefix.as_bytes()) {
+                    break;
+                }
+
+                let mut insc = Inscription::decode(&value);
+                if is_content_category(&insc.mime_category) || insc.content_hash.is_none() {
+                    continue;
+                }
+
+                insc.mime_data = db
+                    .get_inscription_content(&insc.content_hash.take().unwrap())
+                    .unwrap_or_default();
+                txn.put(&key, insc.encode()).unwrap();
+                total_count += 1;
+                if total_count % MIGRATE_BATCH == 0 {
+                    txn.commit().unwrap();
+                    txn = DBTransaction::new(&db);
+                    info!("[migration] content store: {}", total_count);
+                }
+            }
+
+            txn.commit().unwrap();
+            info!("[migration] content store total: {}", total_count);
+        }
+
+        self.sweep_content();
+    }
+
+    fn migrate_delete_prefix(db: &TransactionDB, prefix: &str) {
+        let prefix = prefix.to_string() + ":";
+        let txn = DBTransaction::new(db);
+        let mut iter = DBBase::iterator(db, IteratorMode::From(prefix.as_bytes(), Direction::Forward));
+        while let Some(Ok((key, _))) = iter.next() {
+            if !key.starts_with(prefix.as_bytes()) {
+                break;
+            }
+            txn.delete(&key).unwrap();
+        }
+        txn.commit().unwrap();
+    }
+
+    fn migrate_rows<T: StorageCodec>(&self, prefix: &str) {
+        let db = self.db.write().unwrap();
+        let mut txn = DBTransaction::new(&db);
+        let mut total_count = 0;
+        let mut iter = DBBase::iterator(&*db, IteratorMode::From(prefix.as_bytes(), Direction::Forward));
+
+        while let Some(Ok((key, value))) = iter.next() {
+            if !key.starts_with(prefix.as_bytes()) {
+                break;
+            }
+
+            if storage_codec_version(&value) == STORAGE_CODEC_VERSION {
+                continue;
+            }
+
+            txn.put(&key, T::decode(&value).encode()).unwrap();
+            total_count += 1;
+            if total_count % MIGRATE_BATCH == 0 {
+                txn.commit().unwrap();
+                txn = DBTransaction::new(&db);
+                info!("[migration] storage codec: {} {}", prefix, total_count);
+            }
+        }
+
+        txn.commit().unwrap();
+        info!("[migration] storage codec total: {} {}", prefix, total_count);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        inscription::{
+            db::{make_content_hash, make_index_key},
+            marketplace::types::fixtures,
+            types::{InscriptionMimeCategory, InscriptionVerifiedStatus, WorkerInscribe, WorkerSync},
+            webhook::types::WorkerWebhook,
+        },
+        num_index,
+        txn_db::db::open_test_db,
+    };
+    use std::sync::{Arc, RwLock};
+
+    fn indexer(name: &str) -> Indexer {
+        let db = Arc::new(RwLock::new(open_test_db(name)));
+        Indexer {
+            db: db.clone(),
+            worker_sync: Arc::new(WorkerSync::new(db.clone())),
+            worker_inscribe: Arc::new(WorkerInscribe::new(db.clone())),
+            worker_webhook: Arc::new(WorkerWebhook::new(db.clone())),
+        }
+    }
+
+    fn order(order_id: &str, order_type: MarketOrderType, order_status: MarketOrderStatus) -> MarketOrder {
+        MarketOrder {
+            order_type,
+            order_status,
+            tx_close: format!("0xclose{}", order_id),
+            timestamp: 3600,
+            buyer: "0xbuyer".to_string(),
+            fee: 20,
+            royalty: 10,
+            ..fixtures::order(order_id)
+        }
+    }
+
+    #[test]
+    fn storage_codec_rewrites_json_rows() {
+        let indexer = indexer("migrate-codec");
+        {
+            let db = indexer.db.read().unwrap();
+            let txn = DBTransaction::new(&db);
+            let json = serde_json::to_vec(&order("0x01", MarketOrderType::NFT, MarketOrderStatus::Open)).unwrap();
+            txn.put(make_index_key(KEY_MARKET_ORDER_INDEX_ID, "0x01"), json).unwrap();
+            txn.market_order_save(&order("0x02", MarketOrderType::Token, MarketOrderStatus::Close));
+            txn.commit().unwrap();
+        }
+
+        indexer.migrate_storage_codec();
+
+        let db = indexer.db.read().unwrap();
+        for order_id in ["0x01", "0x02"] {
+            let data = DBBase::get(&*db, make_index_key(KEY_MARKET_ORDER_INDEX_ID, order_id))
+                .unwrap()
+                .unwrap();
+            assert_eq!(storage_codec_version(&data), STORAGE_CODEC_VERSION);
+        }
+        let order = db.market_get_order_by_id("0x01").unwrap();
+        assert_eq!(order.order_type, MarketOrderType::NFT);
+        assert_eq!(order.order_status, MarketOrderStatus::Open);
+        assert_eq!(order.total_price, 1000);
+    }
+
+    #[test]
+    fn market_trades_backfill_closed_listings() {
+        let indexer = indexer("migrate-trades");
+        {
+            let db = indexer.db.read().unwrap();
+            let txn = DBTransaction::new(&db);
+            txn.market_order_save(&order("0x01", MarketOrderType::Token, MarketOrderStatus::Close));
+            txn.market_order_save(&order("0x02", MarketOrderType::NFT, MarketOrderStatus::Close));
+            txn.market_order_save(&order("0x03", MarketOrderType::Token, MarketOrderStatus::Open));
+            txn.market_order_save(&order("0x04", MarketOrderType::TokenBid, MarketOrderStatus::Close));
+            // written by the listing close before trades existed, rebuilt from the trades
+            txn.market_volume_add("test", 3600, 999);
+            txn.commit().unwrap();
+        }
+
+        indexer.migrate_market_trades();
+
+        let db = indexer.db.read().unwrap();
+        let trades = db.market_get_latest_trades("test", 10);
+        let order_ids: Vec<&str> = trades.iter().map(|trade| trade.order_id.as_str()).collect();
+        assert_eq!(order_ids, vec!["0x01", "0x02"]);
+
+        let trade = &trades[0];
+        assert_eq!(trade.tx, "0xclose0x01");
+        assert_eq!(trade.seller, "0xseller");
+        assert_eq!(trade.buyer, "0xbuyer");
+        assert_eq!(trade.market, "0xmarket");
+        assert_eq!(trade.timestamp, 3600);
+        assert_eq!(trade.seller_proceeds, 970);
+
+        // only the token listing counts towards the volume
+        let volume = db.market_get_volume("test", 0);
+        assert_eq!(volume.volume, 1000);
+        assert_eq!(volume.txs, 1);
+    }
+
+    #[test]
+    fn market_trades_replay_candles_oldest_first() {
+        let indexer = indexer("migrate-candles");
+        {
+            let db = indexer.db.read().unwrap();
+            let txn = DBTransaction::new(&db);
+            for (order_id, timestamp, unit_price) in [("0x01", 3700, 10), ("0x02", 3650, 20), ("0x03", 3800, 15)] {
+                let mut order = order(order_id, MarketOrderType::Token, MarketOrderStatus::Close);
+                order.tick = "other".to_string();
+                order.timestamp = timestamp;
+                order.unit_price = unit_price;
+                txn.market_order_save(&order);
+            }
+            txn.commit().unwrap();
+        }
+
+        indexer.migrate_market_trades();
+
+        let db = indexer.db.read().unwrap();
+        let candles = db.market_get_candles("other", "1h", 0, 7200, 10);
+        assert_eq!(candles.len(), 1);
+        assert_eq!(candles[0].open, 20);
+        assert_eq!(candles[0].close, 15);
+        assert_eq!(candles[0].high, 20);
+        assert_eq!(candles[0].low, 10);
+        assert_eq!(candles[0].txs, 3);
+    }
+
+    #[test]
+    fn content_store_keeps_only_referenced_content() {
+        let indexer = indexer("migrate-content");
+        let transfer_hash = make_content_hash(b"0xtransfer");
+        let text_hash = make_content_hash(b"text");
+        {
+            let db = indexer.db.read().unwrap();
+            let txn = DBTransaction::new(&db);
+            for (id, mime_category, content_hash) in [
+                (1, InscriptionMimeCategory::Transfer, &transfer_hash),
+                (2, InscriptionMimeCategory::Text, &text_hash),
+            ] {
+                let insc = Inscription {
+                    id,
+                    from: "0xfrom".to_string(),
+                    to: "0xto".to_string(),
+                    blocknumber: 1,
+                    tx_hash: format!("0xtx{}", id),
+                    tx_index: 0,
+                    mime_category,
+                    mime_type: "".to_string(),
+                    mime_data: "".to_string(),
+                    timestamp: 10,
+                    event_logs: vec![],
+                    market_order_id: None,
+                    verified: InscriptionVerifiedStatus::Successful,
+                    fail_reason: None,
+                    signature: None,
+                    content_hash: Some(content_hash.clone()),
+                    json: serde_json::Value::Null,
+                };
+                txn.put(make_index_key(KEY_INSC_INDEX_ID, num_index!(id)), insc.encode())
+                    .unwrap();
+            }
+            txn.inscription_content_insert("0xtransfer");
+            txn.inscription_content_insert("text");
+            // left behind, no inscription refers to it
+            txn.inscription_content_insert("orphan");
+            txn.commit().unwrap();
+        }
+
+        indexer.migrate_content_store();
+
+        let db = indexer.db.read().unwrap();
+        let transfer = db.get_inscription_meta_by_id(1).unwrap();
+        assert_eq!(transfer.content_hash, None);
+        assert_eq!(transfer.mime_data, "0xtransfer");
+        assert_eq!(db.get_inscription_by_id(2).unwrap().mime_data, "text");
+        assert!(db.get_inscription_content(&transfer_hash).is_none());
+        assert!(db.get_inscription_content(&make_content_hash(b"orphan")).is_none());
+    }
+
+    #[test]
+    fn migrate_runs_to_the_latest_version() {
+        let indexer = indexer("migrate-version");
+        indexer.migrate();
+        assert_eq!(indexer.db.read().unwrap().get_db_version(), DB_VERSION);
+
+        // a migrated db has no pending steps
+        indexer.migrate();
+        assert_eq!(indexer.db.read().unwrap().get_db_version(), DB_VERSION);
+    }
+}
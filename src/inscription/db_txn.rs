@@ -1,12 +1,13 @@
 use super::db::*;
 use super::types::*;
+use crate::config::CONTENT_INLINE_MAX;
 use crate::num_index;
 use crate::num_index_desc;
-use crate::txn_db::TxnDB;
-use log::{debug, info};
-use rocksdb::{Transaction, TransactionDB};
+use crate::txn_db::{codec::StorageCodec, transaction::DBTransaction, TxnDB};
+use log::debug;
+use rocksdb::TransactionDB;
 
-impl<'a> InscribeTxn<'a> for Transaction<'a, TransactionDB> {
+impl<'a> InscribeTxn<'a> for DBTransaction<'a> {
     fn set_top_inscription_id(&self, id: u64) {
         self.put(KEY_INSC_TOP.as_bytes(), id.to_be_bytes()).unwrap();
     }
@@ -24,6 +25,18 @@ impl<'a> InscribeTxn<'a> for Transaction<'a, TransactionDB> {
             .unwrap();
     }
 
+    fn set_rollback_count(&self, count: u64) {
+        self.put(KEY_ROLLBACK_COUNT.as_bytes(), count.to_be_bytes()).unwrap();
+    }
+
+    fn set_reindex_pending(&self) {
+        self.put(KEY_REINDEX_PENDING.as_bytes(), 1u64.to_be_bytes()).unwrap();
+    }
+
+    fn set_db_version(&self, version: u64) {
+        self.put(KEY_DB_VERSION.as_bytes(), version.to_be_bytes()).unwrap();
+    }
+
     fn set_block_hash(&self, blocknumber: u64, block_hash: &str) {
         let key = make_index_key(KEY_SYNC_BLOCK_HASH, blocknumber);
         self.put(key.as_bytes(), block_hash.as_bytes()).unwrap();
@@ -68,9 +81,33 @@ impl<'a> InscribeTxn<'a> for Transaction<'a, TransactionDB> {
     }
 
     fn inscription_update(&self, insc: &Inscription) {
-        let insc_data = serde_json::to_string(insc).unwrap();
+        let content = is_content_category(&insc.mime_category);
+        let insc_data = if content && (insc.content_hash.is_some() || insc.mime_data.len() > *CONTENT_INLINE_MAX) {
+            let content_hash = match &insc.content_hash {
+                Some(content_hash) => content_hash.clone(),
+                None => self.inscription_content_insert(&insc.mime_data),
+            };
+            let insc_meta = Inscription {
+                mime_data: String::new(),
+                content_hash: Some(content_hash),
+                ..insc.clone()
+            };
+            insc_meta.encode()
+        } else {
+            insc.encode()
+        };
+
         let index_key_id = make_index_key(KEY_INSC_INDEX_ID, num_index!(insc.id));
-        self.put(index_key_id.as_bytes(), insc_data.as_bytes()).unwrap();
+        self.put(index_key_id.as_bytes(), insc_data).unwrap();
+    }
+
+    fn inscription_content_insert(&self, data: &str) -> String {
+        let content_hash = make_content_hash(data.as_bytes());
+        let index_key_content = make_index_key(KEY_INSC_CONTENT, &content_hash);
+        if self.get(index_key_content.as_bytes()).unwrap().is_none() {
+            self.put(index_key_content.as_bytes(), data.as_bytes()).unwrap();
+        }
+        content_hash
     }
 
     fn inscription_nft_holder_update(&self, db: &TransactionDB, id: u64, new_holder: &str) {
@@ -107,12 +144,12 @@ impl<'a> InscribeTxn<'a> for Transaction<'a, TransactionDB> {
     }
 
     fn inscription_token_insert(&self, token: &InscriptionToken) {
-        let json_data = serde_json::to_string(token).unwrap();
+        let token_data = token.encode();
         let index_key_id = make_index_key(KEY_INSC_TOKEN_INDEX_ID, num_index!(token.insc_id));
         let index_key_tick = make_index_key(KEY_INSC_TOKEN_INDEX_TICK, &token.tick);
         let index_key_tick_i = make_index_key(KEY_INSC_TOKEN_INDEX_TICK_I, &token.tick_i);
 
-        self.put(index_key_id.as_bytes(), json_data.as_bytes()).unwrap();
+        self.put(index_key_id.as_bytes(), token_data).unwrap();
         self.put(index_key_tick.as_bytes(), token.insc_id.to_be_bytes()).unwrap();
         self.put(index_key_tick_i.as_bytes(), token.insc_id.to_be_bytes()).unwrap();
     }
@@ -178,26 +215,6 @@ impl<'a> InscribeTxn<'a> for Transaction<'a, TransactionDB> {
 
     fn inscription_token_update(&self, token: &InscriptionToken) {
         let index_key_id = make_index_key(KEY_INSC_TOKEN_INDEX_ID, num_index!(token.insc_id));
-        let token_data = serde_json::to_string(token).unwrap();
-        self.put(index_key_id.as_bytes(), token_data.as_bytes()).unwrap();
-    }
-
-    fn delete_keys(&self, prefix: &str, max: u64) -> u64 {
-        let mut count = 0;
-        let mut iter = self.prefix_iterator(prefix.as_bytes());
-        while let Some(Ok((key, _))) = iter.next() {
-            if !key.starts_with(prefix.as_bytes()) {
-                break;
-            }
-
-            count += 1;
-            self.delete(key).unwrap();
-            if count >= max {
-                break;
-            }
-        }
-
-        info!("delete keys: {} {}", prefix, count);
-        count
+        self.put(index_key_id.as_bytes(), token.encode()).unwrap();
     }
 }
@@ -1,7 +1,7 @@
 use super::db::*;
 use super::types::*;
-use crate::num_index;
-use crate::txn_db::{DBBase, TxnDB};
+use crate::txn_db::{codec::StorageCodec, DBBase, TxnDB};
+use crate::{num_index, num_index_desc};
 use log::error;
 use rocksdb::{DBAccess, Direction, IteratorMode};
 use std::collections::HashMap;
@@ -23,6 +23,18 @@ impl<T: DBBase + TxnDB + DBAccess> InscribeDB for T {
         self.get_u64(KEY_ROLLBACK_BLOCKNUMBER)
     }
 
+    fn get_rollback_count(&self) -> u64 {
+        self.get_u64(KEY_ROLLBACK_COUNT)
+    }
+
+    fn get_reindex_pending(&self) -> bool {
+        self.get_u64(KEY_REINDEX_PENDING) > 0
+    }
+
+    fn get_db_version(&self) -> u64 {
+        self.get_u64(KEY_DB_VERSION)
+    }
+
     fn get_block_hash(&self, blocknumber: u64) -> Option<String> {
         let key = make_index_key(KEY_SYNC_BLOCK_HASH, blocknumber);
         self.get_string(&key)
@@ -34,19 +46,54 @@ impl<T: DBBase + TxnDB + DBAccess> InscribeDB for T {
     }
 
     fn get_inscription_by_id(&self, id: u64) -> Option<Inscription> {
-        let key_id = make_index_key(KEY_INSC_INDEX_ID, num_index!(id));
-        match self.get(key_id.as_bytes()).unwrap() {
-            Some(data) => {
-                let mut insc: Inscription = serde_json::from_slice(&data).unwrap();
-                if insc.mime_category == InscriptionMimeCategory::Json {
-                    insc.json = serde_json::from_str::<serde_json::Value>(insc.mime_data.as_str()).unwrap();
+        let mut insc = self.get_inscription_meta_by_id(id)?;
+        if let Some(content_hash) = &insc.content_hash {
+            match self.get_inscription_content(content_hash) {
+                Some(content) => insc.mime_data = content,
+                None => {
+                    // the meta row is still useful, callers see an empty payload
+                    error!("get_inscription_by_id content not found: {} {}", id, content_hash);
+                    return Some(insc);
+                }
+            }
+        }
+        if insc.mime_category == InscriptionMimeCategory::Json {
+            match serde_json::from_str::<serde_json::Value>(insc.mime_data.as_str()) {
+                Ok(json) => insc.json = json,
+                Err(err) => {
+                    error!("get_inscription_by_id json parse failed: {} {}", id, err);
+                    return None;
                 }
-                Some(insc)
             }
+        }
+        Some(insc)
+    }
+
+    fn get_inscription_meta_by_id(&self, id: u64) -> Option<Inscription> {
+        let key_id = make_index_key(KEY_INSC_INDEX_ID, num_index!(id));
+        match self.get(key_id.as_bytes()).unwrap() {
+            Some(data) => Some(Inscription::decode(&data)),
             None => None,
         }
     }
 
+    fn get_inscriptions_meta_by_id(&self, id_list: &[u64]) -> Vec<Inscription> {
+        let mut insc_list: Vec<Inscription> = Vec::new();
+        for id in id_list {
+            if let Some(insc) = self.get_inscription_meta_by_id(*id) {
+                insc_list.push(insc);
+            } else {
+                error!("get_inscriptions_meta_by_id Inscription not found: {}", id)
+            }
+        }
+        insc_list
+    }
+
+    fn get_inscription_content(&self, content_hash: &str) -> Option<String> {
+        let key = make_index_key(KEY_INSC_CONTENT, content_hash);
+        self.get_string(&key)
+    }
+
     fn get_inscriptions_by_id(&self, id_list: &Vec<u64>) -> Vec<Inscription> {
         let mut insc_list: Vec<Inscription> = Vec::new();
         for id in id_list {
@@ -80,6 +127,12 @@ impl<T: DBBase + TxnDB + DBAccess> InscribeDB for T {
         self.get_string(&key_id)
     }
 
+    fn inscription_nft_collection_exists(&self, tx: &str) -> bool {
+        let id = self.get_inscription_id_by_tx(tx);
+        let key_id = make_index_key(KEY_INSC_NFT_COLL_INDEX_ID, num_index_desc!(id));
+        id > 0 && self.get(key_id.as_bytes()).unwrap().is_some()
+    }
+
     fn get_first_value(&self, prefix: &str) -> Option<(Box<[u8]>, Box<[u8]>)> {
         let mut iter = self.iterator(IteratorMode::From(prefix.as_bytes(), Direction::Forward));
         match iter.next() {
@@ -104,7 +157,7 @@ impl<T: DBBase + TxnDB + DBAccess> InscribeDB for T {
 
         let key_id = make_index_key(KEY_INSC_TOKEN_INDEX_ID, num_index!(id));
         let result = self.get(key_id.as_bytes()).unwrap();
-        serde_json::from_slice(&result.unwrap()).unwrap()
+        Some(InscriptionToken::decode(&result.unwrap()))
     }
 
     fn get_tokens(&self) -> HashMap<String, InscriptionToken> {
@@ -116,7 +169,7 @@ impl<T: DBBase + TxnDB + DBAccess> InscribeDB for T {
                 break;
             }
 
-            let token: InscriptionToken = serde_json::from_slice(&value).unwrap();
+            let token = InscriptionToken::decode(&value);
             tokens.insert(token.tick.to_string(), token);
         }
 
@@ -132,7 +185,7 @@ impl<T: DBBase + TxnDB + DBAccess> InscribeDB for T {
                 break;
             }
 
-            let token: InscriptionToken = serde_json::from_slice(&value).unwrap();
+            let token = InscriptionToken::decode(&value);
             list.push(token);
         }
         list
@@ -149,8 +202,33 @@ impl<T: DBBase + TxnDB + DBAccess> InscribeDB for T {
     }
 
     fn get_items(&self, prefix: &str, start: &str, skip: u64, limit: u64, dir: rocksdb::Direction) -> Vec<(Vec<u8>, Vec<u8>)> {
+        self.get_items_after(prefix, start, None, skip, limit, dir)
+    }
+
+    fn get_item_keys(&self, prefix: &str, start: &str, skip: u64, limit: u64, dir: rocksdb::Direction) -> Vec<String> {
+        self.get_item_keys_after(prefix, start, None, skip, limit, dir)
+    }
+
+    /// Items of `prefix` from `start`, or right after the key `after` when it is set.
+    fn get_items_after(
+        &self,
+        prefix: &str,
+        start: &str,
+        after: Option<&str>,
+        skip: u64,
+        limit: u64,
+        dir: rocksdb::Direction,
+    ) -> Vec<(Vec<u8>, Vec<u8>)> {
         let mut item_list: Vec<(Vec<u8>, Vec<u8>)> = Vec::new();
-        let mut iter = self.iterator(IteratorMode::From(start.as_bytes(), dir));
+        let mut iter = self
+            .iterator(IteratorMode::From(after.unwrap_or(start).as_bytes(), dir))
+            .peekable();
+        if let (Some(after), Some(Ok((key, _)))) = (after, iter.peek()) {
+            if key.as_ref() == after.as_bytes() {
+                iter.next();
+            }
+        }
+
         for _ in 0..skip {
             if let Some(Ok(_)) = iter.next() {
             } else {
@@ -173,28 +251,18 @@ impl<T: DBBase + TxnDB + DBAccess> InscribeDB for T {
         item_list
     }
 
-    fn get_item_keys(&self, prefix: &str, start: &str, skip: u64, limit: u64, dir: rocksdb::Direction) -> Vec<String> {
-        let mut key_list: Vec<String> = Vec::new();
-        let mut iter = self.iterator(IteratorMode::From(start.as_bytes(), dir));
-        for _ in 0..skip {
-            if let Some(Ok(_)) = iter.next() {
-            } else {
-                break;
-            }
-        }
-
-        for _ in 0..limit {
-            if let Some(Ok((key, _))) = iter.next() {
-                if !key.starts_with(prefix.as_bytes()) {
-                    break;
-                }
-
-                key_list.push(String::from_utf8(key.to_vec()).unwrap());
-            } else {
-                break;
-            }
-        }
-
-        key_list
+    fn get_item_keys_after(
+        &self,
+        prefix: &str,
+        start: &str,
+        after: Option<&str>,
+        skip: u64,
+        limit: u64,
+        dir: rocksdb::Direction,
+    ) -> Vec<String> {
+        self.get_items_after(prefix, start, after, skip, limit, dir)
+            .into_iter()
+            .map(|(key, _)| String::from_utf8(key).unwrap())
+            .collect()
     }
 }
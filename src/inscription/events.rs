@@ -0,0 +1,221 @@
+use super::{
+    db::InscribeDB,
+    marketplace::{db::KEY_MARKET_ORDER_INDEX_ID, types::MarketOrder},
+    trait_json_value::JsonValueTrait,
+    types::*,
+    webhook::webhooks_enabled,
+};
+use crate::{
+    inscription::db::make_index_key,
+    txn_db::{codec::StorageCodec, transaction::DBTransaction},
+};
+use rocksdb::TransactionDB;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashSet, sync::Arc};
+use tokio::sync::broadcast;
+
+/// Events kept for subscribers which fall behind, older ones are dropped.
+pub const INSCRIBE_EVENTS_BUFFER: usize = 4096;
+
+lazy_static! {
+    pub static ref INSCRIBE_EVENTS: broadcast::Sender<Arc<InscribeEvent>> = broadcast::channel(INSCRIBE_EVENTS_BUFFER).0;
+}
+
+/// A change committed by the indexer, pushed to the api streams and webhooks.
+#[derive(Debug, Clone)]
+pub enum InscribeEvent {
+    Inscription(Inscription),
+    TokenTransfer {
+        tick: String,
+        insc_id: u64,
+        tx: String,
+        from: String,
+        to: String,
+        amount: u64,
+        blocknumber: u64,
+    },
+    NFTTransfer {
+        nft_id: u64,
+        transfer_id: u64,
+        tx: String,
+        from: String,
+        to: String,
+        holder: String,
+        collection: Option<String>,
+        blocknumber: u64,
+    },
+    MarketOrder {
+        order: MarketOrder,
+        collection: Option<String>,
+    },
+    Rollback {
+        blocknumber: u64,
+    },
+}
+
+impl InscribeEvent {
+    pub fn name(&self) -> &'static str {
+        match self {
+            InscribeEvent::Inscription(_) => "inscription",
+            InscribeEvent::TokenTransfer { .. } => "token_transfer",
+            InscribeEvent::NFTTransfer { .. } => "nft_transfer",
+            InscribeEvent::MarketOrder { .. } => "market_order",
+            InscribeEvent::Rollback { .. } => "rollback",
+        }
+    }
+}
+
+/// Filters of an event subscriber, every set filter must match. Rollbacks always match.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct InscribeEventFilter {
+    /// comma separated event names
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub events: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub address: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tick: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub protocol: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub category: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub collection: Option<String>,
+}
+
+impl InscribeEventFilter {
+    pub fn matches(&self, event: &InscribeEvent) -> bool {
+        let (addresses, tick, protocol, category, collection) = match event {
+            InscribeEvent::Inscription(insc) => (
+                vec![insc.from.as_str(), insc.to.as_str()],
+                insc.json["tick"].as_str(),
+                insc.json["p"].as_str(),
+                Some(insc.mime_category.as_str()),
+                None,
+            ),
+            InscribeEvent::TokenTransfer { tick, from, to, .. } => {
+                (vec![from.as_str(), to.as_str()], Some(tick.as_str()), None, None, None)
+            }
+            InscribeEvent::NFTTransfer {
+                from,
+                to,
+                holder,
+                collection,
+                ..
+            } => (
+                vec![from.as_str(), to.as_str(), holder.as_str()],
+                None,
+                None,
+                None,
+                collection.as_deref(),
+            ),
+            InscribeEvent::MarketOrder { order, collection } => (
+                vec![order.from.as_str(), order.buyer.as_str()],
+                Some(order.tick.as_str()),
+                None,
+                None,
+                collection.as_deref(),
+            ),
+            InscribeEvent::Rollback { .. } => return true,
+        };
+
+        if let Some(events) = &self.events {
+            if !events.split(',').any(|name| name.trim() == event.name()) {
+                return false;
+            }
+        }
+
+        if let Some(address) = &self.address {
+            let address = address.to_lowercase();
+            if !addresses.iter().any(|item| *item == address) {
+                return false;
+            }
+        }
+
+        let matches = |filter: &Option<String>, value: Option<&str>| match filter {
+            Some(filter) => value.is_some_and(|value| value.eq_ignore_ascii_case(filter)),
+            None => true,
+        };
+        matches(&self.tick, tick)
+            && matches(&self.protocol, protocol)
+            && matches(&self.category, category)
+            && matches(&self.collection, collection)
+    }
+}
+
+pub fn publish_events(events: Vec<InscribeEvent>) {
+    for event in events {
+        // no subscriber is not an error
+        let _ = INSCRIBE_EVENTS.send(Arc::new(event));
+    }
+}
+
+impl InscribeContext {
+    /// Events of the block range being saved, read through the transaction before it commits.
+    /// `market_orders` are the ids of the orders listed, changed or expired, one event each with its final state.
+    /// Empty when there is neither a stream subscriber nor a webhook.
+    pub fn inscribe_events(&self, db: &TransactionDB, txn: &DBTransaction, market_orders: &[String]) -> Vec<InscribeEvent> {
+        let mut events = Vec::new();
+        if INSCRIBE_EVENTS.receiver_count() == 0 && !webhooks_enabled() {
+            return events;
+        }
+
+        let insc_by_id = |id: u64| self.inscriptions.iter().find(|insc| insc.id == id);
+        let nft_collection = |nft_id: u64| match nft_id {
+            0 => None,
+            _ => db.get_inscription_nft_collection_by_id(nft_id),
+        };
+
+        for insc in &self.inscriptions {
+            events.push(InscribeEvent::Inscription(insc.clone()));
+        }
+
+        for (tick, insc_id) in &self.token_transfers {
+            let insc = insc_by_id(*insc_id).unwrap();
+            events.push(InscribeEvent::TokenTransfer {
+                tick: tick.clone(),
+                insc_id: insc.id,
+                tx: insc.tx_hash.clone(),
+                from: insc.from.clone(),
+                to: insc.to.clone(),
+                amount: insc.json["amt"].parse_u64().unwrap(),
+                blocknumber: insc.blocknumber,
+            });
+        }
+
+        for trans in &self.nft_transfers {
+            let insc = insc_by_id(trans.transfer_id).unwrap();
+            events.push(InscribeEvent::NFTTransfer {
+                nft_id: trans.nft_id,
+                transfer_id: trans.transfer_id,
+                tx: insc.tx_hash.clone(),
+                from: insc.from.clone(),
+                to: insc.to.clone(),
+                holder: self.nft_holders.get(&trans.nft_id).cloned().unwrap_or_default(),
+                collection: nft_collection(trans.nft_id),
+                blocknumber: insc.blocknumber,
+            });
+        }
+
+        let mut seen = HashSet::new();
+        for order_id in market_orders {
+            if !seen.insert(order_id) {
+                continue;
+            }
+
+            let index_key_id = make_index_key(KEY_MARKET_ORDER_INDEX_ID, order_id);
+            if let Some(data) = txn.get(index_key_id.as_bytes()).unwrap() {
+                let order = MarketOrder::decode(&data);
+                let collection = if order.collection.is_empty() {
+                    nft_collection(order.nft_id)
+                } else {
+                    Some(order.collection.clone())
+                };
+                events.push(InscribeEvent::MarketOrder { order, collection });
+            }
+        }
+
+        events
+    }
+}
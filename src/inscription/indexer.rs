@@ -1,47 +1,62 @@
 use super::{
     db::{InscribeDB, InscribeTxn},
-    types::{Indexer, WorkerInscribe, WorkerSync},
+    db_checkpoint::{rollback_marker_save, rollback_marker_take, RollbackMarker},
+    events::{publish_events, InscribeEvent},
+    types::{Indexer, Inscription, WorkerInscribe, WorkerSync},
+    webhook::{
+        db::{InscribeWebhookDB, InscribeWebhookTxn},
+        types::WorkerWebhook,
+    },
 };
 use crate::{
-    config::{DB_PATH, REINDEX},
-    global::{sleep_ms, ROLLBACK_BLOCK},
+    config::{DB_PATH, REINDEX, START_BLOCK},
+    global::{sleep_ms, REINDEX_REQUEST, ROLLBACK_BLOCK},
+    metrics::ROLLBACKS,
+    txn_db::{
+        codec::StorageCodec,
+        db::{column_family_options, open_txn_db},
+        transaction::DBTransaction,
+        DBBase,
+    },
 };
 use log::{error, info};
-use rocksdb::TransactionDB;
-use std::sync::{Arc, RwLock};
+use rocksdb::{Direction, IteratorMode};
+use std::{
+    collections::HashSet,
+    sync::{Arc, RwLock},
+};
 
 impl Indexer {
     pub fn new() -> Self {
-        let txn_db = TransactionDB::open_default(DB_PATH.as_str()).unwrap();
+        let txn_db = open_txn_db(DB_PATH.as_str());
         let db = Arc::new(RwLock::new(txn_db));
 
-        let start_sync_blocknumber = db.read().unwrap().get_sync_blocknumber();
-        info!("indexer start blocknumber: {}", start_sync_blocknumber);
-
         Indexer {
             db: db.clone(),
             worker_sync: Arc::new(WorkerSync::new(db.clone())),
             worker_inscribe: Arc::new(WorkerInscribe::new(db.clone())),
+            worker_webhook: Arc::new(WorkerWebhook::new(db.clone())),
         }
     }
 
-    fn delete_keys(&self, prefix: &str) {
-        let db = self.db.write().unwrap();
-        let mut total_delete_count = 0;
-        loop {
-            let txn = db.transaction();
-            let delete_count = txn.delete_keys(prefix, 1000000);
-            txn.commit().unwrap();
-            total_delete_count += delete_count;
-            info!("delete_keys total: {} {}", prefix, total_delete_count);
-            if delete_count < 1000000 {
-                break;
-            }
-        }
+    fn reset_column_family(&self, name: &str) {
+        let mut db = self.db.write().unwrap();
+        db.drop_cf(name).unwrap();
+        db.create_cf(name, &column_family_options()).unwrap();
+        info!("[indexer] reset column family: {}", name);
     }
 
     pub fn rollback(&self, blocknumber: u64) {
+        let (webhook_seq, rollback_count) = {
+            let db = self.db.read().unwrap();
+            (db.webhook_get_seq(), db.get_rollback_count())
+        };
         if crate::inscription::db_checkpoint::rollback(blocknumber) {
+            rollback_marker_save(&RollbackMarker {
+                blocknumber,
+                webhook_seq,
+                rollback_count: rollback_count + 1,
+            });
             info!("[checkpoint] rollback to: {}, need to restart", blocknumber);
         } else {
             error!("[checkpoint] rollback failed: {}", blocknumber);
@@ -50,70 +65,131 @@ impl Indexer {
     }
 
     pub fn init(&self) {
+        self.migrate();
+
+        let start_sync_blocknumber = self.db.read().unwrap().get_sync_blocknumber();
+        info!("indexer start blocknumber: {}", start_sync_blocknumber);
+
         let rollback_blocknumber = self.db.read().unwrap().get_rollback_blocknumber();
         if rollback_blocknumber > 0 {
             self.rollback(rollback_blocknumber);
         }
 
-        if *REINDEX {
+        // the outbox of the restored checkpoint does not have the rollback
+        if let Some(marker) = rollback_marker_take() {
+            {
+                let db = self.db.write().unwrap();
+                let txn = DBTransaction::new(&db);
+                txn.webhook_seq_restore(marker.webhook_seq);
+                txn.set_rollback_count(marker.rollback_count);
+                txn.webhook_enqueue(&[InscribeEvent::Rollback {
+                    blocknumber: marker.blocknumber,
+                }]);
+                txn.commit().unwrap();
+            }
+            self.sweep_content();
+            info!("[indexer] rollback to: {} done", marker.blocknumber);
+        }
+        ROLLBACKS.inc_by(self.db.read().unwrap().get_rollback_count());
+
+        if *REINDEX || self.db.read().unwrap().get_reindex_pending() {
             self.reindex();
         }
     }
 
     pub fn reindex(&self) {
         use super::db::*;
-        use super::marketplace::db::*;
-
-        self.delete_keys(KEY_ROLLBACK_BLOCKNUMBER);
-        // KEY_SYNC_BLOCKNUMBER
-        // KEY_SYNC_BLOCK_HASH
-
-        // KEY_INSC_SYNC_TOP
-        self.delete_keys(KEY_INSC_TOP);
-
-        // KEY_INSC_INDEX_ID
-        // KEY_INSC_INDEX_TX
-        self.delete_keys(KEY_INSC_INDEX_SIGN);
-        self.delete_keys(KEY_INSC_INDEX_NFT_ID);
-        // KEY_INSC_INDEX_CREATER
-        self.delete_keys(KEY_INSC_INDEX_ADDRESS);
-
-        self.delete_keys(KEY_INSC_NFT_INDEX_CREATER);
-        self.delete_keys(KEY_INSC_NFT_INDEX_HOLDER);
-        self.delete_keys(KEY_INSC_NFT_INDEX_HOLDER_ADDRESS);
-        self.delete_keys(KEY_INSC_NFT_TRANS_INDEX_ID);
-        self.delete_keys(KEY_INSC_NFT_COLL_INDEX_ID);
-        self.delete_keys(KEY_INSC_NFT_COLL_ITEM_INDEX_ID);
-
-        self.delete_keys(KEY_INSC_TOKEN_INDEX_ID);
-        self.delete_keys(KEY_INSC_TOKEN_INDEX_TICK);
-        self.delete_keys(KEY_INSC_TOKEN_INDEX_TICK_I);
-        self.delete_keys(KEY_INSC_TOKEN_TRANSFER);
-
-        self.delete_keys(KEY_INSC_BALANCE_INDEX_TICK_BALANCE_HOLDER);
-        self.delete_keys(KEY_INSC_BALANCE_INDEX_HOLDER_TICK);
-
-        self.delete_keys(KEY_MARKET_ORDER_INDEX_ID);
-        self.delete_keys(KEY_MARKET_ORDER_INDEX_SELLER);
-        self.delete_keys(KEY_MARKET_ORDER_INDEX_TICK_PRICE);
-        self.delete_keys(KEY_MARKET_ORDER_INDEX_NFT);
-        self.delete_keys(KEY_MARKET_ORDER_INDEX_TIME);
-        self.delete_keys(KEY_MARKET_ORDER_INDEX_TICK_TIME);
-        self.delete_keys(KEY_MARKET_ORDER_INDEX_SELLER_CLOSE_CANCEL);
-        self.delete_keys(KEY_MARKET_ORDER_INDEX_CLOSE_TICK_TIME);
+
+        {
+            let db = self.db.write().unwrap();
+            let txn = DBTransaction::new(&db);
+            txn.delete(KEY_ROLLBACK_BLOCKNUMBER).unwrap();
+            txn.delete(KEY_REINDEX_PENDING).unwrap();
+            // KEY_SYNC_BLOCKNUMBER
+            // KEY_SYNC_BLOCK_HASH
+
+            // KEY_INSC_SYNC_TOP
+            txn.delete(KEY_INSC_TOP).unwrap();
+            txn.commit().unwrap();
+        }
+
+        // CF_INSCRIPTION
+        self.reset_column_family(CF_INSCRIPTION_ADDRESS);
+        // CF_CONTENT
+        self.reset_column_family(CF_NFT);
+        self.reset_column_family(CF_TOKEN);
+        self.reset_column_family(CF_BALANCE);
+        self.reset_column_family(CF_MARKET);
+        self.sweep_content();
 
         info!("[indexer] reindex done");
     }
 
-    async fn check_rollback(&self) {
+    /// Delete the content rows no inscription refers to.
+    pub fn sweep_content(&self) {
+        use super::db::{KEY_INSC_CONTENT, KEY_INSC_INDEX_ID};
+
+        let db = self.db.write().unwrap();
+        let mut content_hashes = HashSet::new();
+        let prefix = KEY_INSC_INDEX_ID.to_string() + ":";
+        let mut iter = DBBase::iterator(&*db, IteratorMode::From(prefix.as_bytes(), Direction::Forward));
+        while let Some(Ok((key, value))) = iter.next() {
+            if !key.starts_with(prefix.as_bytes()) {
+                break;
+            }
+            if let Some(content_hash) = Inscription::decode(&value).content_hash {
+                content_hashes.insert(content_hash);
+            }
+        }
+
+        let txn = DBTransaction::new(&db);
+        let mut total_count = 0;
+        let prefix = KEY_INSC_CONTENT.to_string() + ":";
+        let mut iter = DBBase::iterator(&*db, IteratorMode::From(prefix.as_bytes(), Direction::Forward));
+        while let Some(Ok((key, _))) = iter.next() {
+            if !key.starts_with(prefix.as_bytes()) {
+                break;
+            }
+            let content_hash = String::from_utf8_lossy(&key[prefix.len()..]);
+            if !content_hashes.contains(content_hash.as_ref()) {
+                txn.delete(&key).unwrap();
+                total_count += 1;
+            }
+        }
+        txn.commit().unwrap();
+        info!("[indexer] content rows swept: {}", total_count);
+    }
+
+    /// Rollbacks and reindexes run on the next start, the process exits once one is requested.
+    async fn check_restart(&self) {
         loop {
-            let blocknumber = *ROLLBACK_BLOCK.lock().unwrap();
-            if blocknumber > 0 {
-                let db = self.db.write().unwrap();
-                let txn = db.transaction();
-                txn.set_rollback_blocknumber(blocknumber);
-                txn.commit().unwrap();
-                info!("[indexer] set rollback to: {}, need to restart", blocknumber);
+            let rollback_blocknumber = *ROLLBACK_BLOCK.lock().unwrap();
+            let reindex = *REINDEX_REQUEST.lock().unwrap();
+            if rollback_blocknumber > 0 || reindex {
+                let blocknumber = if rollback_blocknumber > 0 {
+                    rollback_blocknumber
+                } else {
+                    *START_BLOCK
+                };
+                let events = vec![InscribeEvent::Rollback { blocknumber }];
+                {
+                    let db = self.db.write().unwrap();
+                    let txn = DBTransaction::new(&db);
+                    if rollback_blocknumber > 0 {
+                        txn.set_rollback_blocknumber(rollback_blocknumber);
+                        info!("[indexer] set rollback to: {}, need to restart", rollback_blocknumber);
+                    } else {
+                        // a rollback is queued for the webhooks after the restore, the outbox is restored with it
+                        txn.set_reindex_pending();
+                        txn.webhook_enqueue(&events);
+                        info!("[indexer] set reindex, need to restart");
+                    }
+                    txn.commit().unwrap();
+                }
+
+                // give the api streams and webhooks a moment to deliver the event before exit
+                publish_events(events);
+                sleep_ms(1000).await;
                 std::process::exit(0);
             }
             sleep_ms(1000).await;
@@ -123,7 +199,8 @@ impl Indexer {
     pub async fn run(&self) {
         WorkerSync::run(self.worker_sync.clone());
         WorkerInscribe::run(self.worker_inscribe.clone());
+        WorkerWebhook::run(self.worker_webhook.clone());
 
-        self.check_rollback().await;
+        self.check_restart().await;
     }
 }
@@ -1,32 +1,33 @@
 use super::{
     db::{InscribeDB, InscribeTxn},
-    types::{InscribeContext, Inscription},
+    types::{InscribeContext, Inscription, InscriptionFailReason},
 };
+use crate::txn_db::transaction::DBTransaction;
 use log::{info, warn};
-use rocksdb::{Transaction, TransactionDB};
+use rocksdb::TransactionDB;
 pub const APP_OPER_COLLECTION_DEPLOY: &'static str = "deploy";
 
 pub trait ProcessBlockContextJsonCollection {
-    fn execute_app_collection(&self, insc: &Inscription) -> bool;
-    fn save_inscribe_collection(&self, db: &TransactionDB, txn: &Transaction<TransactionDB>, insc: &Inscription);
+    fn execute_app_collection(&self, insc: &mut Inscription) -> bool;
+    fn save_inscribe_collection(&self, db: &TransactionDB, txn: &DBTransaction, insc: &Inscription);
     fn get_nft_collection(&self, nft_id: u64) -> Option<String>;
 }
 
 impl ProcessBlockContextJsonCollection for InscribeContext {
-    fn execute_app_collection(&self, insc: &Inscription) -> bool {
+    fn execute_app_collection(&self, insc: &mut Inscription) -> bool {
         let oper = insc.json["op"].as_str();
         if oper.is_none() {
             warn!("[indexer] inscribe collection null oper: {}", insc.tx_hash);
-            return false;
+            return insc.fail(InscriptionFailReason::CollectionInvalidOperation);
         }
 
         let oper = oper.unwrap();
         if oper != APP_OPER_COLLECTION_DEPLOY {
             warn!("[indexer] inscribe collection invalid oper {}: {}", insc.tx_hash, oper);
-            return false;
+            return insc.fail(InscriptionFailReason::CollectionInvalidOperation);
         }
 
-        let json = &insc.json;
+        let json = insc.json.clone();
         if !json["items"].is_array()
             || !json["name"].is_string()
             || !json["description"].is_string()
@@ -34,20 +35,20 @@ impl ProcessBlockContextJsonCollection for InscribeContext {
             || !json["image"].is_string()
             || !json["icon"].is_string()
         {
-            return false;
+            return insc.fail(InscriptionFailReason::CollectionInvalidFields);
         }
 
         let items = json["items"].as_array().unwrap();
         for item in items {
             if !item.is_object() {
                 info!("[indexer] inscribe collection invalid item: {}", insc.tx_hash);
-                return false;
+                return insc.fail(InscriptionFailReason::CollectionInvalidItem);
             }
 
             let item_tx_hash = item["tx"].as_str();
             if item_tx_hash.is_none() {
                 info!("[indexer] inscribe collection invalid item: {}", insc.tx_hash);
-                return false;
+                return insc.fail(InscriptionFailReason::CollectionInvalidItem);
             }
 
             let item_tx_hash = item_tx_hash.unwrap();
@@ -57,7 +58,7 @@ impl ProcessBlockContextJsonCollection for InscribeContext {
                     "[indexer] inscribe collection item not found: {} {}",
                     insc.tx_hash, item_tx_hash
                 );
-                return false;
+                return insc.fail(InscriptionFailReason::CollectionItemNotFound);
             }
 
             let item_insc = item_insc.unwrap();
@@ -67,7 +68,7 @@ impl ProcessBlockContextJsonCollection for InscribeContext {
                     "[indexer] inscribe collection item already in collection: {} {}",
                     insc.tx_hash, item_tx_hash
                 );
-                return false;
+                return insc.fail(InscriptionFailReason::CollectionItemInCollection);
             }
 
             let item_holder = self.db.read().unwrap().get_inscription_nft_holder_by_id(item_insc.id);
@@ -76,7 +77,7 @@ impl ProcessBlockContextJsonCollection for InscribeContext {
                     "[indexer] inscribe collection item holder not found: {} {}",
                     insc.tx_hash, item_tx_hash
                 );
-                return false;
+                return insc.fail(InscriptionFailReason::CollectionItemNotFound);
             }
 
             if item_holder.unwrap() != insc.from {
@@ -84,7 +85,7 @@ impl ProcessBlockContextJsonCollection for InscribeContext {
                     "[indexer] inscribe collection item holder not match: {} {}",
                     insc.tx_hash, item_tx_hash,
                 );
-                return false;
+                return insc.fail(InscriptionFailReason::CollectionItemHolderMismatch);
             }
         }
 
@@ -102,7 +103,7 @@ impl ProcessBlockContextJsonCollection for InscribeContext {
         db.get_inscription_nft_collection_by_id(nft_id)
     }
 
-    fn save_inscribe_collection(&self, db: &TransactionDB, txn: &Transaction<TransactionDB>, insc: &Inscription) {
+    fn save_inscribe_collection(&self, db: &TransactionDB, txn: &DBTransaction, insc: &Inscription) {
         txn.inscription_nft_collection_insert(insc);
         let items = insc.json["items"].as_array().unwrap();
         for item in items {
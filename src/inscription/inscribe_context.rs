@@ -1,15 +1,17 @@
 use super::{
-    db::{InscribeDB, InscribeTxn},
+    db::{make_content_hash, InscribeDB, InscribeTxn},
+    events::publish_events,
     inscribe_json::ProcessBlockContextJson,
-    marketplace::MarketPlace,
+    marketplace::{inscribe_market::market_list_event, MarketPlace},
     types::*,
+    webhook::db::InscribeWebhookTxn,
 };
 use crate::config::MARKET_ADDRESS_LIST;
+use crate::txn_db::transaction::DBTransaction;
 use log::info;
-use rocksdb::{Transaction, TransactionDB};
-use sha1::{Digest, Sha1};
+use rocksdb::TransactionDB;
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     sync::{Arc, RwLock},
 };
 
@@ -24,13 +26,16 @@ impl InscribeContext {
             token_cache: db.read().unwrap().get_tokens(),
             token_balance_change: HashMap::new(),
             token_transfers: Vec::new(),
+            market_order_fills: HashMap::new(),
+            market_bid_accepts: HashSet::new(),
         }
     }
 
     pub fn save(&mut self) {
         let db = self.db.clone();
         let db = db.write().unwrap();
-        let txn = db.transaction();
+        let txn = DBTransaction::new(&db);
+        let mut market_orders = Vec::new();
 
         for insc in &self.inscriptions {
             txn.inscription_inscribe(insc);
@@ -38,18 +43,25 @@ impl InscribeContext {
                 if insc.mime_category == InscriptionMimeCategory::Json {
                     self.save_inscribe_json(&db, &txn, &insc);
                 }
-                self.save_market(&db, &txn, &insc);
+                market_orders.extend(self.save_market(&db, &txn, insc));
             }
         }
 
+        market_orders.extend(self.save_market_expire(&db, &txn));
+
         txn.set_top_inscription_id(self.inscriptions.last().unwrap().id);
 
         self.save_token(&db, &txn);
         self.save_token_transfer(&txn);
         self.save_nft_transfer(&db, &txn);
 
+        let events = self.inscribe_events(&db, &txn, &market_orders);
+        txn.webhook_enqueue(&events);
+
         txn.commit().unwrap();
 
+        publish_events(events);
+
         info!(
             "[indexer] inscribe inscriptions: {} + {}, blocknumber: {} -> {}",
             self.inscriptions.first().unwrap().id,
@@ -59,7 +71,7 @@ impl InscribeContext {
         );
     }
 
-    fn save_token(&mut self, db: &TransactionDB, txn: &Transaction<TransactionDB>) {
+    fn save_token(&mut self, db: &TransactionDB, txn: &DBTransaction) {
         for (_, token) in &self.token_cache {
             if token.deploy {
                 txn.inscription_token_insert(token);
@@ -104,7 +116,7 @@ impl InscribeContext {
             InscriptionMimeCategory::Json => self.process_inscribe_json(insc),
             InscriptionMimeCategory::Text | InscriptionMimeCategory::Image => self.process_inscribe_plain(insc),
             InscriptionMimeCategory::Invoke => self.process_inscribe_invoke(insc),
-            _ => false,
+            _ => insc.fail(InscriptionFailReason::MimeCategoryInvalid),
         };
 
         insc.verified = if ins_result {
@@ -115,13 +127,10 @@ impl InscribeContext {
     }
 
     fn process_inscribe_plain(&mut self, insc: &mut Inscription) -> bool {
-        let mut hasher = Sha1::new();
-        hasher.update(insc.mime_data.as_bytes());
-        let result = hasher.finalize();
-        let signature = format!("{:x}", result);
+        let signature = make_content_hash(insc.mime_data.as_bytes());
         if self.nft_signature_exists(signature.as_str()) {
             info!("[indexer] inscribe existed: {} {}", insc.tx_hash.as_str(), signature);
-            return false;
+            return insc.fail(InscriptionFailReason::NftContentExists);
         }
 
         self.nft_signatures.insert(signature.clone(), insc.id);
@@ -149,7 +158,12 @@ impl InscribeContext {
         }
     }
 
-    fn process_inscribe_nft_transfer(&mut self, insc: &Inscription) -> bool {
+    fn process_inscribe_nft_transfer(&mut self, insc: &mut Inscription) -> bool {
+        if MARKET_ADDRESS_LIST.contains(&insc.to) && market_list_event(insc).is_none() {
+            info!("[indexer] transfer to market without list event: {}", insc.tx_hash);
+            return insc.fail(InscriptionFailReason::MarketListEventNotFound);
+        }
+
         let mut trans = Vec::new();
 
         for i in (0..insc.mime_data.len()).step_by(TRANSFER_TX_HEX_LENGTH) {
@@ -162,7 +176,7 @@ impl InscribeContext {
                         "[indexer] transfer inscription mime category not match: {} {}",
                         insc.tx_hash, item_insc_tx
                     );
-                    return false;
+                    return insc.fail(InscriptionFailReason::TransferMimeCategoryInvalid);
                 }
 
                 let item_holder = self.get_nft_holder(item_insc.id);
@@ -182,11 +196,11 @@ impl InscribeContext {
                         "[indexer] transfer inscription holder not match: {} {}",
                         insc.tx_hash, item_insc_tx
                     );
-                    return false;
+                    return insc.fail(InscriptionFailReason::TransferHolderMismatch);
                 }
             } else {
                 info!("[indexer] transfer inscription not found: {} {}", insc.tx_hash, item_insc_tx);
-                return false;
+                return insc.fail(InscriptionFailReason::TransferInscriptionNotFound);
             }
         }
 
@@ -194,13 +208,13 @@ impl InscribeContext {
         true
     }
 
-    fn save_token_transfer(&self, txn: &rocksdb::Transaction<rocksdb::TransactionDB>) {
+    fn save_token_transfer(&self, txn: &DBTransaction) {
         for (tick, id) in &self.token_transfers {
             txn.inscription_token_transfer_insert(tick, *id);
         }
     }
 
-    fn save_nft_transfer(&self, db: &TransactionDB, txn: &rocksdb::Transaction<rocksdb::TransactionDB>) {
+    fn save_nft_transfer(&self, db: &TransactionDB, txn: &DBTransaction) {
         for trans in &self.nft_transfers {
             txn.inscription_nft_transfer_insert(trans);
         }
@@ -1,34 +1,35 @@
 use super::{
     inscribe_collection::ProcessBlockContextJsonCollection,
     inscribe_token::ProcessBlockContextJsonToken,
-    types::{InscribeContext, Inscription, APP_PROTO_COLLECTION},
+    types::{InscribeContext, Inscription, InscriptionFailReason, APP_PROTO_COLLECTION},
 };
 use crate::config::TOKEN_PROTOCOL;
+use crate::txn_db::transaction::DBTransaction;
 use log::info;
-use rocksdb::{Transaction, TransactionDB};
+use rocksdb::TransactionDB;
 
 pub trait ProcessBlockContextJson {
-    fn process_inscribe_json(&mut self, insc: &Inscription) -> bool;
-    fn save_inscribe_json(&self, db: &TransactionDB, txn: &Transaction<TransactionDB>, insc: &Inscription);
+    fn process_inscribe_json(&mut self, insc: &mut Inscription) -> bool;
+    fn save_inscribe_json(&self, db: &TransactionDB, txn: &DBTransaction, insc: &Inscription);
 }
 
 impl ProcessBlockContextJson for InscribeContext {
-    fn process_inscribe_json(&mut self, insc: &Inscription) -> bool {
-        if let Some(protocol) = insc.json["p"].as_str() {
+    fn process_inscribe_json(&mut self, insc: &mut Inscription) -> bool {
+        if let Some(protocol) = insc.json["p"].as_str().map(|protocol| protocol.to_string()) {
             if protocol == *TOKEN_PROTOCOL {
                 self.execute_app_token(insc)
             } else if protocol == APP_PROTO_COLLECTION {
                 self.execute_app_collection(insc)
             } else {
                 info!("[indexer] inscribe json: unknown protocol: {} {}", insc.tx_hash, protocol);
-                false
+                insc.fail(InscriptionFailReason::JsonUnknownProtocol)
             }
         } else {
-            false
+            insc.fail(InscriptionFailReason::JsonUnknownProtocol)
         }
     }
 
-    fn save_inscribe_json(&self, db: &TransactionDB, txn: &Transaction<TransactionDB>, insc: &Inscription) {
+    fn save_inscribe_json(&self, db: &TransactionDB, txn: &DBTransaction, insc: &Inscription) {
         let p = insc.json["p"].as_str().unwrap();
         if p == APP_PROTO_COLLECTION {
             self.save_inscribe_collection(db, txn, insc);
@@ -2,7 +2,7 @@ use super::{
     db::InscribeDB,
     marketplace::APP_OPER_TOKEN_MARKET_LIST,
     trait_json_value::JsonValueTrait,
-    types::{InscribeContext, Inscription, InscriptionToken},
+    types::{InscribeContext, Inscription, InscriptionFailReason, InscriptionToken},
 };
 use crate::config::{MARKET_ADDRESS_LIST, START_BLOCK_MINT, TICK_MAX_LEN};
 use log::{debug, info};
@@ -12,84 +12,91 @@ const APP_OPER_TOKEN_DEPLOY: &'static str = "deploy";
 const APP_OPER_TOKEN_MINT: &'static str = "mint";
 const APP_OPER_TOKEN_TRANSFER: &'static str = "transfer";
 
-const TOKEN_BALANCE_MAX: u64 = 1e18 as u64;
+pub const TOKEN_BALANCE_MAX: u64 = 1e18 as u64;
 
 pub trait ProcessBlockContextJsonToken {
-    fn check_deploy(&self, insc: &Inscription) -> bool;
-    fn check_mint(&self, insc: &Inscription) -> bool;
-    fn check_transfer(&self, insc: &Inscription) -> bool;
+    fn check_deploy(&self, insc: &mut Inscription) -> bool;
+    fn check_mint(&self, insc: &mut Inscription) -> bool;
+    fn check_transfer(&self, insc: &mut Inscription) -> bool;
 
     fn get_token_balance(&self, tick: &str, address: &str) -> u64;
     fn token_balance_change_update(&mut self, tick: &str, address: &str, amount: i64);
 
-    fn execute_app_token(&mut self, insc: &Inscription) -> bool;
-    fn execute_app_token_deploy(&mut self, insc: &Inscription) -> bool;
-    fn execute_app_token_mint(&mut self, insc: &Inscription) -> bool;
-    fn execute_app_token_transfer(&mut self, insc: &Inscription) -> bool;
-    fn execute_app_token_market_list(&mut self, insc: &Inscription) -> bool;
+    fn execute_app_token(&mut self, insc: &mut Inscription) -> bool;
+    fn execute_app_token_deploy(&mut self, insc: &mut Inscription) -> bool;
+    fn execute_app_token_mint(&mut self, insc: &mut Inscription) -> bool;
+    fn execute_app_token_transfer(&mut self, insc: &mut Inscription) -> bool;
+    fn execute_app_token_market_list(&mut self, insc: &mut Inscription) -> bool;
 }
 
 impl ProcessBlockContextJsonToken for InscribeContext {
-    fn execute_app_token(&mut self, insc: &Inscription) -> bool {
+    fn execute_app_token(&mut self, insc: &mut Inscription) -> bool {
         let op_value = &insc.json["op"];
         if !op_value.is_string() {
             info!("[indexer] inscribe token invalid oper {}", insc.tx_hash);
-            return false;
+            return insc.fail(InscriptionFailReason::TokenInvalidOperation);
         }
 
         if !insc.json["tick"].is_string() {
             info!("[indexer] inscribe token invalid tick {}", insc.tx_hash);
-            return false;
+            return insc.fail(InscriptionFailReason::TokenInvalidTick);
         }
 
-        let op = op_value.as_str().unwrap();
+        let op = op_value.as_str().unwrap().to_string();
 
-        match op {
+        match op.as_str() {
             APP_OPER_TOKEN_DEPLOY => self.execute_app_token_deploy(insc),
             APP_OPER_TOKEN_MINT => self.execute_app_token_mint(insc),
             APP_OPER_TOKEN_TRANSFER => self.execute_app_token_transfer(insc),
             APP_OPER_TOKEN_MARKET_LIST => self.execute_app_token_market_list(insc),
-            _ => false,
+            _ => {
+                info!("[indexer] inscribe token unknown oper {}: {}", insc.tx_hash, op);
+                insc.fail(InscriptionFailReason::TokenInvalidOperation)
+            }
         }
     }
 
-    fn check_deploy(&self, insc: &Inscription) -> bool {
-        let tick = insc.json["tick"].as_str().unwrap();
+    fn check_deploy(&self, insc: &mut Inscription) -> bool {
+        let tick = insc.json["tick"].as_str().unwrap().to_string();
         if tick.len() > *TICK_MAX_LEN {
-            return false;
+            return insc.fail(InscriptionFailReason::TokenInvalidTick);
         }
 
         if tick.find(':').is_some() {
-            return false;
+            return insc.fail(InscriptionFailReason::TokenInvalidTick);
         }
 
         let token_max = match insc.json["max"].parse_u64() {
             Some(value) => value,
             None => {
-                return false;
+                return insc.fail(InscriptionFailReason::TokenInvalidMax);
             }
         };
 
         let token_lmi = match insc.json["lmi"].parse_u64() {
             Some(value) => value,
             None => {
-                return false;
+                return insc.fail(InscriptionFailReason::TokenInvalidLimit);
             }
         };
 
-        if token_max > TOKEN_BALANCE_MAX || token_lmi > token_max {
-            return false;
+        if token_max > TOKEN_BALANCE_MAX {
+            return insc.fail(InscriptionFailReason::TokenInvalidMax);
+        }
+
+        if token_lmi > token_max {
+            return insc.fail(InscriptionFailReason::TokenInvalidLimit);
         }
 
-        if self.db.read().unwrap().token_exists_i(tick) {
+        if self.db.read().unwrap().token_exists_i(&tick) {
             info!("[indexer] inscribe token deploy: token existed: {} {}", insc.tx_hash, tick);
-            return false;
+            return insc.fail(InscriptionFailReason::TokenExists);
         }
 
         true
     }
 
-    fn execute_app_token_deploy(&mut self, insc: &Inscription) -> bool {
+    fn execute_app_token_deploy(&mut self, insc: &mut Inscription) -> bool {
         if !self.check_deploy(insc) {
             return false;
         }
@@ -117,8 +124,6 @@ impl ProcessBlockContextJsonToken for InscribeContext {
                 deploy: true,
 
                 market_updated: false,
-                market_volume24h: 0,
-                market_txs24h: 0,
                 market_cap: 0,
                 market_floor_price: 0,
             },
@@ -128,58 +133,58 @@ impl ProcessBlockContextJsonToken for InscribeContext {
         true
     }
 
-    fn check_mint(&self, insc: &Inscription) -> bool {
+    fn check_mint(&self, insc: &mut Inscription) -> bool {
         if *START_BLOCK_MINT > insc.blocknumber {
-            return false;
+            return insc.fail(InscriptionFailReason::TokenMintNotStarted);
         }
 
-        let tick = insc.json["tick"].as_str().unwrap();
+        let tick = insc.json["tick"].as_str().unwrap().to_string();
         let mint_amt = match insc.json["amt"].parse_u64() {
             Some(value) => value,
             None => {
                 debug!("[indexer] token mint: invalid amount: {} {}", insc.tx_hash, tick);
-                return false;
+                return insc.fail(InscriptionFailReason::TokenInvalidAmount);
             }
         };
 
         if mint_amt == 0 || mint_amt > TOKEN_BALANCE_MAX {
             debug!("[indexer] token mint: invalid amount: {} {} {}", insc.tx_hash, tick, mint_amt);
-            return false;
+            return insc.fail(InscriptionFailReason::TokenInvalidAmount);
         }
 
-        let token = match self.token_cache.get(tick) {
+        let token = match self.token_cache.get(&tick) {
             Some(value) => value,
             None => {
                 debug!("[indexer] token mint: token not found: {} {}", insc.tx_hash, tick);
-                return false;
+                return insc.fail(InscriptionFailReason::TokenNotFound);
             }
         };
 
         if mint_amt > token.mint_limit {
             debug!("[indexer] token mint: mint limit: {} {}", insc.tx_hash, tick);
-            return false;
+            return insc.fail(InscriptionFailReason::TokenMintLimitExceeded);
         }
 
         if token.mint_finished {
             debug!("[indexer] token mint: mint finished: {} {}", insc.tx_hash, tick);
-            return false;
+            return insc.fail(InscriptionFailReason::TokenMintFinished);
         }
 
         if mint_amt + token.mint_progress > token.mint_max {
             debug!("[indexer] token mint: mint overflow: {} {}", insc.tx_hash, tick);
-            return false;
+            return insc.fail(InscriptionFailReason::TokenMintOverflow);
         }
 
         true
     }
 
-    fn check_transfer(&self, insc: &Inscription) -> bool {
-        let tick = insc.json["tick"].as_str().unwrap();
+    fn check_transfer(&self, insc: &mut Inscription) -> bool {
+        let tick = insc.json["tick"].as_str().unwrap().to_string();
         let transfer_amt = match insc.json["amt"].parse_u64() {
             Some(value) => value,
             None => {
                 info!("[indexer] token transfer: invalid amount: {} {}", insc.tx_hash, tick);
-                return false;
+                return insc.fail(InscriptionFailReason::TokenInvalidAmount);
             }
         };
 
@@ -188,26 +193,26 @@ impl ProcessBlockContextJsonToken for InscribeContext {
                 "[indexer] token transfer: invalid amount: {} {} {}",
                 insc.tx_hash, tick, transfer_amt
             );
-            return false;
+            return insc.fail(InscriptionFailReason::TokenInvalidAmount);
         }
 
-        let token = match self.token_cache.get(tick) {
+        let token = match self.token_cache.get(&tick) {
             Some(value) => value,
             None => {
                 info!("[indexer] token transfer: token not found: {} {}", insc.tx_hash, tick);
-                return false;
+                return insc.fail(InscriptionFailReason::TokenNotFound);
             }
         };
 
         if !token.mint_finished {
             info!("[indexer] token transfer: mint not finished: {} {}", insc.tx_hash, tick);
-            return false;
+            return insc.fail(InscriptionFailReason::TokenMintNotFinished);
         }
 
         true
     }
 
-    fn execute_app_token_mint(&mut self, insc: &Inscription) -> bool {
+    fn execute_app_token_mint(&mut self, insc: &mut Inscription) -> bool {
         if !self.check_mint(insc) {
             return false;
         }
@@ -260,7 +265,7 @@ impl ProcessBlockContextJsonToken for InscribeContext {
         }
     }
 
-    fn execute_app_token_transfer(&mut self, insc: &Inscription) -> bool {
+    fn execute_app_token_transfer(&mut self, insc: &mut Inscription) -> bool {
         if !self.check_transfer(insc) {
             return false;
         }
@@ -283,17 +288,25 @@ impl ProcessBlockContextJsonToken for InscribeContext {
                 "[indexer] token transfer failed: {} {} {} {} {} {} ",
                 insc.tx_hash, insc.from, insc.to, tick, balance_from, transfer_amount
             );
-            false
+            insc.fail(InscriptionFailReason::TokenBalanceInsufficient)
         }
     }
 
-    fn execute_app_token_market_list(&mut self, insc: &Inscription) -> bool {
+    fn execute_app_token_market_list(&mut self, insc: &mut Inscription) -> bool {
         if !MARKET_ADDRESS_LIST.contains(&insc.to) {
             info!(
                 "[indexer] token market list: invalid market address: {} {} {}",
                 insc.tx_hash, insc.from, insc.to
             );
-            return false;
+            return insc.fail(InscriptionFailReason::MarketAddressInvalid);
+        }
+        if !insc.json["expire"].is_null() && insc.json["expire"].parse_u64().is_none() {
+            info!("[indexer] token market list: invalid expire: {} {}", insc.tx_hash, insc.from);
+            return insc.fail(InscriptionFailReason::MarketExpireInvalid);
+        }
+        if insc.market_order_id.is_none() {
+            info!("[indexer] token market list: no order id: {} {}", insc.tx_hash, insc.from);
+            return insc.fail(InscriptionFailReason::MarketListEventNotFound);
         }
         info!("[indexer] token market list: {} {} {}", insc.tx_hash, insc.from, insc.to);
         self.execute_app_token_transfer(insc)
@@ -1,10 +1,15 @@
-use super::types::{MarketOrder, MarketOrderStatus, MarketOrderType};
+use super::types::{
+    MarketCandle, MarketFee, MarketOrder, MarketOrderStatus, MarketOrderType, MarketRevenue, MarketTrade, MarketVolume,
+};
 use crate::{
-    inscription::db::{make_index_key, make_index_key2, make_index_key3},
+    inscription::{
+        db::{make_index_key, make_index_key2, make_index_key3, InscribeDB},
+        types::InscriptionFailReason,
+    },
     num_index, num_index_desc,
-    txn_db::{DBBase, TxnDB},
+    txn_db::{codec::StorageCodec, transaction::DBTransaction, DBBase, TxnDB},
 };
-use rocksdb::{DBAccess, Transaction, TransactionDB};
+use rocksdb::{DBAccess, TransactionDB};
 
 pub const KEY_MARKET_ORDER_INDEX_ID: &'static str = "market_id";
 pub const KEY_MARKET_ORDER_INDEX_SELLER: &'static str = "market_seller-sort-id";
@@ -14,57 +19,200 @@ pub const KEY_MARKET_ORDER_INDEX_TIME: &'static str = "market_time-id";
 pub const KEY_MARKET_ORDER_INDEX_TICK_TIME: &'static str = "market_tick_time-id";
 pub const KEY_MARKET_ORDER_INDEX_SELLER_CLOSE_CANCEL: &'static str = "market_seller_close_cancel-sort-id";
 pub const KEY_MARKET_ORDER_INDEX_CLOSE_TICK_TIME: &'static str = "market_close_tick_time-id";
+pub const KEY_MARKET_ORDER_INDEX_EXPIRE: &'static str = "market_expire_time-id";
+
+// market trade, one per fill
+pub const KEY_MARKET_TRADE_INDEX_ID: &'static str = "market_trade_tx_id";
+pub const KEY_MARKET_TRADE_INDEX_TICK_TIME: &'static str = "market_trade_tick_time-tx-id";
+pub const KEY_MARKET_TRADE_INDEX_BUYER: &'static str = "market_trade_buyer-time-tx-id";
+pub const KEY_MARKET_TRADE_INDEX_SELLER: &'static str = "market_trade_seller-time-tx-id";
+
+// market bid, best price first
+pub const KEY_MARKET_BID_INDEX_TICK_PRICE: &'static str = "market_bid_tick_price-id";
+pub const KEY_MARKET_BID_INDEX_COLLECTION_PRICE: &'static str = "market_bid_collection_price-id";
+pub const KEY_MARKET_BID_INDEX_NFT_PRICE: &'static str = "market_bid_nft_price-id";
+
+// market volume
+pub const KEY_MARKET_VOLUME_TICK_HOUR: &'static str = "market_volume_tick_hour";
+pub const MARKET_VOLUME_BUCKET: u64 = 3600;
+
+// market revenue, fee and royalty totals
+pub const KEY_MARKET_REVENUE_MARKET: &'static str = "market_revenue_market";
+pub const KEY_MARKET_REVENUE_CREATOR: &'static str = "market_revenue_creator";
+
+// market candle
+pub const KEY_MARKET_CANDLE_TICK_INTERVAL_TIME: &'static str = "market_candle_tick_interval_time";
+pub const MARKET_CANDLE_INTERVALS: [(&'static str, u64); 3] = [("1m", 60), ("1h", 3600), ("1d", 86400)];
+pub const MARKET_CANDLE_MAX_COUNT: u64 = 1000;
+
+pub fn market_candle_interval(interval: &str) -> Option<u64> {
+    MARKET_CANDLE_INTERVALS
+        .iter()
+        .find(|(name, _)| *name == interval)
+        .map(|(_, seconds)| *seconds)
+}
+
+/// Sorts u128 prices from high to low, wei prices do not fit `num_index_desc!`.
+pub fn price_index_desc(price: u128) -> String {
+    format!("{:039}", u128::MAX - price)
+}
+
+/// The best bid index key of a bid order, None for listings.
+pub fn market_bid_index_key(order: &MarketOrder) -> Option<String> {
+    match order.order_type {
+        MarketOrderType::TokenBid => Some(make_index_key3(
+            KEY_MARKET_BID_INDEX_TICK_PRICE,
+            &order.tick,
+            price_index_desc(order.unit_price),
+            &order.order_id,
+        )),
+        MarketOrderType::NFTBid if !order.collection.is_empty() => Some(make_index_key3(
+            KEY_MARKET_BID_INDEX_COLLECTION_PRICE,
+            &order.collection,
+            price_index_desc(order.total_price),
+            &order.order_id,
+        )),
+        MarketOrderType::NFTBid => Some(make_index_key3(
+            KEY_MARKET_BID_INDEX_NFT_PRICE,
+            num_index!(order.nft_id),
+            price_index_desc(order.total_price),
+            &order.order_id,
+        )),
+        MarketOrderType::NFT | MarketOrderType::Token => None,
+    }
+}
 
 pub trait InscribeMarketDB: TxnDB {
     fn market_get_order_by_id(&self, order_id: &str) -> Option<MarketOrder>;
-    fn market_get_latest_closed_orders(&self, tick: &str, count: u64) -> Vec<MarketOrder>;
+    fn market_get_trades(&self, prefix: &str, skip: u64, count: u64) -> Vec<MarketTrade>;
+    fn market_get_trades_by_keys(&self, prefix: &str, key_list: &[String]) -> Vec<MarketTrade>;
+    fn market_get_latest_trades(&self, tick: &str, count: u64) -> Vec<MarketTrade>;
+    fn market_get_address_trades(&self, index: &str, address: &str, skip: u64, count: u64) -> Vec<MarketTrade>;
+    fn market_get_volume(&self, tick: &str, since: u64) -> MarketVolume;
+    fn market_get_candles(&self, tick: &str, interval: &str, from: u64, to: u64, limit: u64) -> Vec<MarketCandle>;
+    fn market_get_revenue(&self, index: &str, address: &str) -> MarketRevenue;
 }
 
 impl<T: DBBase + TxnDB + DBAccess> InscribeMarketDB for T {
     fn market_get_order_by_id(&self, order_id: &str) -> Option<MarketOrder> {
         let index_key_id = make_index_key(KEY_MARKET_ORDER_INDEX_ID, &order_id);
         match self.get(index_key_id.as_bytes()).unwrap() {
-            Some(data) => Some(serde_json::from_slice(&data).unwrap()),
+            Some(data) => Some(MarketOrder::decode(&data)),
             None => None,
         }
     }
 
-    fn market_get_latest_closed_orders(&self, tick: &str, count: u64) -> Vec<MarketOrder> {
-        let mut order_count = 0;
-        let prefix = make_index_key(KEY_MARKET_ORDER_INDEX_CLOSE_TICK_TIME, tick) + ":";
-        let mut iter = self.iterator(rocksdb::IteratorMode::From(prefix.as_bytes(), rocksdb::Direction::Forward));
-        let mut orders = Vec::new();
+    /// Trades of a `{prefix}{time}:{tx}:{order_id}` index, newest first.
+    fn market_get_trades(&self, prefix: &str, skip: u64, count: u64) -> Vec<MarketTrade> {
+        let key_list = self.get_item_keys(prefix, prefix, skip, count, rocksdb::Direction::Forward);
+        self.market_get_trades_by_keys(prefix, &key_list)
+    }
 
-        while let Some(Ok((key, _))) = iter.next() {
+    /// Trades of index keys read from a `{prefix}{time}:{tx}:{order_id}` index.
+    fn market_get_trades_by_keys(&self, prefix: &str, key_list: &[String]) -> Vec<MarketTrade> {
+        let mut trades = Vec::new();
+        for key in key_list {
+            // {time}:{tx}:{order_id}
+            let (_, trade_id) = key[prefix.len()..].split_once(':').unwrap();
+            let index_key_id = make_index_key(KEY_MARKET_TRADE_INDEX_ID, trade_id);
+            let data = self.get(index_key_id.as_bytes()).unwrap().unwrap();
+
+            trades.push(MarketTrade::decode(&data));
+        }
+        trades
+    }
+
+    fn market_get_latest_trades(&self, tick: &str, count: u64) -> Vec<MarketTrade> {
+        let prefix = make_index_key(KEY_MARKET_TRADE_INDEX_TICK_TIME, tick) + ":";
+        self.market_get_trades(&prefix, 0, count)
+    }
+
+    fn market_get_address_trades(&self, index: &str, address: &str, skip: u64, count: u64) -> Vec<MarketTrade> {
+        let prefix = make_index_key(index, address) + ":";
+        self.market_get_trades(&prefix, skip, count)
+    }
+
+    fn market_get_volume(&self, tick: &str, since: u64) -> MarketVolume {
+        let prefix = make_index_key(KEY_MARKET_VOLUME_TICK_HOUR, tick) + ":";
+        let since_bucket = since.div_ceil(MARKET_VOLUME_BUCKET);
+        let start_key = make_index_key2(KEY_MARKET_VOLUME_TICK_HOUR, tick, num_index!(since_bucket));
+        let mut iter = self.iterator(rocksdb::IteratorMode::From(start_key.as_bytes(), rocksdb::Direction::Forward));
+        let mut result = MarketVolume::default();
+
+        while let Some(Ok((key, value))) = iter.next() {
             if !key.starts_with(prefix.as_bytes()) {
                 break;
             }
 
-            let key = String::from_utf8(key.to_vec()).unwrap();
-            let order_id = key.rfind(':').map(|i| key[i + 1..].to_string()).unwrap();
-            let order = self.market_get_order_by_id(&order_id).unwrap();
+            let bucket = MarketVolume::decode(&value);
+            result.volume += bucket.volume;
+            result.txs += bucket.txs;
+        }
+        result
+    }
 
-            orders.push(order);
+    fn market_get_candles(&self, tick: &str, interval: &str, from: u64, to: u64, limit: u64) -> Vec<MarketCandle> {
+        let seconds = market_candle_interval(interval).unwrap();
+        let prefix = make_index_key2(KEY_MARKET_CANDLE_TICK_INTERVAL_TIME, tick, interval) + ":";
+        let start_key = make_index_key3(
+            KEY_MARKET_CANDLE_TICK_INTERVAL_TIME,
+            tick,
+            interval,
+            num_index!(from / seconds * seconds),
+        );
+        let mut iter = self.iterator(rocksdb::IteratorMode::From(start_key.as_bytes(), rocksdb::Direction::Forward));
+        let mut candles = Vec::new();
 
-            order_count += 1;
-            if order_count >= count {
+        while let Some(Ok((key, value))) = iter.next() {
+            if !key.starts_with(prefix.as_bytes()) || candles.len() as u64 >= limit {
                 break;
             }
+
+            let candle = MarketCandle::decode(&value);
+            if candle.time > to {
+                break;
+            }
+            candles.push(candle);
+        }
+        candles
+    }
+
+    fn market_get_revenue(&self, index: &str, address: &str) -> MarketRevenue {
+        let index_key_revenue = make_index_key(index, address);
+        match self.get(index_key_revenue.as_bytes()).unwrap() {
+            Some(data) => MarketRevenue::decode(&data),
+            None => MarketRevenue::default(),
         }
-        orders
     }
 }
 
 pub trait InscribeMarketTxn<'a> {
     fn market_order_save(&self, order: &MarketOrder);
-    fn market_order_set_price(&self, db: &TransactionDB, tx_hash: &str, order_id: &str, total_price: u128);
-    fn market_order_cancel(&self, db: &TransactionDB, tx_hash: &str, order_id: &str);
-    fn market_order_close(&self, db: &TransactionDB, tx_hash: &str, order_id: &str, buyer: &str);
+    fn market_order_set_price(&self, tx_hash: &str, order_id: &str, total_price: u128, expire_at: u64);
+    fn market_order_cancel(&self, tx_hash: &str, order_id: &str);
+    fn market_order_unlist(&self, order: &MarketOrder);
+    fn market_order_expire(&self, order: &mut MarketOrder);
+    fn market_orders_expire(&self, db: &TransactionDB, timestamp: u64) -> Vec<String>;
+    fn market_order_close(&self, tx_hash: &str, order_id: &str, buyer: &str, fill_amount: u64, fee: &MarketFee, timestamp: u64);
+    /// `nft` is the id and tx of the item sold into a collection bid.
+    fn market_bid_close(
+        &self,
+        tx_hash: &str,
+        order_id: &str,
+        seller: &str,
+        nft: (u64, &str),
+        fee: &MarketFee,
+        timestamp: u64,
+    ) -> Result<(), InscriptionFailReason>;
+    fn market_trade_save(&self, trade: &MarketTrade);
+    fn market_revenue_add(&self, index: &str, address: &str, trade: &MarketTrade);
+    fn market_volume_add(&self, tick: &str, timestamp: u64, total_price: u128);
+    fn market_candle_update(&self, tick: &str, timestamp: u64, unit_price: u128, total_price: u128, amount: u64);
 }
 
-impl<'a> InscribeMarketTxn<'a> for Transaction<'a, TransactionDB> {
+impl<'a> InscribeMarketTxn<'a> for DBTransaction<'a> {
     fn market_order_save(&self, order: &MarketOrder) {
-        let order_data = serde_json::to_string(order).unwrap();
+        let order_data = order.encode();
 
         let index_key_id = make_index_key(KEY_MARKET_ORDER_INDEX_ID, &order.order_id);
         let index_key_seller = make_index_key3(
@@ -81,22 +229,56 @@ impl<'a> InscribeMarketTxn<'a> for Transaction<'a, TransactionDB> {
             &order.order_id,
         );
 
-        self.put(index_key_id.as_bytes(), order_data.as_bytes()).unwrap();
+        self.put(index_key_id.as_bytes(), order_data).unwrap();
         self.put(index_key_seller.as_bytes(), "").unwrap();
         self.put(index_key_time.as_bytes(), "").unwrap();
         self.put(index_key_tick_time.as_bytes(), "").unwrap();
+
+        if order.expire_at > 0 {
+            let index_key_expire = make_index_key2(KEY_MARKET_ORDER_INDEX_EXPIRE, num_index!(order.expire_at), &order.order_id);
+            self.put(index_key_expire.as_bytes(), "").unwrap();
+        }
+
+        if let Some(index_key_bid) = market_bid_index_key(order) {
+            self.put(index_key_bid.as_bytes(), "").unwrap();
+        }
     }
 
-    fn market_order_set_price(&self, db: &TransactionDB, tx_hash: &str, order_id: &str, total_price: u128) {
-        let mut order = db.market_get_order_by_id(&order_id).unwrap();
+    fn market_order_set_price(&self, tx_hash: &str, order_id: &str, total_price: u128, expire_at: u64) {
+        // read through the transaction, the order may have been listed or filled earlier in this block
+        let index_key_id = make_index_key(KEY_MARKET_ORDER_INDEX_ID, order_id);
+        let mut order = MarketOrder::decode(&self.get(index_key_id.as_bytes()).unwrap().unwrap());
+        if expire_at > 0 {
+            if order.expire_at > 0 {
+                let index_key_expire =
+                    make_index_key2(KEY_MARKET_ORDER_INDEX_EXPIRE, num_index!(order.expire_at), &order.order_id);
+                self.delete(index_key_expire.as_bytes()).unwrap();
+            }
+
+            order.expire_at = expire_at;
+            let index_key_expire = make_index_key2(KEY_MARKET_ORDER_INDEX_EXPIRE, num_index!(order.expire_at), &order.order_id);
+            self.put(index_key_expire.as_bytes(), "").unwrap();
+        }
+        if order.order_type == MarketOrderType::Token {
+            let index_key_tick_price = make_index_key3(
+                KEY_MARKET_ORDER_INDEX_TICK_PRICE,
+                &order.tick,
+                num_index!(order.unit_price),
+                &order.order_id,
+            );
+            self.delete(index_key_tick_price.as_bytes()).unwrap();
+        }
+
         order.total_price = total_price;
-        order.unit_price = total_price / order.amount as u128;
+        // an nft order has no amount, its unit price is the total price
+        order.unit_price = match order.amount {
+            0 => total_price,
+            amount => total_price / amount as u128,
+        };
         order.tx_setprice = tx_hash.to_string();
         order.order_status = MarketOrderStatus::Open;
 
-        let order_data = serde_json::to_string(&order).unwrap();
-        let index_key_id = make_index_key(KEY_MARKET_ORDER_INDEX_ID, &order.order_id);
-        self.put(index_key_id.as_bytes(), order_data.as_bytes()).unwrap();
+        self.put(index_key_id.as_bytes(), order.encode()).unwrap();
 
         match order.order_type {
             MarketOrderType::NFT => {
@@ -113,18 +295,27 @@ impl<'a> InscribeMarketTxn<'a> for Transaction<'a, TransactionDB> {
 
                 self.put(index_key_tick_price.as_bytes(), "").unwrap();
             }
+            MarketOrderType::TokenBid | MarketOrderType::NFTBid => panic!("set price of bid order: {}", order.order_id),
         }
     }
 
-    fn market_order_cancel(&self, db: &TransactionDB, tx_hash: &str, order_id: &str) {
-        let mut order = db.market_get_order_by_id(&order_id).unwrap();
+    fn market_order_cancel(&self, tx_hash: &str, order_id: &str) {
+        // read through the transaction, the order may have been listed or filled earlier in this block
+        let index_key_id = make_index_key(KEY_MARKET_ORDER_INDEX_ID, order_id);
+        let mut order = MarketOrder::decode(&self.get(index_key_id.as_bytes()).unwrap().unwrap());
         order.tx_cancel = tx_hash.to_string();
         order.order_status = MarketOrderStatus::Canceled;
+        self.put(index_key_id.as_bytes(), order.encode()).unwrap();
 
-        let order_data = serde_json::to_string(&order).unwrap();
-        let index_key_id = make_index_key(KEY_MARKET_ORDER_INDEX_ID, &order.order_id);
-        self.put(index_key_id.as_bytes(), order_data.as_bytes()).unwrap();
+        if order.expire_at > 0 {
+            let index_key_expire = make_index_key2(KEY_MARKET_ORDER_INDEX_EXPIRE, num_index!(order.expire_at), &order.order_id);
+            self.delete(index_key_expire.as_bytes()).unwrap();
+        }
+
+        self.market_order_unlist(&order);
+    }
 
+    fn market_order_unlist(&self, order: &MarketOrder) {
         let index_key_seller = make_index_key3(
             KEY_MARKET_ORDER_INDEX_SELLER,
             &order.from,
@@ -141,7 +332,9 @@ impl<'a> InscribeMarketTxn<'a> for Transaction<'a, TransactionDB> {
         );
         self.put(index_key_seller_close_cancel.as_bytes(), "").unwrap();
 
-        if !order.tx_setprice.is_empty() {
+        if let Some(index_key_bid) = market_bid_index_key(order) {
+            self.delete(index_key_bid.as_bytes()).unwrap();
+        } else if !order.tx_setprice.is_empty() {
             match order.order_type {
                 MarketOrderType::NFT => {
                     let index_key_nft =
@@ -158,19 +351,116 @@ impl<'a> InscribeMarketTxn<'a> for Transaction<'a, TransactionDB> {
 
                     self.delete(index_key_tick_price.as_bytes()).unwrap();
                 }
+                MarketOrderType::TokenBid | MarketOrderType::NFTBid => {}
             }
         }
     }
 
-    fn market_order_close(&self, db: &TransactionDB, tx_hash: &str, order_id: &str, buyer: &str) {
-        let mut order = db.market_get_order_by_id(&order_id).unwrap();
-        order.tx_close = tx_hash.to_string();
+    fn market_order_expire(&self, order: &mut MarketOrder) {
+        order.order_status = MarketOrderStatus::Expired;
+
+        let order_data = order.encode();
+        let index_key_id = make_index_key(KEY_MARKET_ORDER_INDEX_ID, &order.order_id);
+        self.put(index_key_id.as_bytes(), order_data).unwrap();
+
+        self.market_order_unlist(order);
+    }
+
+    /// Returns the ids of the orders which expired.
+    fn market_orders_expire(&self, db: &TransactionDB, timestamp: u64) -> Vec<String> {
+        let mut order_ids = Vec::new();
+        let prefix = KEY_MARKET_ORDER_INDEX_EXPIRE.to_string() + ":";
+        let mut iter = DBBase::iterator(
+            db,
+            rocksdb::IteratorMode::From(prefix.as_bytes(), rocksdb::Direction::Forward),
+        );
+
+        while let Some(Ok((key, _))) = iter.next() {
+            if !key.starts_with(prefix.as_bytes()) {
+                break;
+            }
+
+            let key = String::from_utf8(key.to_vec()).unwrap();
+            let mut parts = key[prefix.len()..].splitn(2, ':');
+            let expire_at = parts.next().unwrap().parse::<u64>().unwrap();
+            if expire_at > timestamp {
+                break;
+            }
+            let order_id = parts.next().unwrap();
+
+            self.delete(key.as_bytes()).unwrap();
+
+            let index_key_id = make_index_key(KEY_MARKET_ORDER_INDEX_ID, order_id);
+            let mut order = MarketOrder::decode(&self.get(index_key_id.as_bytes()).unwrap().unwrap());
+            let listed = order.order_status == MarketOrderStatus::Init || order.order_status == MarketOrderStatus::Open;
+            // the deadline may have been moved by a set price in this block
+            if listed && order.expire_at <= timestamp {
+                self.market_order_expire(&mut order);
+                order_ids.push(order.order_id);
+            }
+        }
+        order_ids
+    }
+
+    fn market_order_close(
+        &self,
+        tx_hash: &str,
+        order_id: &str,
+        buyer: &str,
+        fill_amount: u64,
+        fee: &MarketFee,
+        timestamp: u64,
+    ) {
+        // read through the transaction, the order may have been filled earlier in this block
+        let index_key_id = make_index_key(KEY_MARKET_ORDER_INDEX_ID, order_id);
+        let mut order = MarketOrder::decode(&self.get(index_key_id.as_bytes()).unwrap().unwrap());
+        let fill_price = order.fill_price(fill_amount);
+        let seller_proceeds = fill_price.saturating_sub(fee.fee + fee.royalty);
+
+        self.market_trade_save(&MarketTrade {
+            tx: tx_hash.to_string(),
+            order_id: order.order_id.clone(),
+            order_type: order.order_type,
+            tick: order.tick.clone(),
+            nft_id: order.nft_id,
+            seller: order.from.clone(),
+            buyer: buyer.to_string(),
+            amount: fill_amount,
+            total_price: fill_price,
+            unit_price: order.unit_price,
+            timestamp,
+            market: fee.market.clone(),
+            creator: fee.creator.clone(),
+            fee: fee.fee,
+            royalty: fee.royalty,
+            seller_proceeds,
+        });
+
         order.buyer = buyer.to_string();
+        order.fee += fee.fee;
+        order.royalty += fee.royalty;
+        order.seller_proceeds += seller_proceeds;
+        if order.order_type == MarketOrderType::Token {
+            order.filled_amount += fill_amount;
+
+            self.market_volume_add(&order.tick, timestamp, fill_price);
+            self.market_candle_update(&order.tick, timestamp, order.unit_price, fill_price, fill_amount);
+
+            if order.filled_amount < order.amount {
+                // partially filled, stays listed at its unit price
+                self.put(index_key_id.as_bytes(), order.encode()).unwrap();
+                return;
+            }
+        }
+
+        order.tx_close = tx_hash.to_string();
         order.order_status = MarketOrderStatus::Close;
+        self.put(index_key_id.as_bytes(), order.encode()).unwrap();
 
-        let order_data = serde_json::to_string(&order).unwrap();
-        let index_key_id = make_index_key(KEY_MARKET_ORDER_INDEX_ID, &order.order_id);
-        self.put(index_key_id.as_bytes(), order_data.as_bytes()).unwrap();
+        if order.expire_at > 0 {
+            let index_key_expire = make_index_key2(KEY_MARKET_ORDER_INDEX_EXPIRE, num_index!(order.expire_at), &order.order_id);
+            self.delete(index_key_expire.as_bytes()).unwrap();
+        }
 
         let index_key_seller = make_index_key3(
             KEY_MARKET_ORDER_INDEX_SELLER,
@@ -210,6 +500,277 @@ impl<'a> InscribeMarketTxn<'a> for Transaction<'a, TransactionDB> {
                 );
                 self.put(index_key_close_tick_time.as_bytes(), "").unwrap();
             }
+            MarketOrderType::TokenBid | MarketOrderType::NFTBid => panic!("close bid order as listing: {}", order.order_id),
+        }
+    }
+
+    /// Fails when the bid is missing or no longer open, it may have been accepted earlier in this block.
+    fn market_bid_close(
+        &self,
+        tx_hash: &str,
+        order_id: &str,
+        seller: &str,
+        (nft_id, nft_tx): (u64, &str),
+        fee: &MarketFee,
+        timestamp: u64,
+    ) -> Result<(), InscriptionFailReason> {
+        // read through the transaction, the bid may have been offered or accepted earlier in this block
+        let index_key_id = make_index_key(KEY_MARKET_ORDER_INDEX_ID, order_id);
+        let mut order = match self.get(index_key_id.as_bytes()).unwrap() {
+            Some(data) => MarketOrder::decode(&data),
+            None => return Err(InscriptionFailReason::MarketOrderNotFound),
+        };
+        if order.order_status != MarketOrderStatus::Open {
+            return Err(InscriptionFailReason::MarketOrderNotOpen);
+        }
+
+        order.tx_close = tx_hash.to_string();
+        order.seller = seller.to_string();
+        order.order_status = MarketOrderStatus::Close;
+        order.fee = fee.fee;
+        order.royalty = fee.royalty;
+        order.seller_proceeds = order.total_price.saturating_sub(fee.fee + fee.royalty);
+
+        if order.expire_at > 0 {
+            let index_key_expire = make_index_key2(KEY_MARKET_ORDER_INDEX_EXPIRE, num_index!(order.expire_at), &order.order_id);
+            self.delete(index_key_expire.as_bytes()).unwrap();
+        }
+
+        // the best bid key of a collection bid does not depend on the item sold into it
+        self.market_order_unlist(&order);
+
+        if !order.collection.is_empty() {
+            order.nft_id = nft_id;
+            order.nft_tx = nft_tx.to_string();
+        }
+
+        self.put(index_key_id.as_bytes(), order.encode()).unwrap();
+
+        self.market_trade_save(&MarketTrade {
+            tx: tx_hash.to_string(),
+            order_id: order.order_id.clone(),
+            order_type: order.order_type,
+            tick: order.tick.clone(),
+            nft_id: order.nft_id,
+            seller: order.seller.clone(),
+            buyer: order.from.clone(),
+            amount: order.amount,
+            total_price: order.total_price,
+            unit_price: order.unit_price,
+            timestamp,
+            market: fee.market.clone(),
+            creator: fee.creator.clone(),
+            fee: order.fee,
+            royalty: order.royalty,
+            seller_proceeds: order.seller_proceeds,
+        });
+
+        if order.order_type == MarketOrderType::TokenBid {
+            let index_key_close_tick_time = make_index_key3(
+                KEY_MARKET_ORDER_INDEX_CLOSE_TICK_TIME,
+                &order.tick,
+                num_index_desc!(order.timestamp),
+                &order.order_id,
+            );
+            self.put(index_key_close_tick_time.as_bytes(), "").unwrap();
+
+            self.market_volume_add(&order.tick, timestamp, order.total_price);
+            self.market_candle_update(&order.tick, timestamp, order.unit_price, order.total_price, order.amount);
+        }
+        Ok(())
+    }
+
+    fn market_trade_save(&self, trade: &MarketTrade) {
+        let index_key_id = make_index_key2(KEY_MARKET_TRADE_INDEX_ID, &trade.tx, &trade.order_id);
+        self.put(index_key_id.as_bytes(), trade.encode()).unwrap();
+
+        if !trade.tick.is_empty() {
+            let index_key_tick_time = make_index_key3(
+                KEY_MARKET_TRADE_INDEX_TICK_TIME,
+                &trade.tick,
+                num_index_desc!(trade.timestamp),
+                make_index_key(&trade.tx, &trade.order_id),
+            );
+            self.put(index_key_tick_time.as_bytes(), "").unwrap();
+        }
+
+        let trade_id = make_index_key(&trade.tx, &trade.order_id);
+        let index_key_buyer = make_index_key3(
+            KEY_MARKET_TRADE_INDEX_BUYER,
+            &trade.buyer,
+            num_index_desc!(trade.timestamp),
+            &trade_id,
+        );
+        let index_key_seller = make_index_key3(
+            KEY_MARKET_TRADE_INDEX_SELLER,
+            &trade.seller,
+            num_index_desc!(trade.timestamp),
+            &trade_id,
+        );
+        self.put(index_key_buyer.as_bytes(), "").unwrap();
+        self.put(index_key_seller.as_bytes(), "").unwrap();
+
+        if !trade.market.is_empty() {
+            self.market_revenue_add(KEY_MARKET_REVENUE_MARKET, &trade.market, trade);
         }
+        if !trade.creator.is_empty() {
+            self.market_revenue_add(KEY_MARKET_REVENUE_CREATOR, &trade.creator, trade);
+        }
+    }
+
+    fn market_revenue_add(&self, index: &str, address: &str, trade: &MarketTrade) {
+        let index_key_revenue = make_index_key(index, address);
+
+        let mut revenue = match self.get(index_key_revenue.as_bytes()).unwrap() {
+            Some(data) => MarketRevenue::decode(&data),
+            None => MarketRevenue::default(),
+        };
+        revenue.volume += trade.total_price;
+        revenue.fee += trade.fee;
+        revenue.royalty += trade.royalty;
+        revenue.seller_proceeds += trade.seller_proceeds;
+        revenue.txs += 1;
+
+        self.put(index_key_revenue.as_bytes(), revenue.encode()).unwrap();
+    }
+
+    fn market_volume_add(&self, tick: &str, timestamp: u64, total_price: u128) {
+        let index_key_volume = make_index_key2(
+            KEY_MARKET_VOLUME_TICK_HOUR,
+            tick,
+            num_index!(timestamp / MARKET_VOLUME_BUCKET),
+        );
+
+        let mut volume = match self.get(index_key_volume.as_bytes()).unwrap() {
+            Some(data) => MarketVolume::decode(&data),
+            None => MarketVolume::default(),
+        };
+        volume.volume += total_price;
+        volume.txs += 1;
+
+        self.put(index_key_volume.as_bytes(), volume.encode()).unwrap();
+    }
+
+    fn market_candle_update(&self, tick: &str, timestamp: u64, unit_price: u128, total_price: u128, amount: u64) {
+        for (interval, seconds) in MARKET_CANDLE_INTERVALS {
+            let time = timestamp / seconds * seconds;
+            let index_key_candle = make_index_key3(KEY_MARKET_CANDLE_TICK_INTERVAL_TIME, tick, interval, num_index!(time));
+
+            let candle = match self.get(index_key_candle.as_bytes()).unwrap() {
+                Some(data) => {
+                    let candle = MarketCandle::decode(&data);
+                    MarketCandle {
+                        high: std::cmp::max(candle.high, unit_price),
+                        low: std::cmp::min(candle.low, unit_price),
+                        close: unit_price,
+                        volume: candle.volume + total_price,
+                        amount: candle.amount + amount,
+                        txs: candle.txs + 1,
+                        ..candle
+                    }
+                }
+                None => MarketCandle {
+                    time,
+                    open: unit_price,
+                    high: unit_price,
+                    low: unit_price,
+                    close: unit_price,
+                    volume: total_price,
+                    amount,
+                    txs: 1,
+                },
+            };
+
+            self.put(index_key_candle.as_bytes(), candle.encode()).unwrap();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{inscription::marketplace::types::fixtures, txn_db::db::open_test_db};
+
+    fn order(order_id: &str, expire_at: u64, order_status: MarketOrderStatus) -> MarketOrder {
+        MarketOrder {
+            expire_at,
+            order_status,
+            ..fixtures::order(order_id)
+        }
+    }
+
+    #[test]
+    fn expire_returns_the_expired_orders() {
+        let db = open_test_db("market-expire");
+        let txn = DBTransaction::new(&db);
+        txn.market_order_save(&order("0x01", 100, MarketOrderStatus::Open));
+        txn.market_order_save(&order("0x02", 200, MarketOrderStatus::Open));
+        txn.market_order_save(&order("0x03", 120, MarketOrderStatus::Init));
+        txn.market_order_save(&order("0x04", 0, MarketOrderStatus::Open));
+        txn.commit().unwrap();
+
+        let txn = DBTransaction::new(&db);
+        // moved past the block timestamp by a set price earlier in the block
+        txn.market_order_set_price("0xsetprice", "0x03", 2000, 300);
+        assert_eq!(txn.market_orders_expire(&db, 150), vec!["0x01".to_string()]);
+        txn.commit().unwrap();
+
+        let status = |order_id: &str| db.market_get_order_by_id(order_id).unwrap().order_status;
+        assert_eq!(status("0x01"), MarketOrderStatus::Expired);
+        assert_eq!(status("0x02"), MarketOrderStatus::Open);
+        assert_eq!(status("0x04"), MarketOrderStatus::Open);
+
+        let txn = DBTransaction::new(&db);
+        assert_eq!(
+            txn.market_orders_expire(&db, 1000),
+            vec!["0x02".to_string(), "0x03".to_string()]
+        );
+        txn.commit().unwrap();
+    }
+
+    #[test]
+    fn set_price_of_an_nft_order() {
+        let db = open_test_db("market-set-price-nft");
+        let txn = DBTransaction::new(&db);
+        let mut nft = order("0x01", 0, MarketOrderStatus::Init);
+        nft.order_type = MarketOrderType::NFT;
+        nft.amount = 0;
+        txn.market_order_save(&nft);
+        txn.market_order_set_price("0xsetprice", "0x01", 2000, 0);
+        txn.commit().unwrap();
+
+        let nft = db.market_get_order_by_id("0x01").unwrap();
+        assert_eq!(nft.order_status, MarketOrderStatus::Open);
+        assert_eq!(nft.total_price, 2000);
+        assert_eq!(nft.unit_price, 2000);
+    }
+
+    #[test]
+    fn bid_closes_once_within_the_block() {
+        let db = open_test_db("market-bid-close");
+        let txn = DBTransaction::new(&db);
+        let mut bid = order("0x01", 0, MarketOrderStatus::Open);
+        bid.order_type = MarketOrderType::TokenBid;
+        // offered and accepted in the same block
+        txn.market_order_save(&bid);
+
+        let fee = MarketFee::default();
+        assert_eq!(
+            txn.market_bid_close("0xaccept1", "0x01", "0xbidseller", (0, ""), &fee, 20),
+            Ok(())
+        );
+        assert_eq!(
+            txn.market_bid_close("0xaccept2", "0x01", "0xbidseller", (0, ""), &fee, 20),
+            Err(InscriptionFailReason::MarketOrderNotOpen)
+        );
+        assert_eq!(
+            txn.market_bid_close("0xaccept3", "0x02", "0xbidseller", (0, ""), &fee, 20),
+            Err(InscriptionFailReason::MarketOrderNotFound)
+        );
+        txn.commit().unwrap();
+
+        assert_eq!(db.market_get_order_by_id("0x01").unwrap().tx_close, "0xaccept1");
+        assert_eq!(db.market_get_latest_trades("test", 10).len(), 1);
+        assert_eq!(db.market_get_volume("test", 0).txs, 1);
     }
 }
@@ -1,102 +1,205 @@
 use super::{
     db::{InscribeMarketDB, InscribeMarketTxn},
-    market_abi::MARKET_ABI_JSON,
-    types::{MarketOrder, MarketOrderStatus, MarketOrderType},
+    market_config::{market_contract_of_log, MarketContract, MarketEvent},
+    types::{MarketFee, MarketOrder, MarketOrderStatus, MarketOrderType},
     MarketPlace, APP_OPER_TOKEN_MARKET_LIST,
 };
 use crate::{
     config::MARKET_ADDRESS_LIST,
-    ethereum::{Web3ABILogEvent, Web3LogEvent},
     inscription::{
         db::{make_index_key, InscribeDB},
-        inscribe_token::ProcessBlockContextJsonToken,
-        marketplace::db::KEY_MARKET_ORDER_INDEX_TICK_PRICE,
+        inscribe_collection::ProcessBlockContextJsonCollection,
+        inscribe_token::{ProcessBlockContextJsonToken, TOKEN_BALANCE_MAX},
+        marketplace::db::{KEY_MARKET_ORDER_INDEX_ID, KEY_MARKET_ORDER_INDEX_TICK_PRICE},
         trait_json_value::JsonValueTrait,
-        types::{InscribeContext, Inscription, InscriptionMimeCategory, InscriptionToken, NFTTransfer, TRANSFER_TX_HEX_LENGTH},
+        types::{
+            InscribeContext, Inscription, InscriptionFailReason, InscriptionMimeCategory, InscriptionToken, NFTTransfer,
+            TRANSFER_TX_HEX_LENGTH,
+        },
     },
+    txn_db::{codec::StorageCodec, transaction::DBTransaction, DBBase},
 };
 use log::{info, warn};
-use rocksdb::{Transaction, TransactionDB};
-
-lazy_static! {
-    pub static ref CONTRACT_MARKET: web3::ethabi::Contract = web3::ethabi::Contract::load(MARKET_ABI_JSON.as_bytes()).unwrap();
-}
+use rocksdb::TransactionDB;
+use std::collections::{HashMap, HashSet};
 
 impl MarketPlace for InscribeContext {
     fn process_inscribe_invoke(&mut self, insc: &mut Inscription) -> bool {
-        assert!(insc.event_logs.len() == 1);
-
-        if let Some(log) = insc.event_logs[0].match_event(&CONTRACT_MARKET, "MarketBuy") {
-            self.execute_market_buy(insc, &log)
-        } else if let Some(log) = insc.event_logs[0].match_event(&CONTRACT_MARKET, "MarketCancel") {
-            self.execute_market_cancel(insc, &log)
-        } else if let Some(log) = insc.event_logs[0].match_event(&CONTRACT_MARKET, "MarketSetPrice") {
-            self.execute_market_set_price(insc, &log)
-        } else {
-            false
+        let event_logs = insc.event_logs.clone();
+        let snapshot = MarketSnapshot::take(self);
+        // the order id derived from the calldata only applies to listings, invokes take the first settled order
+        insc.market_order_id = None;
+        let mut matched = false;
+
+        for event_log in &event_logs {
+            let market = match market_contract_of_log(event_log) {
+                Some(market) => market,
+                None => continue,
+            };
+
+            let result = if let Some(log) = market.match_event(event_log, MarketEvent::Buy) {
+                self.execute_market_buy(insc, market, &log)
+            } else if let Some(log) = market.match_event(event_log, MarketEvent::Cancel) {
+                self.execute_market_cancel(insc, market, &log)
+            } else if let Some(log) = market.match_event(event_log, MarketEvent::SetPrice) {
+                self.execute_market_set_price(insc, market, &log)
+            } else if let Some(log) = market.match_event(event_log, MarketEvent::Offer) {
+                self.execute_market_offer(insc, market, &log)
+            } else if let Some(log) = market.match_event(event_log, MarketEvent::AcceptOffer) {
+                self.execute_market_accept_offer(insc, market, &log)
+            } else {
+                continue;
+            };
+
+            // the market events of a tx settle together or not at all
+            if !result {
+                snapshot.restore(self);
+                insc.market_order_id = None;
+                return false;
+            }
+            matched = true;
+        }
+
+        if !matched {
+            warn!("[indexer] market invoke without market event: {}", insc.tx_hash);
+            return insc.fail(InscriptionFailReason::MarketEventNotFound);
         }
+        true
     }
 
-    fn execute_market_buy(&mut self, insc: &mut Inscription, log: &web3::ethabi::Log) -> bool {
-        let order_id = "0x".to_string() + &log.get_param("orderId").unwrap().to_string();
+    fn execute_market_buy(&mut self, insc: &mut Inscription, market: &MarketContract, log: &web3::ethabi::Log) -> bool {
+        let order_id = market.order_id(log);
         let order = match self.db.read().unwrap().market_get_order_by_id(&order_id) {
             Some(order) => order,
             None => {
                 warn!("[indexer] market_buy order not found: {} {}", insc.tx_hash, order_id);
-                return false;
+                return insc.fail(InscriptionFailReason::MarketOrderNotFound);
             }
         };
 
+        if order.order_status == MarketOrderStatus::Expired || (order.expire_at > 0 && order.expire_at <= insc.timestamp) {
+            warn!("[indexer] market_buy order expired: {} {}", insc.tx_hash, order_id);
+            return insc.fail(InscriptionFailReason::MarketOrderExpired);
+        }
+
+        // unpriced, closed and canceled orders cannot be bought
+        if order.order_status != MarketOrderStatus::Open {
+            warn!("[indexer] market_buy order not open: {} {}", insc.tx_hash, order_id);
+            return insc.fail(InscriptionFailReason::MarketOrderNotOpen);
+        }
+
+        if market.fee(log).saturating_add(market.royalty(log)) > market.price(log) {
+            warn!("[indexer] market_buy fee exceeds price: {} {}", insc.tx_hash, order_id);
+            return insc.fail(InscriptionFailReason::MarketFeeExceedsPrice);
+        }
+
+        if market_fee(&self.db.read().unwrap(), market, log, order.nft_id).is_none() {
+            warn!("[indexer] market_buy collection not found: {} {}", insc.tx_hash, order_id);
+            return insc.fail(InscriptionFailReason::MarketCollectionNotFound);
+        }
+
         let result = match order.order_type {
-            MarketOrderType::Token => self.execute_market_buy_token(insc, &order, log),
-            MarketOrderType::NFT => self.execute_market_buy_nft(insc, &order, log),
+            MarketOrderType::Token => self.execute_market_buy_token(insc, market, &order, log),
+            MarketOrderType::NFT => self.execute_market_buy_nft(insc, market, &order, log),
+            MarketOrderType::TokenBid | MarketOrderType::NFTBid => {
+                warn!("[indexer] market_buy order is a bid: {} {}", insc.tx_hash, order_id);
+                insc.fail(InscriptionFailReason::MarketOrderIsBid)
+            }
         };
 
-        if result {
+        if result && insc.market_order_id.is_none() {
             insc.market_order_id = Some(order_id.clone());
         }
 
         result
     }
 
-    fn execute_market_buy_token(&mut self, insc: &Inscription, order: &MarketOrder, log: &web3::ethabi::Log) -> bool {
-        let total_price = log.get_param("price").unwrap().clone().into_uint().unwrap().as_u128();
-        if order.total_price != total_price {
+    fn execute_market_buy_token(
+        &mut self,
+        insc: &mut Inscription,
+        market: &MarketContract,
+        order: &MarketOrder,
+        log: &web3::ethabi::Log,
+    ) -> bool {
+        let pending_amount = self.market_order_fills.get(&order.order_id).cloned().unwrap_or(0);
+        let remaining_amount = order.amount - order.filled_amount - pending_amount;
+
+        let fill_amount = match market.amount(log) {
+            Some(amount) => amount.try_into().unwrap_or(u64::MAX),
+            None => remaining_amount,
+        };
+        if fill_amount == 0 || fill_amount > remaining_amount {
+            warn!(
+                "[indexer] market_buy amount invalid: {} {} {} {}",
+                insc.tx_hash, order.order_id, remaining_amount, fill_amount
+            );
+            return insc.fail(InscriptionFailReason::MarketAmountInvalid);
+        }
+
+        let mut pending_order = order.clone();
+        pending_order.filled_amount += pending_amount;
+        let fill_price = pending_order.fill_price(fill_amount);
+        let total_price: Result<u128, _> = market.price(log).try_into();
+        if total_price != Ok(fill_price) {
             warn!(
                 "[indexer] market_buy price not match: {} {} {} {}",
-                insc.tx_hash, order.order_id, order.total_price, total_price
+                insc.tx_hash,
+                order.order_id,
+                fill_price,
+                market.price(log)
             );
-            return false;
+            return insc.fail(InscriptionFailReason::MarketPriceMismatch);
         }
 
         let tick = &order.tick;
-        let transfer_amount = order.amount;
         let market_balance = self.get_token_balance(tick, &insc.to);
+        if fill_amount > market_balance {
+            warn!(
+                "[indexer] market_buy market balance insufficient: {} {} {} {}",
+                insc.tx_hash, order.order_id, fill_amount, market_balance
+            );
+            return insc.fail(InscriptionFailReason::MarketBalanceInsufficient);
+        }
 
-        assert!(transfer_amount <= market_balance);
-
-        self.token_balance_change_update(tick, &insc.from, transfer_amount as i64);
-        self.token_balance_change_update(tick, &insc.to, -(transfer_amount as i64));
+        self.token_balance_change_update(tick, &insc.from, fill_amount as i64);
+        self.token_balance_change_update(tick, &insc.to, -(fill_amount as i64));
+        self.market_order_fills
+            .insert(order.order_id.clone(), pending_amount + fill_amount);
 
         info!(
             "[indexer] market_buy_token: {} {} {} {} {}",
-            insc.tx_hash, order.order_id, insc.from, order.tick, order.amount
+            insc.tx_hash, order.order_id, insc.from, order.tick, fill_amount
         );
         true
     }
 
-    fn execute_market_buy_nft(&mut self, insc: &Inscription, order: &MarketOrder, log: &web3::ethabi::Log) -> bool {
-        let total_price = log.get_param("price").unwrap().clone().into_uint().unwrap().as_u128();
-        if order.total_price != total_price {
+    fn execute_market_buy_nft(
+        &mut self,
+        insc: &mut Inscription,
+        market: &MarketContract,
+        order: &MarketOrder,
+        log: &web3::ethabi::Log,
+    ) -> bool {
+        let total_price: Result<u128, _> = market.price(log).try_into();
+        if total_price != Ok(order.total_price) {
             warn!(
                 "[indexer] market_buy price not match: {} {} {} {}",
-                insc.tx_hash, order.order_id, order.total_price, total_price
+                insc.tx_hash,
+                order.order_id,
+                order.total_price,
+                market.price(log)
             );
-            return false;
+            return insc.fail(InscriptionFailReason::MarketPriceMismatch);
         }
 
         let holder = self.get_nft_holder(order.nft_id);
-        assert!(MARKET_ADDRESS_LIST.contains(&holder));
+        if !MARKET_ADDRESS_LIST.contains(&holder) {
+            warn!(
+                "[indexer] market_buy nft not held by market: {} {} {} {}",
+                insc.tx_hash, order.order_id, order.nft_id, holder
+            );
+            return insc.fail(InscriptionFailReason::MarketNftNotHeld);
+        }
 
         match self.nft_holders.get_mut(&order.nft_id) {
             Some(holder) => *holder = insc.from.clone(),
@@ -117,48 +220,76 @@ impl MarketPlace for InscribeContext {
         true
     }
 
-    fn execute_market_cancel(&mut self, insc: &mut Inscription, log: &web3::ethabi::Log) -> bool {
-        let order_id = "0x".to_string() + &log.get_param("orderId").unwrap().to_string();
+    fn execute_market_cancel(&mut self, insc: &mut Inscription, market: &MarketContract, log: &web3::ethabi::Log) -> bool {
+        let order_id = market.order_id(log);
         let order = match self.db.read().unwrap().market_get_order_by_id(&order_id) {
             Some(order) => order,
             None => {
                 warn!("[indexer] market_cancel order not found: {} {}", insc.tx_hash, order_id);
-                return false;
+                return insc.fail(InscriptionFailReason::MarketOrderNotFound);
             }
         };
 
+        if order.order_status == MarketOrderStatus::Close || order.order_status == MarketOrderStatus::Canceled {
+            warn!("[indexer] market_cancel order not open: {} {}", insc.tx_hash, order_id);
+            return insc.fail(InscriptionFailReason::MarketOrderNotOpen);
+        }
+
         let result = match order.order_type {
             MarketOrderType::Token => self.execute_market_cancel_token(insc, &order),
             MarketOrderType::NFT => self.execute_market_cancel_nft(insc, &order),
+            // the bid price is escrowed by the market contract, no inscription to return
+            MarketOrderType::TokenBid | MarketOrderType::NFTBid => {
+                if insc.from != order.from {
+                    warn!(
+                        "[indexer] market_cancel bid not owned: {} {} {}",
+                        insc.tx_hash, order_id, insc.from
+                    );
+                    return insc.fail(InscriptionFailReason::MarketOrderNotOwner);
+                }
+                true
+            }
         };
 
-        if result {
+        if result && insc.market_order_id.is_none() {
             insc.market_order_id = Some(order.order_id.clone());
         }
 
         result
     }
 
-    fn execute_market_cancel_token(&mut self, insc: &Inscription, order: &MarketOrder) -> bool {
+    fn execute_market_cancel_token(&mut self, insc: &mut Inscription, order: &MarketOrder) -> bool {
         let tick = &order.tick;
-        let transfer_amount = order.amount;
+        let pending_amount = self.market_order_fills.get(&order.order_id).cloned().unwrap_or(0);
+        let transfer_amount = order.amount - order.filled_amount - pending_amount;
         let market_balance = self.get_token_balance(tick, &insc.to);
-
-        assert!(transfer_amount <= market_balance);
+        if transfer_amount > market_balance {
+            warn!(
+                "[indexer] market_cancel market balance insufficient: {} {} {} {}",
+                insc.tx_hash, order.order_id, transfer_amount, market_balance
+            );
+            return insc.fail(InscriptionFailReason::MarketBalanceInsufficient);
+        }
 
         self.token_balance_change_update(tick, &insc.from, transfer_amount as i64);
         self.token_balance_change_update(tick, &insc.to, -(transfer_amount as i64));
 
         info!(
             "[indexer] market_cancel_token: {} {} {} {} {}",
-            insc.tx_hash, order.order_id, insc.from, order.tick, order.amount
+            insc.tx_hash, order.order_id, insc.from, order.tick, transfer_amount
         );
         true
     }
 
-    fn execute_market_cancel_nft(&mut self, insc: &Inscription, order: &MarketOrder) -> bool {
+    fn execute_market_cancel_nft(&mut self, insc: &mut Inscription, order: &MarketOrder) -> bool {
         let holder = self.get_nft_holder(order.nft_id);
-        assert!(MARKET_ADDRESS_LIST.contains(&holder));
+        if !MARKET_ADDRESS_LIST.contains(&holder) {
+            warn!(
+                "[indexer] market_cancel nft not held by market: {} {} {} {}",
+                insc.tx_hash, order.order_id, order.nft_id, holder
+            );
+            return insc.fail(InscriptionFailReason::MarketNftNotHeld);
+        }
 
         match self.nft_holders.get_mut(&order.nft_id) {
             Some(holder) => *holder = insc.from.clone(),
@@ -179,51 +310,296 @@ impl MarketPlace for InscribeContext {
         true
     }
 
-    fn execute_market_set_price(&mut self, insc: &mut Inscription, log: &web3::ethabi::Log) -> bool {
-        let order_id = "0x".to_string() + &log.get_param("orderId").unwrap().to_string();
-        if self.db.read().unwrap().market_get_order_by_id(&order_id).is_none() {
-            warn!("[indexer] market_set_price order not found: {} {}", insc.tx_hash, order_id);
-            return false;
+    fn execute_market_set_price(&mut self, insc: &mut Inscription, market: &MarketContract, log: &web3::ethabi::Log) -> bool {
+        let order_id = market.order_id(log);
+        let order = match self.db.read().unwrap().market_get_order_by_id(&order_id) {
+            Some(order) => order,
+            None => {
+                warn!("[indexer] market_set_price order not found: {} {}", insc.tx_hash, order_id);
+                return insc.fail(InscriptionFailReason::MarketOrderNotFound);
+            }
+        };
+
+        if order.order_status == MarketOrderStatus::Expired {
+            warn!("[indexer] market_set_price order expired: {} {}", insc.tx_hash, order_id);
+            return insc.fail(InscriptionFailReason::MarketOrderExpired);
+        }
+
+        if order.order_status == MarketOrderStatus::Close || order.order_status == MarketOrderStatus::Canceled {
+            warn!("[indexer] market_set_price order not open: {} {}", insc.tx_hash, order_id);
+            return insc.fail(InscriptionFailReason::MarketOrderNotOpen);
         }
 
-        let total_price_u256 = log.get_param("price").unwrap().clone().into_uint().unwrap();
+        if order.order_type == MarketOrderType::TokenBid || order.order_type == MarketOrderType::NFTBid {
+            warn!("[indexer] market_set_price order is a bid: {} {}", insc.tx_hash, order_id);
+            return insc.fail(InscriptionFailReason::MarketOrderIsBid);
+        }
+
+        let total_price_u256 = market.price(log);
         let total_price: Result<u128, _> = total_price_u256.try_into();
         match total_price {
             Ok(_) => {
-                insc.market_order_id = Some(order_id.clone());
+                if insc.market_order_id.is_none() {
+                    insc.market_order_id = Some(order_id.clone());
+                }
                 info!("[indexer] market_set_price: {} {}", insc.tx_hash, order_id);
                 true
             }
             Err(_) => {
                 warn!("[indexer] market_set_price price overflow: {} {}", insc.tx_hash, order_id);
-                false
+                insc.fail(InscriptionFailReason::MarketPriceInvalid)
             }
         }
     }
 
-    fn save_market(&self, db: &TransactionDB, txn: &Transaction<TransactionDB>, insc: &Inscription) {
+    fn execute_market_offer(&mut self, insc: &mut Inscription, market: &MarketContract, log: &web3::ethabi::Log) -> bool {
+        let order_id = market.order_id(log);
+        if self.db.read().unwrap().market_get_order_by_id(&order_id).is_some() {
+            warn!("[indexer] market_offer order exists: {} {}", insc.tx_hash, order_id);
+            return insc.fail(InscriptionFailReason::MarketOrderExists);
+        }
+
+        let total_price: Result<u128, _> = market.price(log).try_into();
+        if !matches!(total_price, Ok(total_price) if total_price > 0) {
+            warn!("[indexer] market_offer invalid price: {} {}", insc.tx_hash, order_id);
+            return insc.fail(InscriptionFailReason::MarketPriceInvalid);
+        }
+
+        if let Some(tick) = market.tick(log) {
+            if !self.token_cache.contains_key(&tick) {
+                warn!(
+                    "[indexer] market_offer token not found: {} {} {}",
+                    insc.tx_hash, order_id, tick
+                );
+                return insc.fail(InscriptionFailReason::MarketTokenNotFound);
+            }
+
+            let amount: Result<u64, _> = market.amount(log).unwrap_or_default().try_into();
+            if !matches!(amount, Ok(amount) if amount > 0 && amount <= TOKEN_BALANCE_MAX) {
+                warn!("[indexer] market_offer invalid amount: {} {}", insc.tx_hash, order_id);
+                return insc.fail(InscriptionFailReason::MarketAmountInvalid);
+            }
+        } else if let Some(nft_tx) = market.nft_tx(log) {
+            let db = self.db.read().unwrap();
+            let nft_id = db.get_inscription_id_by_tx(&nft_tx);
+            if nft_id == 0 || db.get_inscription_nft_holder_by_id(nft_id).is_none() {
+                warn!(
+                    "[indexer] market_offer nft not found: {} {} {}",
+                    insc.tx_hash, order_id, nft_tx
+                );
+                return insc.fail(InscriptionFailReason::MarketNftNotFound);
+            }
+        } else if let Some(collection) = market.collection(log) {
+            if !self.db.read().unwrap().inscription_nft_collection_exists(&collection) {
+                warn!(
+                    "[indexer] market_offer collection not found: {} {} {}",
+                    insc.tx_hash, order_id, collection
+                );
+                return insc.fail(InscriptionFailReason::MarketCollectionNotFound);
+            }
+        } else {
+            warn!(
+                "[indexer] market_offer no tick, nft or collection: {} {}",
+                insc.tx_hash, order_id
+            );
+            return insc.fail(InscriptionFailReason::MarketOfferTargetMissing);
+        }
+
+        if insc.market_order_id.is_none() {
+            insc.market_order_id = Some(order_id.clone());
+        }
+        info!("[indexer] market_offer: {} {} {}", insc.tx_hash, order_id, insc.from);
+        true
+    }
+
+    fn execute_market_accept_offer(
+        &mut self,
+        insc: &mut Inscription,
+        market: &MarketContract,
+        log: &web3::ethabi::Log,
+    ) -> bool {
+        let order_id = market.order_id(log);
+        let order = match self.db.read().unwrap().market_get_order_by_id(&order_id) {
+            Some(order) => order,
+            None => {
+                warn!("[indexer] market_accept_offer order not found: {} {}", insc.tx_hash, order_id);
+                return insc.fail(InscriptionFailReason::MarketOrderNotFound);
+            }
+        };
+
+        // a bid closes with its first accept, a second one in the same block finds it still open in the db
+        if order.order_status != MarketOrderStatus::Open
+            || (order.expire_at > 0 && order.expire_at <= insc.timestamp)
+            || self.market_bid_accepts.contains(&order_id)
+        {
+            warn!("[indexer] market_accept_offer order not open: {} {}", insc.tx_hash, order_id);
+            return insc.fail(InscriptionFailReason::MarketOrderNotOpen);
+        }
+
+        if market.fee(log).saturating_add(market.royalty(log)) > order.total_price.into() {
+            warn!(
+                "[indexer] market_accept_offer fee exceeds price: {} {}",
+                insc.tx_hash, order_id
+            );
+            return insc.fail(InscriptionFailReason::MarketFeeExceedsPrice);
+        }
+
+        let nft_id = match market.nft_tx(log) {
+            Some(nft_tx) => self.db.read().unwrap().get_inscription_id_by_tx(&nft_tx),
+            None => 0,
+        };
+        if market_fee(&self.db.read().unwrap(), market, log, nft_id).is_none() {
+            warn!(
+                "[indexer] market_accept_offer collection not found: {} {}",
+                insc.tx_hash, order_id
+            );
+            return insc.fail(InscriptionFailReason::MarketCollectionNotFound);
+        }
+
+        let result = match order.order_type {
+            MarketOrderType::TokenBid => self.execute_market_accept_offer_token(insc, &order),
+            MarketOrderType::NFTBid => self.execute_market_accept_offer_nft(insc, market, &order, log),
+            MarketOrderType::Token | MarketOrderType::NFT => {
+                warn!(
+                    "[indexer] market_accept_offer order is not a bid: {} {}",
+                    insc.tx_hash, order_id
+                );
+                insc.fail(InscriptionFailReason::MarketOrderNotBid)
+            }
+        };
+
+        if result {
+            self.market_bid_accepts.insert(order_id.clone());
+        }
+        if result && insc.market_order_id.is_none() {
+            insc.market_order_id = Some(order_id.clone());
+        }
+
+        result
+    }
+
+    fn execute_market_accept_offer_token(&mut self, insc: &mut Inscription, order: &MarketOrder) -> bool {
+        let tick = &order.tick;
+        let transfer_amount = order.amount;
+        let seller_balance = self.get_token_balance(tick, &insc.from);
+
+        if transfer_amount > seller_balance {
+            warn!(
+                "[indexer] market_accept_offer insufficient balance: {} {} {} {}",
+                insc.tx_hash, order.order_id, insc.from, seller_balance
+            );
+            return insc.fail(InscriptionFailReason::MarketBalanceInsufficient);
+        }
+
+        self.token_balance_change_update(tick, &insc.from, -(transfer_amount as i64));
+        self.token_balance_change_update(tick, &order.from, transfer_amount as i64);
+
+        info!(
+            "[indexer] market_accept_offer_token: {} {} {} {} {}",
+            insc.tx_hash, order.order_id, insc.from, order.tick, order.amount
+        );
+        true
+    }
+
+    fn execute_market_accept_offer_nft(
+        &mut self,
+        insc: &mut Inscription,
+        market: &MarketContract,
+        order: &MarketOrder,
+        log: &web3::ethabi::Log,
+    ) -> bool {
+        let nft_id = if order.collection.is_empty() {
+            order.nft_id
+        } else {
+            let nft_id = match market.nft_tx(log) {
+                Some(nft_tx) => self.db.read().unwrap().get_inscription_id_by_tx(&nft_tx),
+                None => 0,
+            };
+
+            if nft_id == 0 || self.get_nft_collection(nft_id).as_ref() != Some(&order.collection) {
+                warn!(
+                    "[indexer] market_accept_offer nft not in collection: {} {} {}",
+                    insc.tx_hash, order.order_id, order.collection
+                );
+                return insc.fail(InscriptionFailReason::MarketNftNotInCollection);
+            }
+            nft_id
+        };
+
+        let holder = self.get_nft_holder(nft_id);
+        if holder != insc.from {
+            warn!(
+                "[indexer] market_accept_offer nft not owned: {} {} {} {}",
+                insc.tx_hash, order.order_id, insc.from, nft_id
+            );
+            return insc.fail(InscriptionFailReason::MarketNftNotHeld);
+        }
+
+        match self.nft_holders.get_mut(&nft_id) {
+            Some(holder) => *holder = order.from.clone(),
+            None => {
+                self.nft_holders.insert(nft_id, order.from.clone());
+            }
+        }
+
+        self.nft_transfers.push(NFTTransfer {
+            nft_id,
+            transfer_id: insc.id,
+        });
+
+        info!(
+            "[indexer] market_accept_offer_nft: {} {} {} {}",
+            insc.tx_hash, order.order_id, insc.from, nft_id
+        );
+        true
+    }
+
+    /// Returns the ids of the orders the inscription listed or changed.
+    fn save_market(&self, db: &TransactionDB, txn: &DBTransaction, insc: &Inscription) -> Vec<String> {
+        let mut order_ids = Vec::new();
         if !MARKET_ADDRESS_LIST.contains(&insc.to) {
-            return;
+            return order_ids;
         }
 
         if insc.mime_category == InscriptionMimeCategory::Json
             && insc.json["op"].as_str().unwrap() == APP_OPER_TOKEN_MARKET_LIST
         {
             self.save_market_new_order_token(txn, insc);
+            order_ids.push(insc.market_order_id.clone().unwrap());
         } else if insc.mime_category == InscriptionMimeCategory::Transfer {
-            self.save_market_new_order_nft(txn, insc);
+            order_ids.push(self.save_market_new_order_nft(txn, insc));
         } else if insc.mime_category == InscriptionMimeCategory::Invoke {
-            if let Some(log) = insc.event_logs[0].match_event(&CONTRACT_MARKET, "MarketSetPrice") {
-                self.save_market_set_price(db, txn, insc, &log);
-            } else if let Some(log) = insc.event_logs[0].match_event(&CONTRACT_MARKET, "MarketCancel") {
-                self.save_market_cancel(db, txn, insc, &log);
-            } else if let Some(log) = insc.event_logs[0].match_event(&CONTRACT_MARKET, "MarketBuy") {
-                self.save_market_buy(db, txn, insc, &log);
+            for event_log in &insc.event_logs {
+                let market = match market_contract_of_log(event_log) {
+                    Some(market) => market,
+                    None => continue,
+                };
+
+                let log = if let Some(log) = market.match_event(event_log, MarketEvent::SetPrice) {
+                    self.save_market_set_price(txn, insc, market, &log);
+                    log
+                } else if let Some(log) = market.match_event(event_log, MarketEvent::Cancel) {
+                    self.save_market_cancel(txn, insc, market, &log);
+                    log
+                } else if let Some(log) = market.match_event(event_log, MarketEvent::Buy) {
+                    self.save_market_buy(db, txn, insc, market, &log);
+                    log
+                } else if let Some(log) = market.match_event(event_log, MarketEvent::Offer) {
+                    self.save_market_offer(txn, insc, market, &log);
+                    log
+                } else if let Some(log) = market.match_event(event_log, MarketEvent::AcceptOffer) {
+                    self.save_market_accept_offer(db, txn, insc, market, &log);
+                    log
+                } else {
+                    continue;
+                };
+                order_ids.push(market.order_id(&log));
             }
         }
+        order_ids
     }
 
-    fn save_market_new_order_token(&self, txn: &Transaction<TransactionDB>, insc: &Inscription) {
+    fn save_market_new_order_token(&self, txn: &DBTransaction, insc: &Inscription) {
         assert!(insc.market_order_id.is_some());
 
         let tick = insc.json["tick"].as_str().unwrap();
@@ -236,8 +612,10 @@ impl MarketPlace for InscribeContext {
             to: insc.to.clone(),
             nft_id: 0,
             nft_tx: "".to_string(),
+            collection: "".to_string(),
             tick: tick.to_string(),
             amount,
+            filled_amount: 0,
             total_price: 0,
             unit_price: 0,
             tx: insc.tx_hash.clone(),
@@ -246,18 +624,21 @@ impl MarketPlace for InscribeContext {
             tx_close: "".to_string(),
             blocknumber: insc.blocknumber,
             timestamp: insc.timestamp,
+            expire_at: insc.json["expire"].parse_u64().unwrap_or(0),
             order_status: MarketOrderStatus::Init,
             buyer: "".to_string(),
+            seller: "".to_string(),
+            fee: 0,
+            royalty: 0,
+            seller_proceeds: 0,
         };
 
         txn.market_order_save(&order);
     }
 
-    fn save_market_new_order_nft(&self, txn: &Transaction<TransactionDB>, insc: &Inscription) {
-        assert!(insc.event_logs.len() == 1);
-
-        let log = insc.event_logs[0].match_event(&CONTRACT_MARKET, "MarketList").unwrap();
-        let order_id = "0x".to_string() + &"0x".to_string() + &log.get_param("orderId").unwrap().to_string();
+    fn save_market_new_order_nft(&self, txn: &DBTransaction, insc: &Inscription) -> String {
+        let (market, log) = market_list_event(insc).unwrap();
+        let order_id = "0x".to_string() + &market.order_id(&log);
         let nft_tx = "0x".to_string() + &insc.mime_data[0..TRANSFER_TX_HEX_LENGTH];
         let nft_id = self.db.read().unwrap().get_inscription_id_by_tx(&nft_tx);
 
@@ -268,8 +649,10 @@ impl MarketPlace for InscribeContext {
             to: insc.to.clone(),
             nft_id,
             nft_tx,
+            collection: "".to_string(),
             tick: "".to_string(),
             amount: 0,
+            filled_amount: 0,
             total_price: 0,
             unit_price: 0,
             tx: insc.tx_hash.clone(),
@@ -278,64 +661,158 @@ impl MarketPlace for InscribeContext {
             tx_close: "".to_string(),
             blocknumber: insc.blocknumber,
             timestamp: insc.timestamp,
+            expire_at: 0,
             order_status: MarketOrderStatus::Init,
             buyer: "".to_string(),
+            seller: "".to_string(),
+            fee: 0,
+            royalty: 0,
+            seller_proceeds: 0,
         };
 
         txn.market_order_save(&order);
+        order.order_id
     }
 
     fn save_market_set_price(
         &self,
-        db: &TransactionDB,
-        txn: &Transaction<TransactionDB>,
+        txn: &DBTransaction,
         insc: &Inscription,
+        market: &MarketContract,
         log: &web3::ethabi::Log,
     ) {
-        assert!(insc.event_logs.len() == 1);
-
-        let order_id = "0x".to_string() + &log.get_param("orderId").unwrap().to_string();
-        let total_price_u256 = log.get_param("price").unwrap().clone().into_uint().unwrap();
+        let order_id = market.order_id(log);
+        let total_price_u256 = market.price(log);
         let total_price = total_price_u256.try_into();
+        let expire_at = match market.deadline(log) {
+            Some(deadline) => deadline.try_into().unwrap_or(0),
+            None => 0,
+        };
 
-        txn.market_order_set_price(db, &insc.tx_hash, &order_id, total_price.unwrap());
+        txn.market_order_set_price(&insc.tx_hash, &order_id, total_price.unwrap(), expire_at);
     }
 
     fn save_market_buy(
         &self,
         db: &TransactionDB,
-        txn: &Transaction<TransactionDB>,
+        txn: &DBTransaction,
         insc: &Inscription,
+        market: &MarketContract,
         log: &web3::ethabi::Log,
     ) {
-        assert!(insc.event_logs.len() == 1);
-        let order_id = "0x".to_string() + &log.get_param("orderId").unwrap().to_string();
-        txn.market_order_close(db, &insc.tx_hash, &order_id, &insc.from);
+        let order_id = market.order_id(log);
+        // the order may have been partially filled earlier in this block
+        let index_key_id = make_index_key(KEY_MARKET_ORDER_INDEX_ID, &order_id);
+        let order = MarketOrder::decode(&txn.get(index_key_id.as_bytes()).unwrap().unwrap());
+        let fill_amount = match order.order_type {
+            MarketOrderType::Token => match market.amount(log) {
+                Some(amount) => amount.as_u64(),
+                None => order.amount - order.filled_amount,
+            },
+            _ => 0,
+        };
+        let fee = market_fee(db, market, log, order.nft_id).unwrap();
+        txn.market_order_close(&insc.tx_hash, &order_id, &insc.from, fill_amount, &fee, insc.timestamp);
     }
 
     fn save_market_cancel(
+        &self,
+        txn: &DBTransaction,
+        insc: &Inscription,
+        market: &MarketContract,
+        log: &web3::ethabi::Log,
+    ) {
+        let order_id = market.order_id(log);
+        txn.market_order_cancel(&insc.tx_hash, &order_id);
+    }
+
+    fn save_market_offer(&self, txn: &DBTransaction, insc: &Inscription, market: &MarketContract, log: &web3::ethabi::Log) {
+        let total_price: u128 = market.price(log).try_into().unwrap();
+        let expire_at = match market.deadline(log) {
+            Some(deadline) => deadline.try_into().unwrap_or(0),
+            None => 0,
+        };
+
+        let mut order = MarketOrder {
+            order_type: MarketOrderType::NFTBid,
+            order_id: market.order_id(log),
+            from: insc.from.clone(),
+            to: insc.to.clone(),
+            tick: "".to_string(),
+            nft_id: 0,
+            nft_tx: "".to_string(),
+            collection: "".to_string(),
+            amount: 0,
+            filled_amount: 0,
+            total_price,
+            unit_price: total_price,
+            tx: insc.tx_hash.clone(),
+            tx_setprice: "".to_string(),
+            tx_cancel: "".to_string(),
+            tx_close: "".to_string(),
+            blocknumber: insc.blocknumber,
+            timestamp: insc.timestamp,
+            expire_at,
+            order_status: MarketOrderStatus::Open,
+            buyer: insc.from.clone(),
+            seller: "".to_string(),
+            fee: 0,
+            royalty: 0,
+            seller_proceeds: 0,
+        };
+
+        if let Some(tick) = market.tick(log) {
+            order.order_type = MarketOrderType::TokenBid;
+            order.tick = tick;
+            order.amount = market.amount(log).unwrap().try_into().unwrap();
+            order.unit_price = total_price / order.amount as u128;
+        } else if let Some(nft_tx) = market.nft_tx(log) {
+            order.nft_id = self.db.read().unwrap().get_inscription_id_by_tx(&nft_tx);
+            order.nft_tx = nft_tx;
+        } else {
+            order.collection = market.collection(log).unwrap();
+        }
+
+        txn.market_order_save(&order);
+    }
+
+    fn save_market_accept_offer(
         &self,
         db: &TransactionDB,
-        txn: &Transaction<TransactionDB>,
+        txn: &DBTransaction,
         insc: &Inscription,
+        market: &MarketContract,
         log: &web3::ethabi::Log,
     ) {
-        assert!(insc.event_logs.len() == 1);
-        let order_id = "0x".to_string() + &log.get_param("orderId").unwrap().to_string();
-        txn.market_order_cancel(db, &insc.tx_hash, &order_id);
+        let order_id = market.order_id(log);
+        let (nft_id, nft_tx) = match market.nft_tx(log) {
+            Some(nft_tx) => (db.get_inscription_id_by_tx(&nft_tx), nft_tx),
+            None => (0, "".to_string()),
+        };
+        let fee = market_fee(db, market, log, nft_id).unwrap();
+        let result = txn.market_bid_close(&insc.tx_hash, &order_id, &insc.from, (nft_id, &nft_tx), &fee, insc.timestamp);
+        if let Err(reason) = result {
+            warn!(
+                "[indexer] market_accept_offer bid not closed: {} {} {:?}",
+                insc.tx_hash, order_id, reason
+            );
+        }
+    }
+
+    fn save_market_expire(&self, db: &TransactionDB, txn: &DBTransaction) -> Vec<String> {
+        let timestamp = self.inscriptions.last().unwrap().timestamp;
+        txn.market_orders_expire(db, timestamp)
     }
 
     fn update_token_market_info(db: &TransactionDB, token: &mut InscriptionToken) {
         const MCAP_CALC_COUNT: u64 = 16;
-        let orders = db.market_get_latest_closed_orders(&token.tick, MCAP_CALC_COUNT);
-        let mut volume24: u128 = 0;
+        let trades = db.market_get_latest_trades(&token.tick, MCAP_CALC_COUNT);
         let mut total_amount: u128 = 0;
         let mut total_price: u128 = 0;
 
-        for order in &orders {
-            volume24 += order.amount as u128;
-            total_amount += order.amount as u128;
-            total_price += order.total_price;
+        for trade in &trades {
+            total_amount += trade.amount as u128;
+            total_price += trade.total_price;
         }
 
         if total_amount == 0 {
@@ -343,15 +820,13 @@ impl MarketPlace for InscribeContext {
         }
 
         let latest_unit_price = total_price / total_amount;
-        token.market_volume24h = volume24;
-        token.market_txs24h = orders.len() as u64;
 
         // calculate floor price
         let floor_prefix = make_index_key(KEY_MARKET_ORDER_INDEX_TICK_PRICE, &token.tick) + ":";
-        let mut iter = db.iterator(rocksdb::IteratorMode::From(
-            floor_prefix.as_bytes(),
-            rocksdb::Direction::Forward,
-        ));
+        let mut iter = DBBase::iterator(
+            db,
+            rocksdb::IteratorMode::From(floor_prefix.as_bytes(), rocksdb::Direction::Forward),
+        );
         while let Some(Ok((key, _))) = iter.next() {
             if !key.starts_with(floor_prefix.as_bytes()) {
                 break;
@@ -360,7 +835,8 @@ impl MarketPlace for InscribeContext {
             let key = String::from_utf8(key.to_vec()).unwrap();
             let order_id = key.rfind(':').map(|i| key[i + 1..].to_string()).unwrap();
             let order = db.market_get_order_by_id(&order_id).unwrap();
-            if order.amount >= token.mint_limit {
+            // partially filled orders only have what is left for sale
+            if order.amount - order.filled_amount >= token.mint_limit {
                 token.market_floor_price = order.unit_price;
                 break;
             }
@@ -370,3 +846,56 @@ impl MarketPlace for InscribeContext {
         token.market_cap = mcap_unit_price * token.mint_max as u128;
     }
 }
+
+/// Fee split of a fill, the royalty is credited to the creator of the collection the item belongs to. `None` when the
+/// collection of the item has no inscription.
+fn market_fee(db: &TransactionDB, market: &MarketContract, log: &web3::ethabi::Log, nft_id: u64) -> Option<MarketFee> {
+    let creator = match db.get_inscription_nft_collection_by_id(nft_id) {
+        Some(collection) if nft_id > 0 => db.get_inscription_by_tx(&collection)?.from,
+        _ => "".to_string(),
+    };
+
+    Some(MarketFee {
+        market: market.address.clone(),
+        creator,
+        fee: market.fee(log).as_u128(),
+        royalty: market.royalty(log).as_u128(),
+    })
+}
+
+/// The first list event among the event logs of a listing tx.
+pub fn market_list_event(insc: &Inscription) -> Option<(&'static MarketContract, web3::ethabi::Log)> {
+    insc.event_logs.iter().find_map(|event_log| {
+        let market = market_contract_of_log(event_log)?;
+        market.match_event(event_log, MarketEvent::List).map(|log| (market, log))
+    })
+}
+
+/// Context state changed by market settlement, restored when a later event of the same tx fails.
+struct MarketSnapshot {
+    token_balance_change: HashMap<String, HashMap<String, i64>>,
+    nft_holders: HashMap<u64, String>,
+    nft_transfers_len: usize,
+    market_order_fills: HashMap<String, u64>,
+    market_bid_accepts: HashSet<String>,
+}
+
+impl MarketSnapshot {
+    fn take(ctx: &InscribeContext) -> Self {
+        MarketSnapshot {
+            token_balance_change: ctx.token_balance_change.clone(),
+            nft_holders: ctx.nft_holders.clone(),
+            nft_transfers_len: ctx.nft_transfers.len(),
+            market_order_fills: ctx.market_order_fills.clone(),
+            market_bid_accepts: ctx.market_bid_accepts.clone(),
+        }
+    }
+
+    fn restore(self, ctx: &mut InscribeContext) {
+        ctx.token_balance_change = self.token_balance_change;
+        ctx.nft_holders = self.nft_holders;
+        ctx.nft_transfers.truncate(self.nft_transfers_len);
+        ctx.market_order_fills = self.market_order_fills;
+        ctx.market_bid_accepts = self.market_bid_accepts;
+    }
+}
@@ -0,0 +1,247 @@
+use super::market_abi::MARKET_ABI_JSON;
+use crate::{
+    config::{ARGS, MARKET_CONFIG},
+    ethereum::{HexParseTrait, Web3ABILogEvent, Web3LogEvent},
+};
+use serde::Deserialize;
+use std::collections::HashMap;
+
+lazy_static! {
+    pub static ref MARKET_CONTRACTS: HashMap<String, MarketContract> = load_market_contracts();
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MarketEvent {
+    List,
+    Buy,
+    Cancel,
+    SetPrice,
+    Offer,
+    AcceptOffer,
+}
+
+/// Where the order id of a token listing comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum MarketOrderIdSource {
+    /// keccak256 of seller, blocknumber and calldata of the listing tx
+    #[default]
+    Tx,
+    /// the order id field of the list event emitted by the listing tx
+    Event,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct MarketEventNames {
+    pub list: String,
+    pub buy: String,
+    pub cancel: String,
+    pub set_price: String,
+    pub offer: String,
+    pub accept_offer: String,
+}
+
+impl Default for MarketEventNames {
+    fn default() -> Self {
+        MarketEventNames {
+            list: "MarketList".to_string(),
+            buy: "MarketBuy".to_string(),
+            cancel: "MarketCancel".to_string(),
+            set_price: "MarketSetPrice".to_string(),
+            offer: "MarketOffer".to_string(),
+            accept_offer: "MarketAcceptOffer".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct MarketFieldNames {
+    pub order_id: String,
+    pub price: String,
+    pub deadline: String,
+    pub tick: String,
+    pub amount: String,
+    pub nft_tx: String,
+    pub collection: String,
+    pub fee: String,
+    pub royalty: String,
+}
+
+impl Default for MarketFieldNames {
+    fn default() -> Self {
+        MarketFieldNames {
+            order_id: "orderId".to_string(),
+            price: "price".to_string(),
+            deadline: "deadline".to_string(),
+            tick: "tick".to_string(),
+            amount: "amount".to_string(),
+            nft_tx: "nftTx".to_string(),
+            collection: "collection".to_string(),
+            fee: "fee".to_string(),
+            royalty: "royalty".to_string(),
+        }
+    }
+}
+
+/// One market entry of the market config file, every field but the address is optional.
+#[derive(Debug, Deserialize)]
+pub struct MarketConfigItem {
+    pub address: String,
+    /// path of the abi json file, the builtin market abi when empty
+    #[serde(default)]
+    pub abi: String,
+    #[serde(default)]
+    pub events: MarketEventNames,
+    #[serde(default)]
+    pub fields: MarketFieldNames,
+    #[serde(default)]
+    pub order_id_source: MarketOrderIdSource,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MarketConfig {
+    pub markets: Vec<MarketConfigItem>,
+}
+
+pub struct MarketContract {
+    pub address: String,
+    pub contract: web3::ethabi::Contract,
+    pub events: MarketEventNames,
+    pub fields: MarketFieldNames,
+    pub order_id_source: MarketOrderIdSource,
+}
+
+impl MarketContract {
+    fn new(item: MarketConfigItem) -> Self {
+        let contract = if item.abi.is_empty() {
+            web3::ethabi::Contract::load(MARKET_ABI_JSON.as_bytes()).unwrap()
+        } else {
+            let file = std::fs::File::open(&item.abi).unwrap_or_else(|_| panic!("market abi not found: {}", item.abi));
+            web3::ethabi::Contract::load(file).unwrap()
+        };
+
+        MarketContract {
+            address: item.address.to_lowercase(),
+            contract,
+            events: item.events,
+            fields: item.fields,
+            order_id_source: item.order_id_source,
+        }
+    }
+
+    pub fn event_name(&self, event: MarketEvent) -> &str {
+        match event {
+            MarketEvent::List => &self.events.list,
+            MarketEvent::Buy => &self.events.buy,
+            MarketEvent::Cancel => &self.events.cancel,
+            MarketEvent::SetPrice => &self.events.set_price,
+            MarketEvent::Offer => &self.events.offer,
+            MarketEvent::AcceptOffer => &self.events.accept_offer,
+        }
+    }
+
+    pub fn match_event(&self, log: &web3::types::Log, event: MarketEvent) -> Option<web3::ethabi::Log> {
+        if log.address.to_hex_string().to_lowercase() != self.address {
+            return None;
+        }
+        if self.contract.events_by_name(self.event_name(event)).is_err() {
+            return None;
+        }
+        log.match_event(&self.contract, self.event_name(event))
+    }
+
+    pub fn order_id(&self, log: &web3::ethabi::Log) -> String {
+        "0x".to_string() + &log.get_param(&self.fields.order_id).unwrap().to_string()
+    }
+
+    pub fn price(&self, log: &web3::ethabi::Log) -> web3::types::U256 {
+        log.get_param(&self.fields.price).unwrap().clone().into_uint().unwrap()
+    }
+
+    pub fn deadline(&self, log: &web3::ethabi::Log) -> Option<web3::types::U256> {
+        log.get_param(&self.fields.deadline)
+            .and_then(|deadline| deadline.clone().into_uint())
+    }
+
+    pub fn tick(&self, log: &web3::ethabi::Log) -> Option<String> {
+        log.get_param(&self.fields.tick)
+            .and_then(|tick| tick.clone().into_string())
+            .filter(|tick| !tick.is_empty())
+    }
+
+    pub fn amount(&self, log: &web3::ethabi::Log) -> Option<web3::types::U256> {
+        log.get_param(&self.fields.amount)
+            .and_then(|amount| amount.clone().into_uint())
+    }
+
+    /// Market fee of a fill, zero when the event does not carry it.
+    pub fn fee(&self, log: &web3::ethabi::Log) -> web3::types::U256 {
+        log.get_param(&self.fields.fee)
+            .and_then(|fee| fee.clone().into_uint())
+            .unwrap_or_default()
+    }
+
+    /// Creator royalty of a fill, zero when the event does not carry it.
+    pub fn royalty(&self, log: &web3::ethabi::Log) -> web3::types::U256 {
+        log.get_param(&self.fields.royalty)
+            .and_then(|royalty| royalty.clone().into_uint())
+            .unwrap_or_default()
+    }
+
+    pub fn nft_tx(&self, log: &web3::ethabi::Log) -> Option<String> {
+        bytes32_param(log, &self.fields.nft_tx)
+    }
+
+    pub fn collection(&self, log: &web3::ethabi::Log) -> Option<String> {
+        bytes32_param(log, &self.fields.collection)
+    }
+}
+
+/// A tx hash like bytes32 param as 0x prefixed hex, zero bytes mean the param is unset.
+fn bytes32_param(log: &web3::ethabi::Log, name: &str) -> Option<String> {
+    log.get_param(name)
+        .and_then(|param| param.clone().into_fixed_bytes())
+        .filter(|bytes| bytes.iter().any(|byte| *byte != 0))
+        .map(|bytes| "0x".to_string() + &hex::encode(bytes))
+}
+
+fn load_market_contracts() -> HashMap<String, MarketContract> {
+    let mut items: Vec<MarketConfigItem> = ARGS
+        .market_address_list
+        .split(',')
+        .filter(|address| !address.is_empty())
+        .map(|address| MarketConfigItem {
+            address: address.to_string(),
+            abi: "".to_string(),
+            events: MarketEventNames::default(),
+            fields: MarketFieldNames::default(),
+            order_id_source: MarketOrderIdSource::default(),
+        })
+        .collect();
+
+    if !MARKET_CONFIG.is_empty() {
+        let data = std::fs::read_to_string(MARKET_CONFIG.as_str())
+            .unwrap_or_else(|_| panic!("market config not found: {}", *MARKET_CONFIG));
+        let config: MarketConfig = serde_json::from_str(&data).unwrap();
+        items.extend(config.markets);
+    }
+
+    items
+        .into_iter()
+        .map(|item| {
+            let market = MarketContract::new(item);
+            (market.address.clone(), market)
+        })
+        .collect()
+}
+
+pub fn market_contract(address: &str) -> Option<&'static MarketContract> {
+    MARKET_CONTRACTS.get(address)
+}
+
+/// The market contract which emitted the log.
+pub fn market_contract_of_log(log: &web3::types::Log) -> Option<&'static MarketContract> {
+    market_contract(&log.address.to_hex_string().to_lowercase())
+}
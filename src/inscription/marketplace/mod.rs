@@ -1,51 +1,88 @@
 pub mod db;
 pub mod inscribe_market;
 pub mod market_abi;
+pub mod market_config;
 pub mod types;
 
-use self::types::MarketOrder;
+use self::{market_config::MarketContract, types::MarketOrder};
 use super::types::{Inscription, InscriptionToken};
-use rocksdb::{Transaction, TransactionDB};
+use crate::txn_db::transaction::DBTransaction;
+use rocksdb::TransactionDB;
 
 pub const APP_OPER_TOKEN_MARKET_LIST: &'static str = "market_list";
 
 pub trait MarketPlace {
     fn process_inscribe_invoke(&mut self, insc: &mut Inscription) -> bool;
 
-    fn execute_market_buy(&mut self, insc: &mut Inscription, log: &web3::ethabi::Log) -> bool;
-    fn execute_market_buy_token(&mut self, insc: &Inscription, order: &MarketOrder, log: &web3::ethabi::Log) -> bool;
-    fn execute_market_buy_nft(&mut self, insc: &Inscription, order: &MarketOrder, log: &web3::ethabi::Log) -> bool;
+    fn execute_market_buy(&mut self, insc: &mut Inscription, market: &MarketContract, log: &web3::ethabi::Log) -> bool;
+    fn execute_market_buy_token(
+        &mut self,
+        insc: &mut Inscription,
+        market: &MarketContract,
+        order: &MarketOrder,
+        log: &web3::ethabi::Log,
+    ) -> bool;
+    fn execute_market_buy_nft(
+        &mut self,
+        insc: &mut Inscription,
+        market: &MarketContract,
+        order: &MarketOrder,
+        log: &web3::ethabi::Log,
+    ) -> bool;
 
-    fn execute_market_cancel(&mut self, insc: &mut Inscription, log: &web3::ethabi::Log) -> bool;
-    fn execute_market_cancel_token(&mut self, insc: &Inscription, order: &MarketOrder) -> bool;
-    fn execute_market_cancel_nft(&mut self, insc: &Inscription, order: &MarketOrder) -> bool;
+    fn execute_market_cancel(&mut self, insc: &mut Inscription, market: &MarketContract, log: &web3::ethabi::Log) -> bool;
+    fn execute_market_cancel_token(&mut self, insc: &mut Inscription, order: &MarketOrder) -> bool;
+    fn execute_market_cancel_nft(&mut self, insc: &mut Inscription, order: &MarketOrder) -> bool;
 
-    fn execute_market_set_price(&mut self, insc: &mut Inscription, log: &web3::ethabi::Log) -> bool;
+    fn execute_market_set_price(&mut self, insc: &mut Inscription, market: &MarketContract, log: &web3::ethabi::Log) -> bool;
 
-    fn save_market(&self, db: &TransactionDB, txn: &Transaction<TransactionDB>, insc: &Inscription);
-    fn save_market_new_order_token(&self, txn: &Transaction<TransactionDB>, insc: &Inscription);
-    fn save_market_new_order_nft(&self, txn: &Transaction<TransactionDB>, insc: &Inscription);
+    fn execute_market_offer(&mut self, insc: &mut Inscription, market: &MarketContract, log: &web3::ethabi::Log) -> bool;
+    fn execute_market_accept_offer(&mut self, insc: &mut Inscription, market: &MarketContract, log: &web3::ethabi::Log)
+        -> bool;
+    fn execute_market_accept_offer_token(&mut self, insc: &mut Inscription, order: &MarketOrder) -> bool;
+    fn execute_market_accept_offer_nft(
+        &mut self,
+        insc: &mut Inscription,
+        market: &MarketContract,
+        order: &MarketOrder,
+        log: &web3::ethabi::Log,
+    ) -> bool;
+
+    fn save_market(&self, db: &TransactionDB, txn: &DBTransaction, insc: &Inscription) -> Vec<String>;
+    fn save_market_new_order_token(&self, txn: &DBTransaction, insc: &Inscription);
+    fn save_market_new_order_nft(&self, txn: &DBTransaction, insc: &Inscription) -> String;
     fn save_market_set_price(
         &self,
-        db: &TransactionDB,
-        txn: &Transaction<TransactionDB>,
+        txn: &DBTransaction,
         insc: &Inscription,
+        market: &MarketContract,
         log: &web3::ethabi::Log,
     );
     fn save_market_buy(
         &self,
         db: &TransactionDB,
-        txn: &Transaction<TransactionDB>,
+        txn: &DBTransaction,
         insc: &Inscription,
+        market: &MarketContract,
         log: &web3::ethabi::Log,
     );
     fn save_market_cancel(
+        &self,
+        txn: &DBTransaction,
+        insc: &Inscription,
+        market: &MarketContract,
+        log: &web3::ethabi::Log,
+    );
+    fn save_market_offer(&self, txn: &DBTransaction, insc: &Inscription, market: &MarketContract, log: &web3::ethabi::Log);
+    fn save_market_accept_offer(
         &self,
         db: &TransactionDB,
-        txn: &Transaction<TransactionDB>,
+        txn: &DBTransaction,
         insc: &Inscription,
+        market: &MarketContract,
         log: &web3::ethabi::Log,
     );
+    fn save_market_expire(&self, db: &TransactionDB, txn: &DBTransaction) -> Vec<String>;
 
     fn update_token_market_info(db: &TransactionDB, token: &mut InscriptionToken);
 }
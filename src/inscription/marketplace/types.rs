@@ -1,26 +1,28 @@
+use crate::{storage_enum, txn_db::codec::StorageCodec};
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Serialize, Deserialize, PartialEq)]
-pub enum MarketOrderType {
-    #[serde(rename = "0")]
-    NFT,
-    #[serde(rename = "1")]
-    Token,
+storage_enum! {
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub enum MarketOrderType {
+        NFT = 0,
+        Token = 1,
+        TokenBid = 2,
+        NFTBid = 3,
+    }
 }
 
-#[derive(Debug, Serialize, Deserialize, PartialEq)]
-pub enum MarketOrderStatus {
-    #[serde(rename = "0")]
-    Init,
-    #[serde(rename = "1")]
-    Open,
-    #[serde(rename = "2")]
-    Close,
-    #[serde(rename = "3")]
-    Canceled,
+storage_enum! {
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub enum MarketOrderStatus {
+        Init = 0,
+        Open = 1,
+        Close = 2,
+        Canceled = 3,
+        Expired = 4,
+    }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MarketOrder {
     pub order_type: MarketOrderType,
     pub order_id: String,
@@ -29,7 +31,13 @@ pub struct MarketOrder {
     pub tick: String,
     pub nft_id: u64,
     pub nft_tx: String,
+    /// Collection of a collection-wide NFT bid, any item of it can be sold into the bid.
+    #[serde(default)]
+    pub collection: String,
     pub amount: u64,
+    /// Amount of a token listing bought so far, the listing closes once it reaches `amount`.
+    #[serde(default)]
+    pub filled_amount: u64,
     pub total_price: u128,
     pub unit_price: u128,
     pub tx: String,
@@ -41,7 +49,252 @@ pub struct MarketOrder {
     pub blocknumber: u64,
     pub timestamp: u64,
 
+    /// Unix time the order lapses at, 0 means it never expires.
+    #[serde(default)]
+    pub expire_at: u64,
+
     pub order_status: MarketOrderStatus,
 
     pub buyer: String,
+    /// The seller who accepted a bid.
+    #[serde(default)]
+    pub seller: String,
+
+    /// Market fee, creator royalty and what the seller received, summed over the fills of the order.
+    #[serde(default)]
+    pub fee: u128,
+    #[serde(default)]
+    pub royalty: u128,
+    #[serde(default)]
+    pub seller_proceeds: u128,
+}
+
+impl MarketOrder {
+    /// Price of the next `amount` units, rounded so that the fills of an order add up to its `total_price`.
+    pub fn fill_price(&self, amount: u64) -> u128 {
+        if self.amount == 0 {
+            return self.total_price;
+        }
+
+        let paid = |filled: u64| {
+            let price = web3::types::U256::from(self.total_price) * filled / self.amount;
+            price.as_u128()
+        };
+        paid(self.filled_amount + amount) - paid(self.filled_amount)
+    }
+}
+
+/// One fill of an order, a listing or bid closed at once is a single trade.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MarketTrade {
+    pub tx: String,
+    pub order_id: String,
+    pub order_type: MarketOrderType,
+    pub tick: String,
+    pub nft_id: u64,
+    pub seller: String,
+    pub buyer: String,
+    pub amount: u64,
+    pub total_price: u128,
+    pub unit_price: u128,
+    pub timestamp: u64,
+    #[serde(default)]
+    pub market: String,
+    #[serde(default)]
+    pub creator: String,
+    #[serde(default)]
+    pub fee: u128,
+    #[serde(default)]
+    pub royalty: u128,
+    #[serde(default)]
+    pub seller_proceeds: u128,
+}
+
+/// Realized profit of an address on one tick, sells are matched against the average cost of earlier buys.
+#[derive(Debug, Default)]
+pub struct MarketPnl {
+    pub tick: String,
+    pub bought_amount: u64,
+    pub bought_cost: u128,
+    pub sold_amount: u64,
+    pub sold_proceeds: u128,
+    pub position_amount: u64,
+    pub position_cost: u128,
+    pub realized: i128,
+}
+
+impl MarketPnl {
+    pub fn buy(&mut self, amount: u64, cost: u128) {
+        self.bought_amount += amount;
+        self.bought_cost += cost;
+        self.position_amount += amount;
+        self.position_cost += cost;
+    }
+
+    /// Amount sold beyond the tracked position was bought outside the market and is left unmatched.
+    pub fn sell(&mut self, amount: u64, proceeds: u128) {
+        self.sold_amount += amount;
+        self.sold_proceeds += proceeds;
+
+        let matched_amount = std::cmp::min(amount, self.position_amount);
+        if matched_amount == 0 {
+            return;
+        }
+
+        let matched_cost = (web3::types::U256::from(self.position_cost) * matched_amount / self.position_amount).as_u128();
+        let matched_proceeds = (web3::types::U256::from(proceeds) * matched_amount / amount).as_u128();
+
+        self.realized += matched_proceeds as i128 - matched_cost as i128;
+        self.position_amount -= matched_amount;
+        self.position_cost -= matched_cost;
+    }
+}
+
+/// Fee split of one fill as emitted by the market, `creator` is set for items of a collection.
+#[derive(Debug, Default)]
+pub struct MarketFee {
+    pub market: String,
+    pub creator: String,
+    pub fee: u128,
+    pub royalty: u128,
+}
+
+/// Trades summed per market address or per collection creator.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct MarketRevenue {
+    pub volume: u128,
+    pub fee: u128,
+    pub royalty: u128,
+    pub seller_proceeds: u128,
+    pub txs: u64,
+}
+
+/// Trades of a tick closed within one bucket of `MARKET_VOLUME_BUCKET` seconds.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct MarketVolume {
+    pub volume: u128,
+    pub txs: u64,
+}
+
+/// Unit price and volume of a tick's trades closed within one candle interval.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MarketCandle {
+    pub time: u64,
+    pub open: u128,
+    pub high: u128,
+    pub low: u128,
+    pub close: u128,
+    pub volume: u128,
+    pub amount: u64,
+    pub txs: u64,
+}
+
+impl StorageCodec for MarketOrder {}
+
+impl StorageCodec for MarketTrade {}
+
+impl StorageCodec for MarketVolume {}
+
+impl StorageCodec for MarketRevenue {}
+
+impl StorageCodec for MarketCandle {}
+
+/// Orders shared by the market tests.
+#[cfg(test)]
+pub mod fixtures {
+    use super::*;
+
+    /// An open token listing of 100 for 1000, tests set the fields they depend on over it.
+    pub fn order(order_id: &str) -> MarketOrder {
+        MarketOrder {
+            order_type: MarketOrderType::Token,
+            order_id: order_id.to_string(),
+            from: "0xseller".to_string(),
+            to: "0xmarket".to_string(),
+            tick: "test".to_string(),
+            nft_id: 0,
+            nft_tx: "".to_string(),
+            collection: "".to_string(),
+            amount: 100,
+            filled_amount: 0,
+            total_price: 1000,
+            unit_price: 10,
+            tx: format!("0xtx{}", order_id),
+            tx_setprice: "".to_string(),
+            tx_cancel: "".to_string(),
+            tx_close: "".to_string(),
+            blocknumber: 1,
+            timestamp: 10,
+            expire_at: 0,
+            order_status: MarketOrderStatus::Open,
+            buyer: "".to_string(),
+            seller: "".to_string(),
+            fee: 0,
+            royalty: 0,
+            seller_proceeds: 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn order(amount: u64, total_price: u128) -> MarketOrder {
+        MarketOrder {
+            amount,
+            total_price,
+            unit_price: 0,
+            ..fixtures::order("0x01")
+        }
+    }
+
+    #[test]
+    fn partial_fills_add_up_to_the_total_price() {
+        let fills = |mut order: MarketOrder, amounts: &[u64]| {
+            let mut paid = vec![];
+            for amount in amounts {
+                paid.push(order.fill_price(*amount));
+                order.filled_amount += amount;
+            }
+            paid
+        };
+
+        assert_eq!(fills(order(3, 1000), &[1, 1, 1]), vec![333, 333, 334]);
+        assert_eq!(fills(order(7, 100), &[2, 1, 4]).iter().sum::<u128>(), 100);
+    }
+
+    #[test]
+    fn zero_amount_order_fills_at_the_total_price() {
+        let order = order(0, 1000);
+        assert_eq!(order.fill_price(0), 1000);
+    }
+
+    #[test]
+    fn sell_is_matched_against_the_average_cost() {
+        let mut pnl = MarketPnl::default();
+        pnl.buy(10, 100);
+        pnl.buy(10, 300);
+        pnl.sell(5, 150);
+        assert_eq!(pnl.realized, 50);
+        assert_eq!(pnl.position_amount, 15);
+        assert_eq!(pnl.position_cost, 300);
+    }
+
+    #[test]
+    fn sell_larger_than_the_position_leaves_the_rest_unmatched() {
+        let mut pnl = MarketPnl::default();
+        pnl.buy(10, 100);
+        pnl.sell(15, 300);
+        assert_eq!(pnl.sold_amount, 15);
+        assert_eq!(pnl.sold_proceeds, 300);
+        assert_eq!(pnl.realized, 100);
+        assert_eq!(pnl.position_amount, 0);
+        assert_eq!(pnl.position_cost, 0);
+
+        // nothing left to match against
+        pnl.sell(5, 100);
+        assert_eq!(pnl.sold_amount, 20);
+        assert_eq!(pnl.realized, 100);
+    }
 }
@@ -1,7 +1,9 @@
 pub mod db;
 pub mod db_checkpoint;
+pub mod db_migration;
 pub mod db_txn;
 pub mod db_txndb;
+pub mod events;
 pub mod indexer;
 pub mod inscribe_collection;
 pub mod inscribe_context;
@@ -11,5 +13,6 @@ pub mod marketplace;
 pub mod trait_json_value;
 pub mod trait_tx;
 pub mod types;
+pub mod webhook;
 pub mod worker_inscribe;
 pub mod worker_sync;
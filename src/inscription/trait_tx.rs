@@ -1,5 +1,8 @@
-use super::types::*;
-use crate::{config::MARKET_ADDRESS_LIST, ethereum::HexParseTrait};
+use super::{
+    marketplace::market_config::{market_contract, MarketEvent, MarketOrderIdSource},
+    types::*,
+};
+use crate::ethereum::HexParseTrait;
 use log::info;
 use regex::Regex;
 use web3::types::{Block, Transaction};
@@ -57,16 +60,23 @@ impl TrailsTx for Transaction {
             mime_data: "".to_string(),
             mime_category: InscriptionMimeCategory::Null,
             signature: None,
+            content_hash: None,
             timestamp: block.timestamp.as_u64(),
             verified: InscriptionVerifiedStatus::Unresolved,
+            fail_reason: None,
             event_logs: Vec::new(),
             market_order_id: None,
 
             json: serde_json::Value::Null,
         };
 
-        if MARKET_ADDRESS_LIST.contains(&insc.to) {
-            insc.market_order_id = Some(self.get_order_id_by_market_list());
+        if let Some(market) = market_contract(&insc.to) {
+            insc.market_order_id = match market.order_id_source {
+                MarketOrderIdSource::Tx => Some(self.get_order_id_by_market_list()),
+                MarketOrderIdSource::Event => logs
+                    .and_then(|logs| logs.iter().find_map(|log| market.match_event(log, MarketEvent::List)))
+                    .map(|log| market.order_id(&log)),
+            };
         }
 
         if self.inscription_prepare(&mut insc, logs) {
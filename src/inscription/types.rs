@@ -1,7 +1,9 @@
+use super::webhook::types::WorkerWebhook;
+use crate::{storage_enum, txn_db::codec::StorageCodec};
 use rocksdb::TransactionDB;
 use serde::{Deserialize, Serialize};
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     sync::{Arc, RwLock},
 };
 
@@ -11,33 +13,85 @@ pub const TRANSFER_TX_HEX_LENGTH: usize = 64;
 pub const APP_PROTO_MARKET: &'static str = "market";
 pub const APP_PROTO_COLLECTION: &'static str = "collection";
 
-#[derive(Debug, Serialize, Deserialize, PartialEq)]
-pub enum InscriptionVerifiedStatus {
-    #[serde(rename = "0")]
-    Unresolved,
-    #[serde(rename = "1")]
-    Successful,
-    #[serde(rename = "2")]
-    Failed,
-}
-
-#[derive(Debug, Serialize, Deserialize, PartialEq)]
-pub enum InscriptionMimeCategory {
-    #[serde(rename = "0")]
-    Null,
-    #[serde(rename = "1")]
-    Text,
-    #[serde(rename = "2")]
-    Image,
-    #[serde(rename = "3")]
-    Transfer,
-    #[serde(rename = "4")]
-    Json,
-    #[serde(rename = "5")]
-    Invoke,
+storage_enum! {
+    #[derive(Debug, Clone, PartialEq)]
+    pub enum InscriptionVerifiedStatus {
+        Unresolved = 0,
+        Successful = 1,
+        Failed = 2,
+    }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+/// Why an inscription was marked failed.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum InscriptionFailReason {
+    MimeCategoryInvalid,
+    JsonUnknownProtocol,
+
+    NftContentExists,
+    TransferMimeCategoryInvalid,
+    TransferInscriptionNotFound,
+    TransferHolderMismatch,
+
+    TokenInvalidOperation,
+    TokenInvalidTick,
+    TokenInvalidMax,
+    TokenInvalidLimit,
+    TokenInvalidAmount,
+    TokenExists,
+    TokenNotFound,
+    TokenMintNotStarted,
+    TokenMintLimitExceeded,
+    TokenMintFinished,
+    TokenMintOverflow,
+    TokenMintNotFinished,
+    TokenBalanceInsufficient,
+
+    CollectionInvalidOperation,
+    CollectionInvalidFields,
+    CollectionInvalidItem,
+    CollectionItemNotFound,
+    CollectionItemInCollection,
+    CollectionItemHolderMismatch,
+
+    MarketAddressInvalid,
+    MarketExpireInvalid,
+    MarketEventNotFound,
+    MarketListEventNotFound,
+    MarketOrderNotFound,
+    MarketOrderExists,
+    MarketOrderNotOpen,
+    MarketOrderExpired,
+    MarketOrderIsBid,
+    MarketOrderNotBid,
+    MarketPriceMismatch,
+    MarketPriceInvalid,
+    MarketAmountInvalid,
+    MarketFeeExceedsPrice,
+    MarketBalanceInsufficient,
+    MarketNftNotHeld,
+    MarketNftNotFound,
+    MarketNftNotInCollection,
+    MarketTokenNotFound,
+    MarketCollectionNotFound,
+    MarketOfferTargetMissing,
+    MarketOrderNotOwner,
+}
+
+storage_enum! {
+    #[derive(Debug, Clone, PartialEq)]
+    pub enum InscriptionMimeCategory {
+        Null = 0,
+        Text = 1,
+        Image = 2,
+        Transfer = 3,
+        Json = 4,
+        Invoke = 5,
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Inscription {
     pub id: u64,
     pub from: String,
@@ -56,9 +110,15 @@ pub struct Inscription {
 
     pub verified: InscriptionVerifiedStatus,
 
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fail_reason: Option<InscriptionFailReason>,
+
     #[serde(skip_serializing_if = "Option::is_none")]
     pub signature: Option<String>,
 
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content_hash: Option<String>,
+
     #[serde(skip_serializing, default = "default_value_json")]
     pub json: serde_json::Value,
 }
@@ -78,8 +138,6 @@ pub struct InscriptionToken {
     pub mint_progress: u64,
     pub mint_finished: bool,
 
-    pub market_volume24h: u128,
-    pub market_txs24h: u64,
     pub market_cap: u128,
     pub market_floor_price: u128,
 
@@ -108,6 +166,41 @@ pub struct InscriptionTokenSnapshot {
     pub timestamp: u64,
 }
 
+impl StorageCodec for Inscription {}
+
+impl StorageCodec for InscriptionToken {}
+
+impl InscriptionVerifiedStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            InscriptionVerifiedStatus::Unresolved => "unknown",
+            InscriptionVerifiedStatus::Successful => "success",
+            InscriptionVerifiedStatus::Failed => "fail",
+        }
+    }
+}
+
+impl InscriptionMimeCategory {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            InscriptionMimeCategory::Null => "null",
+            InscriptionMimeCategory::Text => "text",
+            InscriptionMimeCategory::Image => "image",
+            InscriptionMimeCategory::Transfer => "transfer",
+            InscriptionMimeCategory::Json => "json",
+            InscriptionMimeCategory::Invoke => "invoke",
+        }
+    }
+}
+
+impl Inscription {
+    /// Record why the inscription failed, returns false for the `execute_*` functions to pass on.
+    pub fn fail(&mut self, reason: InscriptionFailReason) -> bool {
+        self.fail_reason = Some(reason);
+        false
+    }
+}
+
 fn default_value_json() -> serde_json::Value {
     serde_json::Value::Null
 }
@@ -120,6 +213,7 @@ pub struct Indexer {
     pub db: Arc<RwLock<TransactionDB>>,
     pub worker_sync: Arc<WorkerSync>,
     pub worker_inscribe: Arc<WorkerInscribe>,
+    pub worker_webhook: Arc<WorkerWebhook>,
 }
 
 pub struct  NFTTransfer {
@@ -137,6 +231,10 @@ pub struct InscribeContext {
     pub token_cache: HashMap<String, InscriptionToken>,
     pub token_balance_change: HashMap<String, HashMap<String, i64>>,
     pub token_transfers: Vec<(String, u64)>,
+    /// token amount filled per market order id in the current block
+    pub market_order_fills: HashMap<String, u64>,
+    /// bid order ids accepted in the current block
+    pub market_bid_accepts: HashSet<String>,
 }
 
 pub struct WorkerInscribe {
@@ -0,0 +1,145 @@
+use super::{types::WebhookDelivery, WEBHOOKS};
+use crate::{
+    api::router_stream::event_to_display,
+    global::get_timestamp_ms,
+    inscription::{
+        db::{make_index_key, make_index_key2},
+        events::InscribeEvent,
+    },
+    num_index,
+    txn_db::{codec::StorageCodec, transaction::DBTransaction, DBBase, TxnDB},
+};
+use rocksdb::DBAccess;
+use serde_json::json;
+
+pub const KEY_WEBHOOK_SEQ: &'static str = "webhook_seq";
+pub const KEY_WEBHOOK_OUTBOX: &'static str = "webhook_outbox-due-id";
+pub const KEY_WEBHOOK_DEAD: &'static str = "webhook_dead-id";
+
+pub trait InscribeWebhookDB {
+    fn webhook_get_due(&self, now: u64, count: u64) -> Vec<WebhookDelivery>;
+    fn webhook_get_dead(&self, skip: u64, count: u64) -> Vec<WebhookDelivery>;
+    fn webhook_get_seq(&self) -> u64;
+}
+
+impl<T: DBBase + TxnDB + DBAccess> InscribeWebhookDB for T {
+    /// Queued deliveries due at `now`, earliest first.
+    fn webhook_get_due(&self, now: u64, count: u64) -> Vec<WebhookDelivery> {
+        let prefix = KEY_WEBHOOK_OUTBOX.to_string() + ":";
+        let mut iter = self.iterator(rocksdb::IteratorMode::From(prefix.as_bytes(), rocksdb::Direction::Forward));
+        let mut deliveries = Vec::new();
+
+        while let Some(Ok((key, value))) = iter.next() {
+            if !key.starts_with(prefix.as_bytes()) || deliveries.len() as u64 >= count {
+                break;
+            }
+
+            let delivery = WebhookDelivery::decode(&value);
+            if delivery.due > now {
+                break;
+            }
+            deliveries.push(delivery);
+        }
+        deliveries
+    }
+
+    fn webhook_get_dead(&self, skip: u64, count: u64) -> Vec<WebhookDelivery> {
+        let prefix = KEY_WEBHOOK_DEAD.to_string() + ":";
+        let mut iter = self.iterator(rocksdb::IteratorMode::From(prefix.as_bytes(), rocksdb::Direction::Forward));
+        let mut deliveries = Vec::new();
+
+        while let Some(Ok((key, value))) = iter.next() {
+            if !key.starts_with(prefix.as_bytes()) || deliveries.len() as u64 >= count + skip {
+                break;
+            }
+            deliveries.push(WebhookDelivery::decode(&value));
+        }
+        deliveries.split_off(deliveries.len().min(skip as usize))
+    }
+
+    fn webhook_get_seq(&self) -> u64 {
+        match self.get(KEY_WEBHOOK_SEQ).unwrap() {
+            Some(data) => u64::from_be_bytes(data.as_slice().try_into().unwrap()),
+            None => 0,
+        }
+    }
+}
+
+pub trait InscribeWebhookTxn {
+    fn webhook_enqueue(&self, events: &[InscribeEvent]);
+    fn webhook_outbox_put(&self, delivery: &WebhookDelivery);
+    fn webhook_outbox_delete(&self, delivery: &WebhookDelivery);
+    fn webhook_dead_put(&self, delivery: &WebhookDelivery);
+    fn webhook_dead_delete(&self, delivery: &WebhookDelivery);
+    fn webhook_seq_restore(&self, seq: u64);
+}
+
+impl<'a> InscribeWebhookTxn for DBTransaction<'a> {
+    /// Queue the events for every webhook they match, committed with the block.
+    fn webhook_enqueue(&self, events: &[InscribeEvent]) {
+        let webhooks = WEBHOOKS.read().unwrap();
+        if webhooks.is_empty() || events.is_empty() {
+            return;
+        }
+
+        let mut seq = match self.get(KEY_WEBHOOK_SEQ).unwrap() {
+            Some(data) => u64::from_be_bytes(data.as_slice().try_into().unwrap()),
+            None => 0,
+        };
+        let now = get_timestamp_ms() as u64;
+
+        for event in events {
+            let mut data = None;
+            for webhook in webhooks.values() {
+                if !webhook.filter.matches(event) {
+                    continue;
+                }
+
+                seq += 1;
+                let data = data.get_or_insert_with(|| event_to_display(event));
+                self.webhook_outbox_put(&WebhookDelivery {
+                    id: seq,
+                    webhook: webhook.id.clone(),
+                    event: event.name().to_string(),
+                    payload: json!({ "id": seq, "event": event.name(), "data": data }).to_string(),
+                    attempts: 0,
+                    due: now,
+                    error: "".to_string(),
+                });
+            }
+        }
+
+        self.put(KEY_WEBHOOK_SEQ, seq.to_be_bytes()).unwrap();
+    }
+
+    fn webhook_outbox_put(&self, delivery: &WebhookDelivery) {
+        let key = make_index_key2(KEY_WEBHOOK_OUTBOX, num_index!(delivery.due), num_index!(delivery.id));
+        self.put(key.as_bytes(), delivery.encode()).unwrap();
+    }
+
+    fn webhook_outbox_delete(&self, delivery: &WebhookDelivery) {
+        let key = make_index_key2(KEY_WEBHOOK_OUTBOX, num_index!(delivery.due), num_index!(delivery.id));
+        self.delete(key.as_bytes()).unwrap();
+    }
+
+    fn webhook_dead_put(&self, delivery: &WebhookDelivery) {
+        let key = make_index_key(KEY_WEBHOOK_DEAD, num_index!(delivery.id));
+        self.put(key.as_bytes(), delivery.encode()).unwrap();
+    }
+
+    fn webhook_dead_delete(&self, delivery: &WebhookDelivery) {
+        let key = make_index_key(KEY_WEBHOOK_DEAD, num_index!(delivery.id));
+        self.delete(key.as_bytes()).unwrap();
+    }
+
+    /// Continue the delivery ids after `seq`, a restored checkpoint must not hand out ids receivers already got.
+    fn webhook_seq_restore(&self, seq: u64) {
+        let current = match self.get(KEY_WEBHOOK_SEQ).unwrap() {
+            Some(data) => u64::from_be_bytes(data.as_slice().try_into().unwrap()),
+            None => 0,
+        };
+        if seq > current {
+            self.put(KEY_WEBHOOK_SEQ, seq.to_be_bytes()).unwrap();
+        }
+    }
+}
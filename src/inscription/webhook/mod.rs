@@ -0,0 +1,74 @@
+pub mod db;
+pub mod types;
+pub mod worker;
+
+use super::events::InscribeEventFilter;
+use crate::config::WEBHOOK_CONFIG;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::{
+    collections::HashMap,
+    sync::{Mutex, RwLock},
+};
+
+lazy_static! {
+    /// Registered webhooks by id.
+    pub static ref WEBHOOKS: RwLock<HashMap<String, Webhook>> = RwLock::new(load_webhooks());
+    /// Move the dead letters back to the outbox on the next delivery round.
+    pub static ref WEBHOOK_REDELIVER: Mutex<bool> = Mutex::new(false);
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Webhook {
+    pub id: String,
+    pub url: String,
+    /// key of the HMAC-SHA256 signature header, deliveries are unsigned when empty
+    #[serde(default)]
+    pub secret: String,
+    #[serde(default, flatten)]
+    pub filter: InscribeEventFilter,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WebhookConfig {
+    pub webhooks: Vec<Webhook>,
+}
+
+fn load_webhooks() -> HashMap<String, Webhook> {
+    if WEBHOOK_CONFIG.is_empty() {
+        return HashMap::new();
+    }
+
+    let data = std::fs::read_to_string(WEBHOOK_CONFIG.as_str())
+        .unwrap_or_else(|_| panic!("webhook config not found: {}", *WEBHOOK_CONFIG));
+    let config: WebhookConfig = serde_json::from_str(&data).unwrap();
+    config
+        .webhooks
+        .into_iter()
+        .map(|webhook| (webhook.id.clone(), webhook))
+        .collect()
+}
+
+/// Write the webhooks back to the config file, they are kept in memory only without one.
+pub fn save_webhooks(webhooks: &HashMap<String, Webhook>) {
+    if WEBHOOK_CONFIG.is_empty() {
+        return;
+    }
+
+    let mut list: Vec<Webhook> = webhooks.values().cloned().collect();
+    list.sort_by(|a, b| a.id.cmp(&b.id));
+    let data = serde_json::to_string_pretty(&WebhookConfig { webhooks: list }).unwrap();
+    std::fs::write(WEBHOOK_CONFIG.as_str(), data).unwrap();
+}
+
+pub fn webhooks_enabled() -> bool {
+    !WEBHOOKS.read().unwrap().is_empty()
+}
+
+/// Hex HMAC-SHA256 of a delivery payload.
+pub fn webhook_signature(secret: &str, payload: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).unwrap();
+    mac.update(payload.as_bytes());
+    hex::encode(mac.finalize().into_bytes())
+}
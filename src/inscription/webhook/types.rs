@@ -0,0 +1,25 @@
+use crate::txn_db::codec::StorageCodec;
+use rocksdb::TransactionDB;
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, RwLock};
+
+/// An event queued for one webhook.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookDelivery {
+    pub id: u64,
+    pub webhook: String,
+    pub event: String,
+    pub payload: String,
+    pub attempts: u32,
+    /// unix time in ms the next attempt is due at
+    pub due: u64,
+    /// error of the last failed attempt
+    pub error: String,
+}
+
+impl StorageCodec for WebhookDelivery {}
+
+pub struct WorkerWebhook {
+    pub db: Arc<RwLock<TransactionDB>>,
+    pub client: reqwest::Client,
+}
@@ -0,0 +1,381 @@
+use super::{
+    db::{InscribeWebhookDB, InscribeWebhookTxn},
+    types::{WebhookDelivery, WorkerWebhook},
+    webhook_signature, WEBHOOKS, WEBHOOK_REDELIVER,
+};
+use crate::{
+    global::{get_timestamp_ms, sleep_ms},
+    txn_db::transaction::DBTransaction,
+};
+use log::{error, info, warn};
+use rocksdb::TransactionDB;
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+};
+
+/// Failed attempts before a delivery is moved to the dead-letter list.
+pub const WEBHOOK_MAX_ATTEMPTS: u32 = 10;
+const WEBHOOK_BATCH: u64 = 64;
+const WEBHOOK_TIMEOUT_SECS: u64 = 10;
+const WEBHOOK_BACKOFF_BASE_SECS: u64 = 5;
+const WEBHOOK_BACKOFF_MAX_SECS: u64 = 3600;
+
+/// Delay before the next attempt, doubled with every failed attempt.
+fn webhook_backoff_ms(attempts: u32) -> u64 {
+    let secs = WEBHOOK_BACKOFF_BASE_SECS.saturating_mul(1 << attempts.saturating_sub(1).min(16));
+    secs.min(WEBHOOK_BACKOFF_MAX_SECS) * 1000
+}
+
+impl WorkerWebhook {
+    pub fn new(db: Arc<RwLock<TransactionDB>>) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(std::time::Duration::from_secs(WEBHOOK_TIMEOUT_SECS))
+            .build()
+            .unwrap();
+        WorkerWebhook { db, client }
+    }
+
+    async fn deliver(&self, delivery: &WebhookDelivery) -> Result<(), String> {
+        let webhook = WEBHOOKS.read().unwrap().get(&delivery.webhook).cloned();
+        let webhook = webhook.ok_or("webhook removed".to_string())?;
+
+        let mut request = self
+            .client
+            .post(&webhook.url)
+            .header("Content-Type", "application/json")
+            .header("X-Insdexer-Event", &delivery.event)
+            .header("X-Insdexer-Delivery", delivery.id.to_string())
+            .body(delivery.payload.clone());
+        if !webhook.secret.is_empty() {
+            let signature = webhook_signature(&webhook.secret, &delivery.payload);
+            request = request.header("X-Insdexer-Signature", format!("sha256={}", signature));
+        }
+
+        match request.send().await {
+            Ok(response) if response.status().is_success() => Ok(()),
+            Ok(response) => Err(format!("http status: {}", response.status())),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+
+    /// Attempt the due deliveries, returns false when none was due.
+    pub async fn deliver_due(&self) -> bool {
+        let now = get_timestamp_ms() as u64;
+        let deliveries = self.db.read().unwrap().webhook_get_due(now, WEBHOOK_BATCH);
+        if deliveries.is_empty() {
+            return false;
+        }
+
+        // a webhook failing once in a round is not retried in the same round
+        let mut deferred: HashMap<String, u64> = HashMap::new();
+        // the outbox is updated once for the whole round
+        let mut taken = Vec::new();
+        let mut retries = Vec::new();
+        let mut dead = Vec::new();
+
+        for mut delivery in deliveries {
+            let result = if deferred.contains_key(&delivery.webhook) {
+                None
+            } else {
+                Some(self.deliver(&delivery).await)
+            };
+
+            taken.push(delivery.clone());
+
+            match result {
+                None => {
+                    delivery.due = deferred[&delivery.webhook];
+                    retries.push(delivery);
+                }
+                Some(Ok(())) => {
+                    info!("[webhook] delivered: {} {} {}", delivery.webhook, delivery.event, delivery.id);
+                }
+                Some(Err(error)) => {
+                    delivery.attempts += 1;
+                    delivery.error = error;
+                    let removed = !WEBHOOKS.read().unwrap().contains_key(&delivery.webhook);
+                    if removed || delivery.attempts >= WEBHOOK_MAX_ATTEMPTS {
+                        warn!(
+                            "[webhook] dead letter: {} {} {}, {}",
+                            delivery.webhook, delivery.event, delivery.id, delivery.error
+                        );
+                        dead.push(delivery);
+                    } else {
+                        delivery.due = now + webhook_backoff_ms(delivery.attempts);
+                        warn!(
+                            "[webhook] delivery failed: {} {} {}, attempts: {}, {}",
+                            delivery.webhook, delivery.event, delivery.id, delivery.attempts, delivery.error
+                        );
+                        deferred.insert(delivery.webhook.clone(), delivery.due);
+                        retries.push(delivery);
+                    }
+                }
+            }
+        }
+
+        let db = self.db.write().unwrap();
+        let txn = DBTransaction::new(&db);
+        for delivery in &taken {
+            txn.webhook_outbox_delete(delivery);
+        }
+        for delivery in &retries {
+            txn.webhook_outbox_put(delivery);
+        }
+        for delivery in &dead {
+            txn.webhook_dead_put(delivery);
+        }
+        // the round's deliveries stay in the outbox and are attempted again
+        if let Err(e) = txn.commit() {
+            error!("[webhook] outbox update failed: {}, deliveries: {}", e, taken.len());
+        }
+
+        true
+    }
+
+    /// Move every dead letter back to the outbox with its attempts reset.
+    pub fn redeliver_dead(&self) {
+        let db = self.db.write().unwrap();
+        let deliveries = db.webhook_get_dead(0, u64::MAX);
+        let now = get_timestamp_ms() as u64;
+        let count = deliveries.len();
+        let txn = DBTransaction::new(&db);
+        for mut delivery in deliveries {
+            txn.webhook_dead_delete(&delivery);
+            delivery.attempts = 0;
+            delivery.due = now;
+            delivery.error = "".to_string();
+            txn.webhook_outbox_put(&delivery);
+        }
+        txn.commit().unwrap();
+        info!("[webhook] redeliver dead letters: {}", count);
+    }
+
+    async fn run_deliver(&self) {
+        loop {
+            if std::mem::take(&mut *WEBHOOK_REDELIVER.lock().unwrap()) {
+                self.redeliver_dead();
+            }
+
+            if !self.deliver_due().await {
+                sleep_ms(1000).await;
+            }
+        }
+    }
+
+    pub fn run(arc_self: Arc<Self>) {
+        tokio::spawn(async move {
+            arc_self.run_deliver().await;
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{inscription::webhook::Webhook, txn_db::db::open_test_db};
+    use std::sync::Mutex;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::{TcpListener, TcpStream},
+    };
+
+    /// Headers with lowercase names and the body of a request the stand-in received.
+    type Received = (HashMap<String, String>, String);
+
+    /// A local http server answering every request with `status`, returns its url.
+    async fn stand_in(status: u16) -> (String, Arc<Mutex<Vec<Received>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        let received = Arc::new(Mutex::new(Vec::new()));
+
+        let list = received.clone();
+        tokio::spawn(async move {
+            loop {
+                let (stream, _) = listener.accept().await.unwrap();
+                tokio::spawn(stand_in_serve(stream, status, list.clone()));
+            }
+        });
+        (url, received)
+    }
+
+    async fn stand_in_serve(mut stream: TcpStream, status: u16, received: Arc<Mutex<Vec<Received>>>) {
+        let mut buf = Vec::new();
+        loop {
+            let end = loop {
+                if let Some(pos) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+                    break pos + 4;
+                }
+                let mut chunk = [0u8; 4096];
+                match stream.read(&mut chunk).await {
+                    Ok(0) | Err(_) => return,
+                    Ok(n) => buf.extend_from_slice(&chunk[..n]),
+                }
+            };
+
+            let head = String::from_utf8_lossy(&buf[..end]).to_string();
+            let headers: HashMap<String, String> = head
+                .lines()
+                .skip(1)
+                .filter_map(|line| line.split_once(':'))
+                .map(|(name, value)| (name.trim().to_lowercase(), value.trim().to_string()))
+                .collect();
+            let length: usize = headers.get("content-length").map_or(0, |v| v.parse().unwrap());
+            while buf.len() < end + length {
+                let mut chunk = [0u8; 4096];
+                match stream.read(&mut chunk).await {
+                    Ok(0) | Err(_) => return,
+                    Ok(n) => buf.extend_from_slice(&chunk[..n]),
+                }
+            }
+
+            let body = String::from_utf8_lossy(&buf[end..end + length]).to_string();
+            buf.drain(..end + length);
+            received.lock().unwrap().push((headers, body));
+
+            let response = format!("HTTP/1.1 {} Stand-In\r\ncontent-length: 0\r\n\r\n", status);
+            if stream.write_all(response.as_bytes()).await.is_err() {
+                return;
+            }
+        }
+    }
+
+    fn webhook_add(id: &str, url: &str, secret: &str) {
+        let webhook = Webhook {
+            id: id.to_string(),
+            url: url.to_string(),
+            secret: secret.to_string(),
+            filter: Default::default(),
+        };
+        WEBHOOKS.write().unwrap().insert(id.to_string(), webhook);
+    }
+
+    fn worker_with(name: &str, deliveries: &[WebhookDelivery]) -> WorkerWebhook {
+        let db = open_test_db(name);
+        let txn = DBTransaction::new(&db);
+        for delivery in deliveries {
+            txn.webhook_outbox_put(delivery);
+        }
+        txn.commit().unwrap();
+        WorkerWebhook::new(Arc::new(RwLock::new(db)))
+    }
+
+    fn delivery(id: u64, webhook: &str, payload: &str, attempts: u32) -> WebhookDelivery {
+        WebhookDelivery {
+            id,
+            webhook: webhook.to_string(),
+            event: "inscription".to_string(),
+            payload: payload.to_string(),
+            attempts,
+            due: 0,
+            error: "".to_string(),
+        }
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_the_max() {
+        assert_eq!(webhook_backoff_ms(1), 5_000);
+        assert_eq!(webhook_backoff_ms(2), 10_000);
+        assert_eq!(webhook_backoff_ms(3), 20_000);
+        assert_eq!(webhook_backoff_ms(9), 1_280_000);
+        assert_eq!(webhook_backoff_ms(10), 2_560_000);
+        assert_eq!(webhook_backoff_ms(11), 3_600_000);
+        assert_eq!(webhook_backoff_ms(u32::MAX), 3_600_000);
+    }
+
+    #[tokio::test]
+    async fn deliver_signs_the_payload() {
+        let (url, received) = stand_in(200).await;
+        webhook_add("test-signed", &url, "Jefe");
+        let worker = worker_with(
+            "webhook-signed",
+            &[delivery(7, "test-signed", "what do ya want for nothing?", 0)],
+        );
+
+        assert!(worker.deliver_due().await);
+
+        let received = received.lock().unwrap();
+        assert_eq!(received.len(), 1);
+        let (headers, body) = &received[0];
+        // RFC 4231 test case 2
+        assert_eq!(
+            headers["x-insdexer-signature"],
+            "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+        assert_eq!(headers["x-insdexer-event"], "inscription");
+        assert_eq!(headers["x-insdexer-delivery"], "7");
+        assert_eq!(body, "what do ya want for nothing?");
+
+        let db = worker.db.read().unwrap();
+        assert!(db.webhook_get_due(u64::MAX, 10).is_empty());
+        assert!(db.webhook_get_dead(0, 10).is_empty());
+    }
+
+    #[tokio::test]
+    async fn deliver_without_secret_is_unsigned() {
+        let (url, received) = stand_in(204).await;
+        webhook_add("test-unsigned", &url, "");
+        let worker = worker_with("webhook-unsigned", &[delivery(1, "test-unsigned", "{}", 0)]);
+
+        assert!(worker.deliver_due().await);
+
+        let received = received.lock().unwrap();
+        assert_eq!(received.len(), 1);
+        assert!(!received[0].0.contains_key("x-insdexer-signature"));
+    }
+
+    #[tokio::test]
+    async fn dead_letter_after_max_attempts() {
+        let (url, received) = stand_in(500).await;
+        webhook_add("test-dead", &url, "");
+        let worker = worker_with("webhook-dead", &[delivery(3, "test-dead", "{}", WEBHOOK_MAX_ATTEMPTS - 1)]);
+
+        assert!(worker.deliver_due().await);
+        assert_eq!(received.lock().unwrap().len(), 1);
+
+        let db = worker.db.read().unwrap();
+        assert!(db.webhook_get_due(u64::MAX, 10).is_empty());
+        let dead = db.webhook_get_dead(0, 10);
+        assert_eq!(dead.len(), 1);
+        assert_eq!(dead[0].id, 3);
+        assert_eq!(dead[0].attempts, WEBHOOK_MAX_ATTEMPTS);
+        assert!(dead[0].error.contains("500"));
+    }
+
+    #[tokio::test]
+    async fn failure_defers_the_rest_of_the_round() {
+        let (url, received) = stand_in(503).await;
+        webhook_add("test-defer", &url, "");
+        let (url_ok, received_ok) = stand_in(200).await;
+        webhook_add("test-defer-other", &url_ok, "");
+        let worker = worker_with(
+            "webhook-defer",
+            &[
+                delivery(1, "test-defer", "{\"n\":1}", 0),
+                delivery(2, "test-defer", "{\"n\":2}", 0),
+                delivery(3, "test-defer-other", "{\"n\":3}", 0),
+                delivery(4, "test-defer", "{\"n\":4}", 2),
+            ],
+        );
+
+        let before = get_timestamp_ms() as u64;
+        assert!(worker.deliver_due().await);
+        assert_eq!(received.lock().unwrap().len(), 1);
+        // other webhooks are still delivered
+        assert_eq!(received_ok.lock().unwrap().len(), 1);
+
+        let db = worker.db.read().unwrap();
+        assert!(db.webhook_get_due(before, 10).is_empty());
+        let mut queued = db.webhook_get_due(u64::MAX, 10);
+        queued.sort_by_key(|delivery| delivery.id);
+        assert_eq!(queued.iter().map(|d| d.id).collect::<Vec<_>>(), vec![1, 2, 4]);
+        assert_eq!(queued[0].attempts, 1);
+        assert!(queued[0].due >= before + webhook_backoff_ms(1));
+        // the deferred ones keep their attempts and wait for the failed one
+        assert_eq!(queued[1].attempts, 0);
+        assert_eq!(queued[1].due, queued[0].due);
+        assert_eq!(queued[2].attempts, 2);
+        assert_eq!(queued[2].due, queued[0].due);
+        assert!(db.webhook_get_dead(0, 10).is_empty());
+    }
+}
@@ -2,7 +2,10 @@ use super::{
     db::InscribeDB,
     types::{InscribeContext, Inscription, WorkerInscribe},
 };
-use crate::global::sleep_ms;
+use crate::{
+    global::sleep_ms,
+    metrics::{metrics_inscriptions, INSCRIBED_BLOCK},
+};
 use log::info;
 use rocksdb::TransactionDB;
 use std::sync::{Arc, RwLock};
@@ -40,7 +43,7 @@ impl WorkerInscribe {
             return false;
         }
 
-        let insc = self.db.read().unwrap().get_inscription_by_id(insc_id + 1).unwrap();
+        let insc = self.db.read().unwrap().get_inscription_meta_by_id(insc_id + 1).unwrap();
         let current_blocknumber = insc.blocknumber;
         if current_blocknumber >= sync_blocknumber {
             info!("[indexer] inscribe: wait for new block");
@@ -53,6 +56,8 @@ impl WorkerInscribe {
         context.inscriptions = insc_list;
         context.inscribe();
         context.save();
+        metrics_inscriptions(&context.inscriptions);
+        INSCRIBED_BLOCK.set(current_blocknumber as i64);
 
         return true;
     }
@@ -10,7 +10,9 @@ use crate::{
         WORKER_COUNT,
     },
     ethereum::{init_web3_http, HexParseTrait, Web3Ex},
-    global::{get_timestamp_ms, sleep_ms, ROLLBACK_BLOCK},
+    global::{get_timestamp_ms, sleep_ms, CHECKPOINT_REQUEST, ROLLBACK_BLOCK},
+    metrics::{metrics_sync_state, CHAIN_HEAD_BLOCK, SYNCED_BLOCK},
+    txn_db::transaction::DBTransaction,
 };
 use log::{error, info, warn};
 use rocksdb::TransactionDB;
@@ -94,7 +96,11 @@ impl WorkerSync {
         let state = self.state.clone();
         let db = self.db.clone();
 
-        state.write().unwrap().worker_count += 1;
+        {
+            let mut state = state.write().unwrap();
+            state.worker_count += 1;
+            metrics_sync_state(&state);
+        }
 
         tokio::spawn(async move {
             let web3 = init_web3_http(WEB3_PROVIDER.to_string().as_str());
@@ -128,6 +134,7 @@ impl WorkerSync {
                 }
             }
             state.worker_count -= 1;
+            metrics_sync_state(&state);
             info!(
                 "[sync] fetch block: {}, txs: {}, workers: {}, buff_len: {}",
                 blocknumber,
@@ -142,12 +149,13 @@ impl WorkerSync {
         let mut sync_state = self.state.write().unwrap();
         let block = sync_state.blocks.remove(&blocknumber).unwrap();
         let block_logs = sync_state.event_logs.remove(&blocknumber);
+        metrics_sync_state(&sync_state);
         let db = self.db.write().unwrap();
 
         let mut next_insc_id = db.get_top_inscription_sync_id() + 1;
         let mut inscription_count = 0;
 
-        let txn = db.transaction();
+        let txn = DBTransaction::new(&db);
         for tx in &block.transactions {
             let tx_index = tx.transaction_index.unwrap().as_u64();
             let tx_logs = match &block_logs {
@@ -165,6 +173,7 @@ impl WorkerSync {
         txn.set_sync_blocknumber(blocknumber);
         txn.set_top_inscription_sync_id(next_insc_id - 1);
         txn.commit().unwrap();
+        SYNCED_BLOCK.set(blocknumber as i64);
 
         info!(
             "[sync] save block: {}, txs: {}, ins: {}",
@@ -196,7 +205,8 @@ impl WorkerSync {
             }
 
             if saved {
-                if blocknumber % *CHECKPOINT_SPAN == 0 {
+                let requested = std::mem::take(&mut *CHECKPOINT_REQUEST.lock().unwrap());
+                if blocknumber.is_multiple_of(*CHECKPOINT_SPAN) || requested {
                     make_checkpoint(blocknumber);
                 }
             } else {
@@ -236,6 +246,7 @@ impl WorkerSync {
             let target_blocknumber = latest_blocknumber - *CONFIRM_BLOCK;
 
             self.state.write().unwrap().latest_blocknumber = latest_blocknumber;
+            CHAIN_HEAD_BLOCK.set(latest_blocknumber as i64);
 
             if next_blocknumber > target_blocknumber {
                 info!("[sync] wait for new block: {}", next_blocknumber);
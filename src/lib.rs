@@ -1,3 +1,6 @@
+// db keys and column family names are `&'static str` consts throughout
+#![allow(clippy::redundant_static_lifetimes)]
+
 pub mod adjust_open_files;
 pub mod api;
 pub mod args;
@@ -6,6 +9,7 @@ pub mod ethereum;
 pub mod global;
 pub mod inscription;
 pub mod log;
+pub mod metrics;
 pub mod txn_db;
 
 #[macro_use]
@@ -0,0 +1,71 @@
+use crate::inscription::types::{Inscription, WorkerSyncState};
+use prometheus::{
+    exponential_buckets, register_histogram, register_histogram_vec, register_int_counter, register_int_counter_vec,
+    register_int_gauge, Histogram, HistogramVec, IntCounter, IntCounterVec, IntGauge,
+};
+
+lazy_static! {
+    pub static ref CHAIN_HEAD_BLOCK: IntGauge =
+        register_int_gauge!("insdexer_chain_head_block", "Latest blocknumber of the web3 provider").unwrap();
+    pub static ref SYNCED_BLOCK: IntGauge =
+        register_int_gauge!("insdexer_synced_block", "Latest block saved by the sync worker").unwrap();
+    pub static ref INSCRIBED_BLOCK: IntGauge =
+        register_int_gauge!("insdexer_inscribed_block", "Latest block inscribed by the inscribe worker").unwrap();
+    pub static ref SYNC_BUFFER_LENGTH: IntGauge =
+        register_int_gauge!("insdexer_sync_buffer_length", "Fetched blocks waiting to be saved").unwrap();
+    pub static ref SYNC_WORKERS: IntGauge =
+        register_int_gauge!("insdexer_sync_workers", "Sync workers fetching a block").unwrap();
+    pub static ref RPC_DURATION: HistogramVec = register_histogram_vec!(
+        "insdexer_rpc_duration_seconds",
+        "Duration of web3 provider requests",
+        &["method"],
+        exponential_buckets(0.01, 2.0, 12).unwrap()
+    )
+    .unwrap();
+    pub static ref RPC_ERRORS: IntCounterVec =
+        register_int_counter_vec!("insdexer_rpc_errors_total", "Failed web3 provider requests", &["method"]).unwrap();
+    pub static ref INSCRIPTIONS: IntCounterVec = register_int_counter_vec!(
+        "insdexer_inscriptions_total",
+        "Inscribed inscriptions",
+        &["category", "verified"]
+    )
+    .unwrap();
+    pub static ref CHECKPOINT_DURATION: Histogram = register_histogram!(
+        "insdexer_checkpoint_duration_seconds",
+        "Duration of making a checkpoint",
+        exponential_buckets(0.1, 2.0, 10).unwrap()
+    )
+    .unwrap();
+    pub static ref ROLLBACKS: IntCounter =
+        register_int_counter!("insdexer_rollbacks_total", "Checkpoints restored over the life of the db").unwrap();
+    pub static ref API_REQUEST_DURATION: HistogramVec = register_histogram_vec!(
+        "insdexer_api_request_duration_seconds",
+        "Duration of api requests",
+        &["method", "route", "status"],
+        exponential_buckets(0.001, 2.0, 14).unwrap()
+    )
+    .unwrap();
+}
+
+pub fn metrics_sync_state(state: &WorkerSyncState) {
+    SYNC_WORKERS.set(state.worker_count as i64);
+    SYNC_BUFFER_LENGTH.set(state.blocks.len() as i64);
+}
+
+pub fn metrics_inscriptions(insc_list: &[Inscription]) {
+    for insc in insc_list {
+        INSCRIPTIONS
+            .with_label_values(&[insc.mime_category.as_str(), insc.verified.as_str()])
+            .inc();
+    }
+}
+
+/// Observe a web3 request started at `timer`, counting it as an error when it failed.
+pub fn metrics_rpc<T, E>(method: &str, timer: std::time::Instant, result: &Result<T, E>) {
+    RPC_DURATION
+        .with_label_values(&[method])
+        .observe(timer.elapsed().as_secs_f64());
+    if result.is_err() {
+        RPC_ERRORS.with_label_values(&[method]).inc();
+    }
+}
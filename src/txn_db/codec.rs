@@ -0,0 +1,168 @@
+use serde::{
+    de::{self, DeserializeOwned, Visitor},
+    Serialize,
+};
+
+/// Rows written before the codec existed are plain json objects.
+pub const STORAGE_CODEC_JSON: u8 = 0;
+/// MessagePack with named fields, so fields added with `#[serde(default)]` stay readable.
+pub const STORAGE_CODEC_MSGPACK: u8 = 1;
+
+pub const STORAGE_CODEC_VERSION: u8 = STORAGE_CODEC_MSGPACK;
+
+pub fn storage_codec_version(data: &[u8]) -> u8 {
+    match data.first() {
+        Some(b'{') | None => STORAGE_CODEC_JSON,
+        Some(version) => *version,
+    }
+}
+
+pub trait StorageCodec: Serialize + DeserializeOwned {
+    fn encode(&self) -> Vec<u8> {
+        let mut data = vec![STORAGE_CODEC_VERSION];
+        rmp_serde::encode::write_named(&mut data, self).unwrap();
+        data
+    }
+
+    fn try_decode(data: &[u8]) -> Result<Self, String> {
+        match storage_codec_version(data) {
+            STORAGE_CODEC_JSON => serde_json::from_slice(data).map_err(|e| e.to_string()),
+            STORAGE_CODEC_MSGPACK => rmp_serde::from_slice(&data[1..]).map_err(|e| e.to_string()),
+            version => Err(format!(
+                "unknown storage codec version {}, this version reads up to {}, the db may be written by a newer insdexer",
+                version, STORAGE_CODEC_VERSION
+            )),
+        }
+    }
+
+    fn decode(data: &[u8]) -> Self {
+        Self::try_decode(data).unwrap_or_else(|e| panic!("decode {} failed: {}", std::any::type_name::<Self>(), e))
+    }
+}
+
+/// Reads the discriminant of a `storage_enum!` from a MessagePack integer.
+pub struct StorageEnumVisitor;
+
+impl<'de> Visitor<'de> for StorageEnumVisitor {
+    type Value = u64;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("an enum discriminant")
+    }
+
+    fn visit_u64<E: de::Error>(self, value: u64) -> Result<u64, E> {
+        Ok(value)
+    }
+
+    fn visit_i64<E: de::Error>(self, value: i64) -> Result<u64, E> {
+        u64::try_from(value).map_err(E::custom)
+    }
+}
+
+/// A fieldless enum stored as its discriminant: a numeric string in json, as the rows before the codec and the api
+/// have it, and an integer in MessagePack.
+#[macro_export]
+macro_rules! storage_enum {
+    ($(#[$meta:meta])* $vis:vis enum $name:ident { $($variant:ident = $value:literal,)+ }) => {
+        $(#[$meta])*
+        $vis enum $name {
+            $($variant = $value,)+
+        }
+
+        impl ::serde::Serialize for $name {
+            fn serialize<S: ::serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                let value: u8 = match self {
+                    $($name::$variant => $value,)+
+                };
+                if serializer.is_human_readable() {
+                    serializer.serialize_str(&value.to_string())
+                } else {
+                    serializer.serialize_u8(value)
+                }
+            }
+        }
+
+        impl<'de> ::serde::Deserialize<'de> for $name {
+            fn deserialize<D: ::serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                let value = if deserializer.is_human_readable() {
+                    let value = <String as ::serde::Deserialize>::deserialize(deserializer)?;
+                    value.parse::<u64>().map_err(::serde::de::Error::custom)?
+                } else {
+                    deserializer.deserialize_u64($crate::txn_db::codec::StorageEnumVisitor)?
+                };
+                match value {
+                    $($value => Ok($name::$variant),)+
+                    value => Err(::serde::de::Error::custom(format!("unknown {}: {}", stringify!($name), value))),
+                }
+            }
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    storage_enum! {
+        #[derive(Debug, Clone, Copy, PartialEq)]
+        enum Status {
+            Open = 0,
+            Closed = 2,
+        }
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Row {
+        id: u64,
+        status: Status,
+    }
+
+    impl StorageCodec for Row {}
+
+    fn msgpack_row<T: Serialize>(row: &T) -> Vec<u8> {
+        let mut data = vec![STORAGE_CODEC_MSGPACK];
+        rmp_serde::encode::write_named(&mut data, row).unwrap();
+        data
+    }
+
+    #[test]
+    fn enums_are_integers_in_msgpack() {
+        let row = Row {
+            id: 7,
+            status: Status::Closed,
+        };
+        let data = row.encode();
+        let expected = msgpack_row(&serde_json::json!({ "id": 7, "status": 2 }));
+        assert_eq!(data, expected);
+        assert_eq!(Row::decode(&data), row);
+    }
+
+    #[test]
+    fn enums_are_strings_in_json() {
+        let row = Row {
+            id: 7,
+            status: Status::Closed,
+        };
+        assert_eq!(serde_json::to_string(&row).unwrap(), r#"{"id":7,"status":"2"}"#);
+        assert_eq!(Row::decode(br#"{"id":7,"status":"2"}"#), row);
+    }
+
+    #[test]
+    fn unknown_discriminant_is_an_error() {
+        let data = msgpack_row(&serde_json::json!({ "id": 1, "status": 1 }));
+        assert!(Row::try_decode(&data).unwrap_err().contains("unknown Status: 1"));
+    }
+
+    #[test]
+    fn unknown_version_is_an_error() {
+        let mut data = Row {
+            id: 1,
+            status: Status::Open,
+        }
+        .encode();
+        data[0] = 9;
+        let error = Row::try_decode(&data).unwrap_err();
+        assert!(error.contains("unknown storage codec version 9"), "{}", error);
+    }
+}
@@ -1,23 +1,135 @@
 use super::{DBBase, TxnDB};
-use rocksdb::{DBIteratorWithThreadMode, IteratorMode, TransactionDB, DB};
+use crate::{
+    config::DB_BLOCK_CACHE_SIZE,
+    inscription::db::{key_column_family, COLUMN_FAMILIES},
+};
+use rocksdb::{
+    BlockBasedOptions, Cache, ColumnFamilyDescriptor, DBIteratorWithThreadMode, IteratorMode, Options, ReadOptions,
+    SliceTransform, TransactionDB, TransactionDBOptions, DB,
+};
+
+lazy_static! {
+    static ref DB_BLOCK_CACHE: Cache = Cache::new_lru_cache(*DB_BLOCK_CACHE_SIZE * 1024 * 1024);
+}
+
+/// Keys are `index:key...`, the prefix extractor takes the index name with its ':'.
+fn key_index_prefix(key: &[u8]) -> &[u8] {
+    match key.iter().position(|c| *c == b':') {
+        Some(pos) => &key[..=pos],
+        None => key,
+    }
+}
+
+fn key_index_in_domain(key: &[u8]) -> bool {
+    key.contains(&b':')
+}
+
+/// A seek key with an index name scans that index only, every scan stops at the end of its index anyway. A seek key
+/// without one, such as a bare index name, has no prefix to seek by and scans in total order.
+fn iterator_read_options(key: &[u8]) -> ReadOptions {
+    let mut read_options = ReadOptions::default();
+    if key_index_in_domain(key) {
+        read_options.set_prefix_same_as_start(true);
+    } else {
+        read_options.set_total_order_seek(true);
+    }
+    read_options
+}
+
+pub fn column_family_options() -> Options {
+    let mut block_options = BlockBasedOptions::default();
+    block_options.set_block_cache(&DB_BLOCK_CACHE);
+    block_options.set_bloom_filter(10.0, false);
+    block_options.set_cache_index_and_filter_blocks(true);
+
+    let mut options = Options::default();
+    options.set_prefix_extractor(SliceTransform::create(
+        "key_index_prefix",
+        key_index_prefix,
+        Some(key_index_in_domain),
+    ));
+    options.set_memtable_prefix_bloom_ratio(0.1);
+    options.set_block_based_table_factory(&block_options);
+    options
+}
+
+fn column_family_descriptors() -> Vec<ColumnFamilyDescriptor> {
+    COLUMN_FAMILIES
+        .iter()
+        .map(|name| ColumnFamilyDescriptor::new(*name, column_family_options()))
+        .collect()
+}
+
+pub fn open_txn_db(path: &str) -> TransactionDB {
+    let mut options = Options::default();
+    options.create_if_missing(true);
+    options.create_missing_column_families(true);
+
+    TransactionDB::open_cf_descriptors(&options, &TransactionDBOptions::default(), path, column_family_descriptors()).unwrap()
+}
+
+/// A new empty db under the temp dir.
+#[cfg(test)]
+pub fn open_test_db(name: &str) -> TransactionDB {
+    let path = std::env::temp_dir().join(format!("insdexer-test-{}-{}", std::process::id(), name));
+    let _ = std::fs::remove_dir_all(&path);
+    open_txn_db(path.to_str().unwrap())
+}
+
+/// Opens the column families the db has, a db the indexer has not migrated yet only has the default one.
+pub fn open_read_only(path: &str) -> Result<DB, rocksdb::Error> {
+    let column_families = DB::list_cf(&Options::default(), path)?;
+    let descriptors = column_families.into_iter().map(|name| {
+        let options = if COLUMN_FAMILIES.contains(&name.as_str()) {
+            column_family_options()
+        } else {
+            Options::default()
+        };
+        ColumnFamilyDescriptor::new(name, options)
+    });
+    DB::open_cf_descriptors_read_only(&Options::default(), path, descriptors, false)
+}
+
+pub fn open_secondary<P: AsRef<std::path::Path>>(path: P, secondary_path: P) -> Result<DB, rocksdb::Error> {
+    let options = Options::default();
+    let column_families = DB::list_cf(&options, &path)?;
+    DB::open_cf_as_secondary(&options, path, secondary_path, column_families)
+}
 
 impl DBBase for TransactionDB {
     fn get<K: AsRef<[u8]>>(&self, key: K) -> Result<Option<Vec<u8>>, rocksdb::Error> {
-        TransactionDB::get(self, key)
+        let cf = self.cf_handle(key_column_family(key.as_ref())).unwrap();
+        TransactionDB::get_cf(self, cf, key)
     }
 
     fn iterator(&self, mode: IteratorMode) -> DBIteratorWithThreadMode<'_, Self> {
-        TransactionDB::iterator(self, mode)
+        match mode {
+            IteratorMode::From(key, _) => {
+                let cf = self.cf_handle(key_column_family(key)).unwrap();
+                TransactionDB::iterator_cf_opt(self, cf, iterator_read_options(key), mode)
+            }
+            IteratorMode::Start | IteratorMode::End => panic!("iterator without a seek key has no column family"),
+        }
     }
 }
 
+/// The read-only db falls back to the default column family, where the keys are until the indexer migrates the db.
 impl DBBase for DB {
     fn get<K: AsRef<[u8]>>(&self, key: K) -> Result<Option<Vec<u8>>, rocksdb::Error> {
-        DB::get(self, key)
+        match self.cf_handle(key_column_family(key.as_ref())) {
+            Some(cf) => DB::get_cf(self, cf, key),
+            None => DB::get(self, key),
+        }
     }
 
     fn iterator(&self, mode: IteratorMode) -> DBIteratorWithThreadMode<'_, Self> {
-        DB::iterator(self, mode)
+        match mode {
+            IteratorMode::From(key, _) => match self.cf_handle(key_column_family(key)) {
+                Some(cf) => DB::iterator_cf_opt(self, cf, iterator_read_options(key), mode),
+                None => DB::iterator_opt(self, mode, iterator_read_options(key)),
+            },
+            IteratorMode::Start | IteratorMode::End => panic!("iterator without a seek key has no column family"),
+        }
     }
 }
 
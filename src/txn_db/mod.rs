@@ -1,4 +1,6 @@
+pub mod codec;
 pub mod db;
+pub mod transaction;
 
 use rocksdb::{DBAccess, DBIteratorWithThreadMode};
 
@@ -0,0 +1,37 @@
+use crate::inscription::db::key_column_family;
+use rocksdb::{ColumnFamily, Error, Transaction, TransactionDB};
+
+/// A transaction that routes every key to its column family.
+pub struct DBTransaction<'a> {
+    db: &'a TransactionDB,
+    txn: Transaction<'a, TransactionDB>,
+}
+
+impl<'a> DBTransaction<'a> {
+    pub fn new(db: &'a TransactionDB) -> Self {
+        Self {
+            db,
+            txn: db.transaction(),
+        }
+    }
+
+    fn cf_handle(&self, key: &[u8]) -> &'a ColumnFamily {
+        self.db.cf_handle(key_column_family(key)).unwrap()
+    }
+
+    pub fn get<K: AsRef<[u8]>>(&self, key: K) -> Result<Option<Vec<u8>>, Error> {
+        self.txn.get_cf(self.cf_handle(key.as_ref()), key)
+    }
+
+    pub fn put<K: AsRef<[u8]>, V: AsRef<[u8]>>(&self, key: K, value: V) -> Result<(), Error> {
+        self.txn.put_cf(self.cf_handle(key.as_ref()), key, value)
+    }
+
+    pub fn delete<K: AsRef<[u8]>>(&self, key: K) -> Result<(), Error> {
+        self.txn.delete_cf(self.cf_handle(key.as_ref()), key)
+    }
+
+    pub fn commit(self) -> Result<(), Error> {
+        self.txn.commit()
+    }
+}